@@ -0,0 +1,12 @@
+use ai_functions::ai_function;
+
+#[ai_function]
+pub fn print_auth_code(_code_input_and_auth_contract: &str) {
+  /// INPUT: Takes in the Rust CODE_INPUT of a working actix-web server and an AUTH_CONTRACT
+  /// FUNCTION: Adds user signup, login and logout to the server exactly as the AUTH_CONTRACT describes, keeping every existing route and its behaviour
+  /// IMPORTANT: The following libraries are installed: the ones CODE_INPUT already uses, plus argon2 and uuid. No other external libraries should be used.
+  /// IMPORTANT: The server MUST keep listening on 127.0.0.1 at the port read with std::env::var("PORT"), falling back to 8080 when PORT is not set.
+  /// IMPORTANT: GET / MUST answer with the file static/index.html as text/html, read from disk on every request, and 404 while the file does not exist.
+  /// OUTPUT: Print ONLY the complete new code, nothing else. This function ONLY prints code.
+  println!(OUTPUT)
+}
@@ -9,6 +9,9 @@ pub fn print_backend_webserver_code(_project_description_and_template: &str) {
   /// IMPORTANT: The following libraries are already installed
   ///   reqwest, serde, serde_json, tokio, actix-web, async-trait, actix_cors
   /// No other external libraries should be used. Write functions that fit with the description from the PROJECT_DESCRIPTION
+  /// IMPORTANT: The server MUST listen on 127.0.0.1 at the port read with std::env::var("PORT"), falling back to 8080 when PORT is not set. Never hard-code the port.
+  /// IMPORTANT: GET / MUST answer with the file static/index.html as text/html, read from disk on every request, and 404 while the file does not exist.
+  /// IMPORTANT: If a DATA_ACCESS_LAYER is given it is already saved as src/db.rs. Add `mod db;`, open the database once with db::open("data.sqlite3"), share the connection behind a Mutex in the app data and store everything through its functions instead of JSON files.
   /// OUTPUT: Print ONLY the code, nothing else. This function ONLY prints code.
   println!(OUTPUT)
 }
@@ -22,6 +25,8 @@ pub fn print_improved_webserver_code(_project_description_and_template: &str) {
   ///   3. ONLY writes the code. No commentary.
   /// IMPORTANT: The following libraries are already installed. Does not use ANY libraries other than what was provided in the template
   ///   reqwest, serde, serde_json, tokio, actix-web, async-trait
+  /// IMPORTANT: The server MUST listen on 127.0.0.1 at the port read with std::env::var("PORT"), falling back to 8080 when PORT is not set. Never hard-code the port.
+  /// IMPORTANT: GET / MUST answer with the file static/index.html as text/html, read from disk on every request, and 404 while the file does not exist.
   println!(OUTPUT)
 }
 
@@ -30,6 +35,8 @@ pub fn print_fixed_code(_broken_code_with_bugs: &str) {
   /// INPUT: Takes in Rust BROKEN_CODE and the ERROR_BUGS found
   /// FUNCTION: Removes bugs from code
   /// IMPORTANT: Only prints out the new and improved code. No commentary or anything else
+  /// IMPORTANT: The server MUST listen on 127.0.0.1 at the port read with std::env::var("PORT"), falling back to 8080 when PORT is not set. Never hard-code the port.
+  /// IMPORTANT: GET / MUST answer with the file static/index.html as text/html, read from disk on every request, and 404 while the file does not exist.
   println!(OUTPUT)
 }
 
@@ -43,6 +50,7 @@ pub fn print_rest_api_endpoints(_code_input: &str) {
   ///   "method": This represents the method being called
   ///   "request_body": This represents the body of a post method request
   ///   "response": This represents the output based upon the structs in the code and understanding the functions
+  /// IMPORTANT: Leave out the GET / route that serves static/index.html, it is the frontend page and not part of the api.
   /// IMPORTANT: Only prints out the JSON schema. No commentary or anything else.
   /// MUST READ: All keys are strings. Even bool should be wrapped in double quotes as "bool"
   /// EXAMPLE:
@@ -0,0 +1,38 @@
+use ai_functions::ai_function;
+
+#[ai_function]
+pub fn print_data_model(_project_description: &str) {
+  /// INPUT: Takes in a PROJECT_DESCRIPTION for a website that stores data, and optionally the ERROR_BUGS found in a previous data model
+  /// FUNCTION: Designs the SQLite tables the website's backend needs to store its data
+  /// LOGIC: Lists every table in the order it must be created. Each table has:
+  ///   "name": lowercase snake_case table name, eg. "todo_item", never a Rust keyword or "conn", "row", "connection" or "result"
+  ///   "columns": the columns besides "id". Every table gets an integer "id" primary key automatically
+  /// Each column has:
+  ///   "name": lowercase snake_case column name, never a Rust keyword such as "type"
+  ///   "column_type": one of "integer", "real", "text" or "boolean"
+  ///   "nullable": true if the value can be missing, otherwise false
+  ///   "references": the name of an earlier table whose id this integer column holds, or null
+  /// IMPORTANT: Only prints out the JSON. No commentary or anything else.
+  /// EXAMPLE:
+  /// PROJECT_DESCRIPTION = "build a website where users keep a list of todos"
+  /// PRINTS JSON FORMATTED OUTPUT:
+  /// {
+  ///   "tables": [
+  ///     {
+  ///       "name": "user",
+  ///       "columns": [
+  ///         { "name": "username", "column_type": "text", "nullable": false, "references": null }
+  ///       ]
+  ///     },
+  ///     {
+  ///       "name": "todo",
+  ///       "columns": [
+  ///         { "name": "title", "column_type": "text", "nullable": false, "references": null },
+  ///         { "name": "completed", "column_type": "boolean", "nullable": false, "references": null },
+  ///         { "name": "user_id", "column_type": "integer", "nullable": false, "references": "user" }
+  ///       ]
+  ///     }
+  ///   ]
+  /// }
+  println!(OUTPUT)
+}
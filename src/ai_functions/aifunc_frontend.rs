@@ -0,0 +1,23 @@
+use ai_functions::ai_function;
+
+#[ai_function]
+pub fn print_frontend_code(_project_description_and_api: &str) {
+  /// INPUT: Takes in a PROJECT_DESCRIPTION and the API_ENDPOINTS of the website's backend as a JSON list of routes
+  /// FUNCTION: Writes a single page frontend for the website that lets a user do everything the API_ENDPOINTS allow
+  /// IMPORTANT: Print ONE complete HTML document starting with <!DOCTYPE html>. All CSS goes in an inline <style> and all JavaScript in an inline <script>. Do not load any other local file.
+  /// IMPORTANT: Use plain JavaScript and fetch. No frameworks, no build step, no external libraries.
+  /// IMPORTANT: Only call routes and methods listed in API_ENDPOINTS. Build every url as `${API_BASE}/route` with `const API_BASE = window.API_BASE || "";` so the page works when served by the backend or with API_BASE set.
+  /// IMPORTANT: Send request bodies as JSON with the fields listed in each route's request_body, and show the fields of each response.
+  /// IMPORTANT: If API_ENDPOINTS include /auth routes, add register, login and logout forms, keep the token from /auth/login and send it as "Authorization: Bearer <token>".
+  /// OUTPUT: Print ONLY the HTML, nothing else. This function ONLY prints code.
+  println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_fixed_frontend_code(_broken_page_with_bugs: &str) {
+  /// INPUT: Takes in an HTML BROKEN_PAGE, the API_ENDPOINTS it must use and the ERROR_BUGS found when checking it
+  /// FUNCTION: Removes the bugs from the page
+  /// IMPORTANT: Print ONE complete HTML document with inline <style> and <script>, calling only routes and methods listed in API_ENDPOINTS through `${API_BASE}/route`
+  /// IMPORTANT: Only prints out the new and improved page. No commentary or anything else
+  println!(OUTPUT)
+}
@@ -0,0 +1,12 @@
+use ai_functions::ai_function;
+
+#[ai_function]
+pub fn print_handler_tests(_code_input_and_api_endpoints: &str) {
+  /// INPUT: Takes in the Rust CODE_INPUT of an actix-web server's main.rs and its API_ENDPOINTS as a JSON list of routes
+  /// FUNCTION: Writes a Rust test module for the server's handlers. The module is a child of main.rs, declared with #[cfg(test)], so it starts with `use super::*;`
+  /// IMPORTANT: Write one #[actix_web::test] per route in API_ENDPOINTS. Build the App the same way main does, with actix_web::test::init_service, and send actix_web::test::TestRequest with actix_web::test::call_service. Never bind a port or use the network.
+  /// IMPORTANT: Send request bodies with the fields in each route's request_body, create a record first for routes with a {param}, assert a 2xx status and that the JSON response has the fields in the route's response.
+  /// IMPORTANT: Only use actix-web, serde_json and what CODE_INPUT already uses. If PREVIOUS_TESTS and ERROR_BUGS are given, fix those tests instead.
+  /// OUTPUT: Print ONLY the code of the module, nothing else. This function ONLY prints code.
+  println!(OUTPUT)
+}
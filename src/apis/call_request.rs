@@ -1,77 +1,39 @@
-use crate::models::general::llm::{APIResponse, ChatCompletion, Message};
+use crate::apis::llm_provider::{build_provider, LlmConfig, LlmProvider};
+use crate::apis::transcript::{self, TranscriptConfig, TranscriptMode};
+use crate::models::general::error::AutoGippityError;
+use crate::models::general::llm::Message;
 use dotenv::dotenv;
-use reqwest::Client;
-use std::env;
 
-use reqwest::header::{HeaderMap, HeaderValue};
-
-// call LLM ie gpt-4
-pub async fn call_gpt(messages: Vec<Message>) -> Result<String, Box<dyn std::error::Error + Send>> {
+// call LLM ie gpt-4, using whichever provider is configured
+pub async fn call_gpt(
+  messages: Vec<Message>,
+  config: &LlmConfig,
+  transcript_config: &TranscriptConfig,
+) -> Result<String, AutoGippityError> {
   dotenv().ok();
 
-  let api_key: String = env::var("OPEN_AI_KEY").expect("OPEN_AI_KEY not found in env varibales");
-  let api_org: String = env::var("OPEN_AI_ORG").expect("OPEN_AI_ORG not found in env varibales");
-
-  let url: &str = "https://api.openai.com/v1/chat/completions";
-
-  // create headers
-  let mut headers: HeaderMap = HeaderMap::new();
-
-  // create api key header
-  headers.insert(
-    "authorization",
-    HeaderValue::from_str(&format!("Bearer {}", api_key))
-      .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?,
-  );
-
-  // create open ai org
-  headers.insert(
-    "OpenAI-Organization",
-    HeaderValue::from_str(&format!("{}", api_org.as_str()))
-      .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?,
-  );
-
-  let client = Client::builder()
-    .default_headers(headers)
-    .build()
-    .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?;
-
-  // create chat completion
-  let chat_completion: ChatCompletion = ChatCompletion {
-    model: "gpt-4".to_string(),
-    messages,
-    temperature: 0.1,
-  };
+  // serve from cassette when replaying
+  if transcript_config.mode == TranscriptMode::Replay {
+    return transcript::replay(transcript_config, &messages);
+  }
 
-  // ToubleShooting
-  // let res_raw = client
-  // .post(url)
-  // .json(&chat_completion)
-  // .send()
-  // .await
-  // .unwrap();
+  let provider: Box<dyn LlmProvider> = build_provider(config)?;
 
-  // dbg!(res_raw.text().await.unwrap());
+  let response: String = provider.chat(messages.clone()).await?;
 
-  // extract api response
-  let res: APIResponse = client
-    .post(url)
-    .json(&chat_completion)
-    .send()
-    .await
-    .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?
-    .json()
-    .await
-    .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?;
+  // keep a copy for offline runs
+  if transcript_config.mode == TranscriptMode::Record {
+    transcript::record(transcript_config, &messages, &response)?;
+  }
 
   // send response
-
-  Ok(res.choices[0].message.content.clone())
+  Ok(response)
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::models::general::config::LlmSettings;
 
   #[tokio::test]
   async fn test_call_to_openai() {
@@ -82,15 +44,16 @@ mod tests {
 
     let messages: Vec<Message> = vec![message];
 
-    let response = call_gpt(messages).await;
+    let settings: LlmSettings = LlmSettings::test_cassette();
+    let config: LlmConfig = settings.llm_config().unwrap();
+    let response = call_gpt(messages, &config, &settings.transcript_config()).await;
 
     match response {
       Ok(res_str) => {
         dbg!(res_str);
-        assert!(true)
       }
-      Err(_) => {
-        assert!(false);
+      Err(e) => {
+        panic!("call to llm failed: {}", e);
       }
     }
   }
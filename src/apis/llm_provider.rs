@@ -0,0 +1,423 @@
+use crate::models::general::error::AutoGippityError;
+use crate::models::general::llm::{
+  APIResponse, AnthropicRequest, AnthropicResponse, ChatCompletion, Message, OllamaChatRequest,
+  OllamaChatResponse, OllamaOptions,
+};
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProviderKind {
+  OpenAi,
+  Anthropic,
+  Ollama,
+  LlamaCpp,
+}
+
+impl ProviderKind {
+  fn default_base_url(&self) -> &'static str {
+    match self {
+      Self::OpenAi => "https://api.openai.com/v1",
+      Self::Anthropic => "https://api.anthropic.com/v1",
+      Self::Ollama => "http://localhost:11434",
+      Self::LlamaCpp => "http://localhost:8081/v1",
+    }
+  }
+
+  fn default_model(&self) -> &'static str {
+    match self {
+      Self::OpenAi => "gpt-4",
+      Self::Anthropic => "claude-3-5-sonnet-20240620",
+      Self::Ollama => "llama3",
+      Self::LlamaCpp => "local-model",
+    }
+  }
+}
+
+impl FromStr for ProviderKind {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_lowercase().as_str() {
+      "openai" | "open_ai" => Ok(Self::OpenAi),
+      "anthropic" | "claude" => Ok(Self::Anthropic),
+      "ollama" | "local" => Ok(Self::Ollama),
+      "llamacpp" | "llama.cpp" | "llama_cpp" => Ok(Self::LlamaCpp),
+      other => Err(format!(
+        "unknown LLM provider '{}', expected one of openai, anthropic, ollama, llamacpp",
+        other
+      )),
+    }
+  }
+}
+
+// settings needed to talk to a single llm backend
+#[derive(Debug, Clone, PartialEq)]
+pub struct LlmConfig {
+  pub provider: ProviderKind,
+  pub base_url: String,
+  pub model: String,
+  pub temperature: f32,
+  pub api_key: Option<String>,
+  pub organization: Option<String>,
+}
+
+impl LlmConfig {
+  pub fn new(provider: ProviderKind) -> Self {
+    Self {
+      provider,
+      base_url: provider.default_base_url().to_string(),
+      model: provider.default_model().to_string(),
+      temperature: 0.1,
+      api_key: None,
+      organization: None,
+    }
+  }
+
+  // api keys from the environment, keeping the original OPEN_AI_* names for openai
+  pub fn with_env_credentials(mut self) -> Self {
+    self.api_key = match self.provider {
+      ProviderKind::OpenAi => env::var("OPEN_AI_KEY").ok(),
+      ProviderKind::Anthropic => env::var("ANTHROPIC_API_KEY").ok(),
+      ProviderKind::Ollama | ProviderKind::LlamaCpp => env::var("LLM_API_KEY").ok(),
+    };
+    if self.provider == ProviderKind::OpenAi {
+      self.organization = env::var("OPEN_AI_ORG").ok().filter(|org| !org.is_empty());
+    }
+    self
+  }
+}
+
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+  async fn chat(&self, messages: Vec<Message>) -> Result<String, AutoGippityError>;
+}
+
+fn build_client(headers: HeaderMap) -> Result<Client, AutoGippityError> {
+  Client::builder()
+    .default_headers(headers)
+    .build()
+    .map_err(|e| AutoGippityError::Config(e.to_string()))
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, AutoGippityError> {
+  HeaderValue::from_str(value).map_err(|e| AutoGippityError::Config(e.to_string()))
+}
+
+// send a request, surfacing error statuses before trying to decode the body
+async fn send_json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, AutoGippityError> {
+  let res: Response = request.send().await?;
+  let status: u16 = res.status().as_u16();
+
+  // only the delay-seconds form of Retry-After is honoured
+  let retry_after: Option<Duration> = res
+    .headers()
+    .get("retry-after")
+    .and_then(|value| value.to_str().ok())
+    .and_then(|value| value.trim().parse::<u64>().ok())
+    .map(Duration::from_secs);
+
+  let body: String = res.text().await?;
+
+  if !(200..300).contains(&status) {
+    return Err(AutoGippityError::Provider {
+      status,
+      message: body,
+      retry_after,
+    });
+  }
+
+  serde_json::from_str(&body)
+    .map_err(|e| AutoGippityError::Decode(format!("unexpected llm response body: {}", e)))
+}
+
+// openai chat completions, also spoken by llama.cpp and most hosted gateways
+pub struct OpenAiProvider {
+  client: Client,
+  url: String,
+  model: String,
+  temperature: f32,
+}
+
+impl OpenAiProvider {
+  pub fn new(config: &LlmConfig) -> Result<Self, AutoGippityError> {
+    let mut headers: HeaderMap = HeaderMap::new();
+
+    match (&config.api_key, config.provider) {
+      (Some(api_key), _) => {
+        headers.insert(
+          "authorization",
+          header_value(&format!("Bearer {}", api_key))?,
+        );
+      }
+      (None, ProviderKind::OpenAi) => {
+        return Err(AutoGippityError::Config(
+          "OPEN_AI_KEY not found in env variables".to_string(),
+        ))
+      }
+      (None, _) => {}
+    }
+
+    if let Some(org) = &config.organization {
+      headers.insert("OpenAI-Organization", header_value(org)?);
+    }
+
+    Ok(Self {
+      client: build_client(headers)?,
+      url: format!("{}/chat/completions", config.base_url.trim_end_matches('/')),
+      model: config.model.clone(),
+      temperature: config.temperature,
+    })
+  }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+  async fn chat(&self, messages: Vec<Message>) -> Result<String, AutoGippityError> {
+    let chat_completion: ChatCompletion = ChatCompletion {
+      model: self.model.clone(),
+      messages,
+      temperature: self.temperature,
+    };
+
+    let res: APIResponse = send_json(self.client.post(&self.url).json(&chat_completion)).await?;
+
+    res
+      .choices
+      .into_iter()
+      .next()
+      .map(|choice| choice.message.content)
+      .ok_or_else(|| AutoGippityError::Decode("LLM response contained no choices".to_string()))
+  }
+}
+
+// anthropic messages api
+pub struct AnthropicProvider {
+  client: Client,
+  url: String,
+  model: String,
+  temperature: f32,
+}
+
+impl AnthropicProvider {
+  pub fn new(config: &LlmConfig) -> Result<Self, AutoGippityError> {
+    let api_key: &String = config.api_key.as_ref().ok_or_else(|| {
+      AutoGippityError::Config("ANTHROPIC_API_KEY not found in env variables".to_string())
+    })?;
+
+    let mut headers: HeaderMap = HeaderMap::new();
+    headers.insert("x-api-key", header_value(api_key)?);
+    headers.insert("anthropic-version", header_value("2023-06-01")?);
+
+    Ok(Self {
+      client: build_client(headers)?,
+      url: format!("{}/messages", config.base_url.trim_end_matches('/')),
+      model: config.model.clone(),
+      temperature: config.temperature,
+    })
+  }
+
+  // anthropic takes the system prompt separately and the turns must open with a user message
+  fn split_system(messages: Vec<Message>) -> (Option<String>, Vec<Message>) {
+    let (system, rest): (Vec<Message>, Vec<Message>) =
+      messages.into_iter().partition(|msg| msg.role == "system");
+
+    let system_prompt: String = system
+      .into_iter()
+      .map(|msg| msg.content)
+      .collect::<Vec<String>>()
+      .join("\n");
+
+    // a repair retry follows the system prompt with an assistant turn, so the prompt becomes the user turn
+    if rest.first().is_none_or(|msg| msg.role != "user") {
+      let user_msg: Message = Message {
+        role: "user".to_string(),
+        content: system_prompt,
+      };
+      return (None, [vec![user_msg], rest].concat());
+    }
+
+    let system_prompt: Option<String> = Some(system_prompt).filter(|s| !s.is_empty());
+    (system_prompt, rest)
+  }
+}
+
+#[async_trait]
+impl LlmProvider for AnthropicProvider {
+  async fn chat(&self, messages: Vec<Message>) -> Result<String, AutoGippityError> {
+    let (system, messages) = Self::split_system(messages);
+
+    let request: AnthropicRequest = AnthropicRequest {
+      model: self.model.clone(),
+      system,
+      messages,
+      max_tokens: 4096,
+      temperature: self.temperature,
+    };
+
+    let res: AnthropicResponse = send_json(self.client.post(&self.url).json(&request)).await?;
+
+    if res.content.is_empty() {
+      return Err(AutoGippityError::Decode(
+        "LLM response contained no content blocks".to_string(),
+      ));
+    }
+
+    Ok(
+      res
+        .content
+        .into_iter()
+        .map(|block| block.text)
+        .collect::<Vec<String>>()
+        .join(""),
+    )
+  }
+}
+
+// ollama native chat api for locally hosted models
+pub struct OllamaProvider {
+  client: Client,
+  url: String,
+  model: String,
+  temperature: f32,
+}
+
+impl OllamaProvider {
+  pub fn new(config: &LlmConfig) -> Result<Self, AutoGippityError> {
+    let mut headers: HeaderMap = HeaderMap::new();
+    if let Some(api_key) = &config.api_key {
+      headers.insert(
+        "authorization",
+        header_value(&format!("Bearer {}", api_key))?,
+      );
+    }
+
+    Ok(Self {
+      client: build_client(headers)?,
+      url: format!("{}/api/chat", config.base_url.trim_end_matches('/')),
+      model: config.model.clone(),
+      temperature: config.temperature,
+    })
+  }
+}
+
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+  async fn chat(&self, messages: Vec<Message>) -> Result<String, AutoGippityError> {
+    let request: OllamaChatRequest = OllamaChatRequest {
+      model: self.model.clone(),
+      messages,
+      stream: false,
+      options: OllamaOptions {
+        temperature: self.temperature,
+      },
+    };
+
+    let res: OllamaChatResponse = send_json(self.client.post(&self.url).json(&request)).await?;
+
+    Ok(res.message.content)
+  }
+}
+
+pub fn build_provider(config: &LlmConfig) -> Result<Box<dyn LlmProvider>, AutoGippityError> {
+  let provider: Box<dyn LlmProvider> = match config.provider {
+    ProviderKind::OpenAi | ProviderKind::LlamaCpp => Box::new(OpenAiProvider::new(config)?),
+    ProviderKind::Anthropic => Box::new(AnthropicProvider::new(config)?),
+    ProviderKind::Ollama => Box::new(OllamaProvider::new(config)?),
+  };
+  Ok(provider)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::helpers::general::{extend_ai_function, push_repair_turns};
+
+  fn print_thing(_input: &str) -> &'static str {
+    "print_thing"
+  }
+
+  #[test]
+  fn test_parses_provider_names() {
+    assert_eq!(
+      ProviderKind::from_str("OpenAI").unwrap(),
+      ProviderKind::OpenAi
+    );
+    assert_eq!(
+      ProviderKind::from_str("claude").unwrap(),
+      ProviderKind::Anthropic
+    );
+    assert_eq!(
+      ProviderKind::from_str("local").unwrap(),
+      ProviderKind::Ollama
+    );
+    assert_eq!(
+      ProviderKind::from_str("llama.cpp").unwrap(),
+      ProviderKind::LlamaCpp
+    );
+    assert!(ProviderKind::from_str("bard").is_err());
+  }
+
+  #[test]
+  fn test_anthropic_moves_system_prompt() {
+    let messages: Vec<Message> = vec![
+      Message {
+        role: "system".to_string(),
+        content: "FUNCTION print_thing".to_string(),
+      },
+      Message {
+        role: "user".to_string(),
+        content: "thing".to_string(),
+      },
+    ];
+
+    let (system, rest) = AnthropicProvider::split_system(messages);
+    assert_eq!(system, Some("FUNCTION print_thing".to_string()));
+    assert_eq!(rest.len(), 1);
+    assert_eq!(rest[0].role, "user");
+  }
+
+  #[test]
+  fn test_anthropic_promotes_lone_system_prompt() {
+    let messages: Vec<Message> = vec![Message {
+      role: "system".to_string(),
+      content: "FUNCTION print_thing".to_string(),
+    }];
+
+    let (system, rest) = AnthropicProvider::split_system(messages);
+    assert_eq!(system, None);
+    assert_eq!(rest[0].role, "user");
+    assert_eq!(rest[0].content, "FUNCTION print_thing");
+  }
+
+  #[test]
+  fn test_anthropic_repair_turns_open_with_user() {
+    let mut messages: Vec<Message> = vec![extend_ai_function(print_thing, "thing")];
+    push_repair_turns(
+      &mut messages,
+      "not json".to_string(),
+      &["invalid JSON: expected value".to_string()],
+      &serde_json::json!({ "type": "object" }),
+    );
+
+    let (system, rest) = AnthropicProvider::split_system(messages);
+    assert_eq!(system, None);
+    let roles: Vec<&str> = rest.iter().map(|msg| msg.role.as_str()).collect();
+    assert_eq!(roles, vec!["user", "assistant", "user"]);
+    assert!(rest[0].content.starts_with("FUNCTION print_thing"));
+  }
+
+  #[test]
+  fn test_openai_requires_api_key() {
+    let config: LlmConfig = LlmConfig::new(ProviderKind::OpenAi);
+    assert!(OpenAiProvider::new(&config).is_err());
+
+    let local: LlmConfig = LlmConfig::new(ProviderKind::LlamaCpp);
+    assert!(build_provider(&local).is_ok());
+  }
+}
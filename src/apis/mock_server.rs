@@ -0,0 +1,443 @@
+use crate::apis::llm_provider::{LlmConfig, ProviderKind};
+use crate::models::general::config::{Config, LlmSettings};
+use crate::models::general::llm::ChatCompletion;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+// key used for requests that are not wrapped in an ai function
+pub const PLAIN_CHAT: &str = "chat";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockReply {
+  // well formed chat completion carrying this content
+  Content(String),
+  // error status with an optional Retry-After header in seconds
+  Status(u16, Option<u64>),
+  // http 200 with a raw body, eg. something that is not an APIResponse
+  Raw(String),
+}
+
+type Scripts = Arc<Mutex<HashMap<String, VecDeque<MockReply>>>>;
+type CallLog = Arc<Mutex<Vec<String>>>;
+
+// in-process server speaking the /v1/chat/completions shape call_gpt expects
+pub struct MockLlmServer {
+  address: String,
+  scripts: Scripts,
+  calls: CallLog,
+  handle: JoinHandle<()>,
+}
+
+impl MockLlmServer {
+  pub async fn start() -> Self {
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0")
+      .await
+      .expect("failed to bind mock llm server");
+    let address: String = listener.local_addr().unwrap().to_string();
+
+    let scripts: Scripts = Arc::new(Mutex::new(HashMap::new()));
+    let calls: CallLog = Arc::new(Mutex::new(vec![]));
+
+    let server_scripts: Scripts = scripts.clone();
+    let server_calls: CallLog = calls.clone();
+    let handle: JoinHandle<()> = tokio::spawn(async move {
+      while let Ok((stream, _)) = listener.accept().await {
+        let scripts: Scripts = server_scripts.clone();
+        let calls: CallLog = server_calls.clone();
+        tokio::spawn(async move {
+          let _ = handle_connection(stream, scripts, calls).await;
+        });
+      }
+    });
+
+    Self {
+      address,
+      scripts,
+      calls,
+      handle,
+    }
+  }
+
+  // queue replies for an ai function, the last reply keeps being served once the queue drains
+  pub fn on(&self, function_name: &str, replies: Vec<MockReply>) {
+    self
+      .scripts
+      .lock()
+      .unwrap()
+      .entry(function_name.to_string())
+      .or_default()
+      .extend(replies);
+  }
+
+  pub fn url(&self, path: &str) -> String {
+    format!("http://{}{}", self.address, path)
+  }
+
+  pub fn llm_config(&self) -> LlmConfig {
+    let mut config: LlmConfig = LlmConfig::new(ProviderKind::OpenAi);
+    config.base_url = self.url("/v1");
+    config.api_key = Some("mock-key".to_string());
+    config
+  }
+
+  // settings for agents under test, llama.cpp speaks the same api and needs no key
+  pub fn llm_settings(&self) -> LlmSettings {
+    LlmSettings {
+      provider: "llamacpp".to_string(),
+      base_url: Some(self.url("/v1")),
+      ..LlmSettings::default()
+    }
+  }
+
+  // default config with every llm call going to this server
+  pub fn config(&self) -> Config {
+    Config {
+      llm: self.llm_settings(),
+      ..Config::default()
+    }
+  }
+
+  pub fn calls(&self, function_name: &str) -> usize {
+    self
+      .calls
+      .lock()
+      .unwrap()
+      .iter()
+      .filter(|name| name.as_str() == function_name)
+      .count()
+  }
+}
+
+impl Drop for MockLlmServer {
+  fn drop(&mut self) {
+    self.handle.abort();
+  }
+}
+
+// ai function prompts start with "FUNCTION pub fn <name>(...", rustfmt may break the line after fn
+pub fn function_name_from_prompt(content: &str) -> String {
+  let Some(signature) = content
+    .strip_prefix("FUNCTION")
+    .and_then(|rest| rest.trim_start().strip_prefix("pub fn"))
+  else {
+    return PLAIN_CHAT.to_string();
+  };
+
+  let name: String = signature
+    .trim_start()
+    .chars()
+    .take_while(|c| c.is_alphanumeric() || *c == '_')
+    .collect();
+
+  if name.is_empty() {
+    return PLAIN_CHAT.to_string();
+  }
+  name
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+  match status {
+    200 => "OK",
+    201 => "Created",
+    400 => "Bad Request",
+    401 => "Unauthorized",
+    404 => "Not Found",
+    429 => "Too Many Requests",
+    500 => "Internal Server Error",
+    502 => "Bad Gateway",
+    503 => "Service Unavailable",
+    _ => "Unknown",
+  }
+}
+
+pub async fn write_response(
+  stream: &mut TcpStream,
+  status: u16,
+  extra_headers: &str,
+  body: &str,
+) -> std::io::Result<()> {
+  let response: String = format!(
+    "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+    status,
+    reason_phrase(status),
+    body.len(),
+    extra_headers,
+    body
+  );
+  stream.write_all(response.as_bytes()).await?;
+  stream.shutdown().await
+}
+
+// method, path, headers and body of one request
+pub async fn read_request(stream: &mut TcpStream) -> std::io::Result<(String, String, String, String)> {
+  let mut buffer: Vec<u8> = vec![];
+  let mut chunk: [u8; 4096] = [0; 4096];
+
+  // read until the end of the headers
+  let header_end: usize = loop {
+    let read: usize = stream.read(&mut chunk).await?;
+    if read == 0 {
+      return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    buffer.extend_from_slice(&chunk[..read]);
+    if let Some(pos) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+      break pos + 4;
+    }
+  };
+
+  let head: String = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+  let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+  let method: String = request_line.next().unwrap_or_default().to_string();
+  let path: String = request_line.next().unwrap_or_default().to_string();
+
+  let content_length: usize = head
+    .lines()
+    .filter_map(|line| line.split_once(':'))
+    .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+    .and_then(|(_, value)| value.trim().parse().ok())
+    .unwrap_or(0);
+
+  while buffer.len() < header_end + content_length {
+    let read: usize = stream.read(&mut chunk).await?;
+    if read == 0 {
+      break;
+    }
+    buffer.extend_from_slice(&chunk[..read]);
+  }
+
+  let body: String = String::from_utf8_lossy(&buffer[header_end..]).to_string();
+  Ok((method, path, head, body))
+}
+
+async fn handle_connection(
+  mut stream: TcpStream,
+  scripts: Scripts,
+  calls: CallLog,
+) -> std::io::Result<()> {
+  let (method, path, _, body) = read_request(&mut stream).await?;
+
+  if method != "POST" || path != "/v1/chat/completions" {
+    return write_response(&mut stream, 404, "", r#"{"error":"not found"}"#).await;
+  }
+
+  let function_name: String = match serde_json::from_str::<ChatCompletion>(&body) {
+    Ok(completion) => completion
+      .messages
+      .first()
+      .map(|msg| function_name_from_prompt(&msg.content))
+      .unwrap_or_else(|| PLAIN_CHAT.to_string()),
+    Err(_) => return write_response(&mut stream, 400, "", r#"{"error":"bad request"}"#).await,
+  };
+  calls.lock().unwrap().push(function_name.clone());
+
+  let reply: Option<MockReply> = {
+    let mut scripts = scripts.lock().unwrap();
+    scripts.get_mut(&function_name).and_then(|queue| {
+      if queue.len() > 1 {
+        queue.pop_front()
+      } else {
+        queue.front().cloned()
+      }
+    })
+  };
+
+  match reply {
+    Some(MockReply::Content(content)) => {
+      let body: String = serde_json::json!({
+        "choices": [{ "message": { "role": "assistant", "content": content } }]
+      })
+      .to_string();
+      write_response(&mut stream, 200, "", &body).await
+    }
+    Some(MockReply::Status(status, retry_after)) => {
+      let headers: String = retry_after
+        .map(|secs| format!("Retry-After: {}\r\n", secs))
+        .unwrap_or_default();
+      let body: String = serde_json::json!({
+        "error": { "message": format!("mock error {}", status) }
+      })
+      .to_string();
+      write_response(&mut stream, status, &headers, &body).await
+    }
+    Some(MockReply::Raw(raw)) => write_response(&mut stream, 200, "", &raw).await,
+    None => {
+      let body: String = format!(r#"{{"error":"no script for {}"}}"#, function_name);
+      write_response(&mut stream, 404, "", &body).await
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ai_functions::aifunc_backend::print_improved_webserver_code;
+  use crate::ai_functions::aifunc_managing::convert_user_input_to_goal;
+  use crate::apis::call_request::call_gpt;
+  use crate::apis::transcript::TranscriptConfig;
+  use crate::helpers::general::{ai_task_request, extend_ai_function};
+  use crate::models::general::error::AutoGippityError;
+  use crate::models::general::llm::Message;
+
+  #[test]
+  fn test_extracts_function_name() {
+    let msg: Message = extend_ai_function(convert_user_input_to_goal, "todo app");
+    assert_eq!(
+      function_name_from_prompt(&msg.content),
+      "convert_user_input_to_goal"
+    );
+    assert_eq!(function_name_from_prompt("Hi there"), PLAIN_CHAT);
+
+    let msg: Message = extend_ai_function(print_improved_webserver_code, "fn main() {}");
+    assert_eq!(
+      function_name_from_prompt(&msg.content),
+      "print_improved_webserver_code"
+    );
+  }
+
+  #[tokio::test]
+  async fn test_serves_scripted_chat() {
+    let server: MockLlmServer = MockLlmServer::start().await;
+    server.on(
+      PLAIN_CHAT,
+      vec![MockReply::Content("hello from mock".to_string())],
+    );
+
+    let messages: Vec<Message> = vec![Message {
+      role: "user".to_string(),
+      content: "Hi there".to_string(),
+    }];
+
+    let res: String = call_gpt(messages, &server.llm_config(), &TranscriptConfig::default())
+      .await
+      .unwrap();
+    assert_eq!(res, "hello from mock");
+    assert_eq!(server.calls(PLAIN_CHAT), 1);
+  }
+
+  #[tokio::test]
+  async fn test_retries_after_rate_limit() {
+    let server: MockLlmServer = MockLlmServer::start().await;
+    server.on(
+      "convert_user_input_to_goal",
+      vec![
+        MockReply::Status(429, Some(0)),
+        MockReply::Content("build a website that tracks todos".to_string()),
+      ],
+    );
+
+    let res: String = ai_task_request(
+        "todo app".to_string(),
+        "Managing Agent",
+        "Defining user requirements",
+        convert_user_input_to_goal,
+        &server.llm_settings(),
+      )
+      .await
+      .unwrap();
+
+    assert_eq!(res, "build a website that tracks todos");
+    assert_eq!(server.calls("convert_user_input_to_goal"), 2);
+  }
+
+  #[tokio::test]
+  async fn test_does_not_retry_malformed_body() {
+    let server: MockLlmServer = MockLlmServer::start().await;
+    server.on(
+      "convert_user_input_to_goal",
+      vec![
+        MockReply::Raw("{\"choices\": [".to_string()),
+        MockReply::Content("build a website that tracks todos".to_string()),
+      ],
+    );
+
+    let res: Result<String, AutoGippityError> = ai_task_request(
+        "todo app".to_string(),
+        "Managing Agent",
+        "Defining user requirements",
+        convert_user_input_to_goal,
+        &server.llm_settings(),
+      )
+      .await;
+
+    assert!(matches!(res, Err(AutoGippityError::Decode(_))));
+    assert_eq!(server.calls("convert_user_input_to_goal"), 1);
+  }
+
+  #[tokio::test]
+  async fn test_gives_up_past_retry_after_ceiling() {
+    let server: MockLlmServer = MockLlmServer::start().await;
+    server.on(
+      "convert_user_input_to_goal",
+      vec![
+        MockReply::Status(429, Some(3600)),
+        MockReply::Content("build a website that tracks todos".to_string()),
+      ],
+    );
+
+    let res: Result<String, AutoGippityError> = ai_task_request(
+        "todo app".to_string(),
+        "Managing Agent",
+        "Defining user requirements",
+        convert_user_input_to_goal,
+        &server.llm_settings(),
+      )
+      .await;
+
+    assert!(matches!(
+      res,
+      Err(AutoGippityError::Provider { status: 429, .. })
+    ));
+    assert_eq!(server.calls("convert_user_input_to_goal"), 1);
+  }
+
+  #[tokio::test]
+  async fn test_gives_up_on_server_errors() {
+    let server: MockLlmServer = MockLlmServer::start().await;
+    server.on(
+      "convert_user_input_to_goal",
+      vec![MockReply::Status(500, Some(0))],
+    );
+
+    let res: Result<String, AutoGippityError> = ai_task_request(
+        "todo app".to_string(),
+        "Managing Agent",
+        "Defining user requirements",
+        convert_user_input_to_goal,
+        &server.llm_settings(),
+      )
+      .await;
+
+    assert!(matches!(
+      res,
+      Err(AutoGippityError::Provider { status: 500, .. })
+    ));
+    assert_eq!(server.calls("convert_user_input_to_goal"), 3);
+  }
+
+  #[tokio::test]
+  async fn test_does_not_retry_auth_errors() {
+    let server: MockLlmServer = MockLlmServer::start().await;
+    server.on(
+      "convert_user_input_to_goal",
+      vec![MockReply::Status(401, None)],
+    );
+
+    let res: Result<String, AutoGippityError> = ai_task_request(
+        "todo app".to_string(),
+        "Managing Agent",
+        "Defining user requirements",
+        convert_user_input_to_goal,
+        &server.llm_settings(),
+      )
+      .await;
+
+    assert!(matches!(
+      res,
+      Err(AutoGippityError::Provider { status: 401, .. })
+    ));
+    assert_eq!(server.calls("convert_user_input_to_goal"), 1);
+  }
+}
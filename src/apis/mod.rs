@@ -1 +1,6 @@
 pub mod call_request;
+pub mod llm_provider;
+#[cfg(test)]
+pub mod mock_server;
+pub mod retry;
+pub mod transcript;
@@ -0,0 +1,107 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// how often and how patiently llm calls are retried
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+  pub max_attempts: u32,
+  pub base_backoff: Duration,
+  pub max_backoff: Duration,
+  // give up instead of waiting when the server asks for a longer Retry-After
+  pub max_retry_after: Duration,
+  // fraction of the delay randomly added or removed, 0.0 disables jitter
+  pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+  fn default() -> Self {
+    Self {
+      max_attempts: 3,
+      base_backoff: Duration::from_millis(1000),
+      max_backoff: Duration::from_secs(30),
+      max_retry_after: Duration::from_secs(300),
+      jitter: 0.2,
+    }
+  }
+}
+
+impl RetryPolicy {
+  // delay before the next attempt, a server supplied Retry-After is used as given.
+  // None when it is past max_retry_after and the call should give up
+  pub fn backoff(&self, failed_attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+    if let Some(retry_after) = retry_after {
+      return (retry_after <= self.max_retry_after).then_some(retry_after);
+    }
+
+    let exponent: u32 = failed_attempt.saturating_sub(1).min(16);
+    let delay: Duration = self
+      .base_backoff
+      .saturating_mul(2u32.pow(exponent))
+      .min(self.max_backoff);
+
+    if self.jitter <= 0.0 {
+      return Some(delay);
+    }
+
+    // spread in [1 - jitter, 1 + jitter], seeded from the clock to avoid a rand dependency
+    let nanos: u32 = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|since| since.subsec_nanos())
+      .unwrap_or(0);
+    let unit: f64 = (nanos % 10_000) as f64 / 10_000.0;
+    let factor: f64 = 1.0 + self.jitter * (2.0 * unit - 1.0);
+
+    Some(delay.mul_f64(factor).min(self.max_backoff))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn no_jitter() -> RetryPolicy {
+    RetryPolicy {
+      max_attempts: 5,
+      base_backoff: Duration::from_millis(100),
+      max_backoff: Duration::from_millis(500),
+      max_retry_after: Duration::from_secs(60),
+      jitter: 0.0,
+    }
+  }
+
+  #[test]
+  fn test_backoff_grows_exponentially_up_to_cap() {
+    let policy: RetryPolicy = no_jitter();
+
+    assert_eq!(policy.backoff(1, None), Some(Duration::from_millis(100)));
+    assert_eq!(policy.backoff(2, None), Some(Duration::from_millis(200)));
+    assert_eq!(policy.backoff(3, None), Some(Duration::from_millis(400)));
+    assert_eq!(policy.backoff(4, None), Some(Duration::from_millis(500)));
+  }
+
+  #[test]
+  fn test_retry_after_overrides_backoff() {
+    let policy: RetryPolicy = no_jitter();
+
+    assert_eq!(
+      policy.backoff(1, Some(Duration::from_millis(300))),
+      Some(Duration::from_millis(300))
+    );
+    // longer than max_backoff is still honoured as given
+    assert_eq!(
+      policy.backoff(1, Some(Duration::from_secs(60))),
+      Some(Duration::from_secs(60))
+    );
+    assert_eq!(policy.backoff(1, Some(Duration::from_secs(61))), None);
+  }
+
+  #[test]
+  fn test_jitter_stays_in_range() {
+    let mut policy: RetryPolicy = no_jitter();
+    policy.jitter = 0.5;
+
+    for _ in 0..20 {
+      let delay: Duration = policy.backoff(1, None).unwrap();
+      assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(150));
+    }
+  }
+}
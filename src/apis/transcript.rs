@@ -0,0 +1,215 @@
+use crate::models::general::error::AutoGippityError;
+use crate::models::general::llm::Message;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const DEFAULT_CASSETTE_PATH: &str = "cassettes/transcript.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptMode {
+  #[default]
+  #[serde(alias = "live")]
+  Off,
+  Record,
+  Replay,
+}
+
+impl FromStr for TranscriptMode {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_lowercase().as_str() {
+      "" | "off" | "live" => Ok(Self::Off),
+      "record" => Ok(Self::Record),
+      "replay" => Ok(Self::Replay),
+      other => Err(format!(
+        "unknown transcript mode '{}', expected one of off, record, replay",
+        other
+      )),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptConfig {
+  pub mode: TranscriptMode,
+  pub cassette_path: PathBuf,
+}
+
+impl Default for TranscriptConfig {
+  fn default() -> Self {
+    Self {
+      mode: TranscriptMode::Off,
+      cassette_path: PathBuf::from(DEFAULT_CASSETTE_PATH),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CassetteEntry {
+  pub messages: Vec<Message>,
+  pub response: String,
+}
+
+// recorded llm exchanges keyed by a hash of the request messages
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+  pub entries: BTreeMap<String, CassetteEntry>,
+}
+
+impl Cassette {
+  pub fn key(messages: &[Message]) -> String {
+    let mut hasher = Sha256::new();
+    for message in messages {
+      hasher.update(message.role.as_bytes());
+      hasher.update([0u8]);
+      hasher.update(message.content.as_bytes());
+      hasher.update([0u8]);
+    }
+    hasher
+      .finalize()
+      .iter()
+      .map(|byte| format!("{:02x}", byte))
+      .collect()
+  }
+
+  // a missing cassette is treated as empty so recording can start from nothing
+  pub fn load(path: &Path) -> Result<Self, AutoGippityError> {
+    if !path.exists() {
+      return Ok(Self::default());
+    }
+
+    let contents: String = fs::read_to_string(path)?;
+    serde_json::from_str(&contents).map_err(|e| {
+      AutoGippityError::Decode(format!(
+        "failed to decode cassette {}: {}",
+        path.display(),
+        e
+      ))
+    })
+  }
+
+  pub fn save(&self, path: &Path) -> Result<(), AutoGippityError> {
+    if let Some(parent) = path
+      .parent()
+      .filter(|parent| !parent.as_os_str().is_empty())
+    {
+      fs::create_dir_all(parent)?;
+    }
+
+    let contents: String = serde_json::to_string_pretty(self)?;
+    fs::write(path, contents + "\n")?;
+    Ok(())
+  }
+
+  pub fn lookup(&self, messages: &[Message]) -> Option<&str> {
+    self
+      .entries
+      .get(&Self::key(messages))
+      .map(|entry| entry.response.as_str())
+  }
+
+  pub fn record(&mut self, messages: &[Message], response: &str) {
+    self.entries.insert(
+      Self::key(messages),
+      CassetteEntry {
+        messages: messages.to_vec(),
+        response: response.to_string(),
+      },
+    );
+  }
+}
+
+// serve a recorded response, failing loudly when the prompt was never recorded
+pub fn replay(config: &TranscriptConfig, messages: &[Message]) -> Result<String, AutoGippityError> {
+  let cassette: Cassette = Cassette::load(&config.cassette_path)?;
+
+  cassette
+    .lookup(messages)
+    .map(|response| response.to_string())
+    .ok_or_else(|| {
+      AutoGippityError::Config(format!(
+        "no recorded response for prompt {} in {}, re-run with LLM_TRANSCRIPT_MODE=record",
+        Cassette::key(messages),
+        config.cassette_path.display()
+      ))
+    })
+}
+
+// append an exchange to the cassette, read-modify-write so concurrent agents share one file
+pub fn record(
+  config: &TranscriptConfig,
+  messages: &[Message],
+  response: &str,
+) -> Result<(), AutoGippityError> {
+  static CASSETTE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+  let _guard = CASSETTE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+  let mut cassette: Cassette = Cassette::load(&config.cassette_path)?;
+  cassette.record(messages, response);
+  cassette.save(&config.cassette_path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::helpers::test_support::TempDir;
+
+  fn dummy_messages(content: &str) -> Vec<Message> {
+    vec![Message {
+      role: "system".to_string(),
+      content: content.to_string(),
+    }]
+  }
+
+  #[test]
+  fn test_key_is_stable_and_content_sensitive() {
+    let first: String = Cassette::key(&dummy_messages("build a todo app"));
+    let second: String = Cassette::key(&dummy_messages("build a todo app"));
+    let other: String = Cassette::key(&dummy_messages("build a crypto app"));
+
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+    assert_eq!(first.len(), 64);
+  }
+
+  #[test]
+  fn test_record_then_replay_roundtrip() {
+    let temp: TempDir = TempDir::new("cassette_roundtrip");
+    let path: PathBuf = temp.join("cassette.json");
+
+    let config: TranscriptConfig = TranscriptConfig {
+      mode: TranscriptMode::Record,
+      cassette_path: path.clone(),
+    };
+    let messages: Vec<Message> = dummy_messages("build a todo app");
+
+    assert!(replay(&config, &messages).is_err());
+
+    record(&config, &messages, "build a website that tracks todos").unwrap();
+    let replayed: String = replay(&config, &messages).unwrap();
+    assert_eq!(replayed, "build a website that tracks todos");
+  }
+
+  #[test]
+  fn test_parses_transcript_modes() {
+    assert_eq!(
+      TranscriptMode::from_str("Record").unwrap(),
+      TranscriptMode::Record
+    );
+    assert_eq!(
+      TranscriptMode::from_str("replay").unwrap(),
+      TranscriptMode::Replay
+    );
+    assert_eq!(
+      TranscriptMode::from_str("off").unwrap(),
+      TranscriptMode::Off
+    );
+    assert!(TranscriptMode::from_str("rewind").is_err());
+  }
+}
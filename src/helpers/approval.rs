@@ -0,0 +1,311 @@
+use crate::helpers::command_line::confirm_safe_code;
+use crate::helpers::general::GeneratedSource;
+use crate::helpers::security_review::{findings_summary, review_files, Finding, Severity};
+use crate::models::general::error::AutoGippityError;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// how running ai written code gets approved
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalPolicy {
+  // ask on stdin, denied when input is disabled
+  Interactive,
+  // approve when already running inside a container or sandbox
+  Sandbox,
+  // never run generated code
+  Deny,
+  // approve when the check of every security finding is allowlisted
+  Allowlist,
+}
+
+impl FromStr for ApprovalPolicy {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_lowercase().as_str() {
+      "interactive" | "prompt" => Ok(Self::Interactive),
+      "sandbox" => Ok(Self::Sandbox),
+      "deny" | "never" => Ok(Self::Deny),
+      "allowlist" => Ok(Self::Allowlist),
+      other => Err(format!(
+        "unknown approval policy '{}', expected one of interactive, sandbox, deny, allowlist",
+        other
+      )),
+    }
+  }
+}
+
+// container markers, or AUTO_GIPPITY_SANDBOXED=true set by whoever launched us
+pub fn running_in_sandbox() -> bool {
+  if let Ok(flag) = env::var("AUTO_GIPPITY_SANDBOXED") {
+    return flag.trim().parse().unwrap_or(false);
+  }
+  env::var("container").is_ok()
+    || Path::new("/.dockerenv").exists()
+    || Path::new("/run/.containerenv").exists()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApprovalDecision {
+  pub policy: ApprovalPolicy,
+  pub approved: bool,
+  pub reason: String,
+  // every file that was checked, the approval covers all of them
+  pub files: Vec<PathBuf>,
+  pub findings: Vec<Finding>,
+}
+
+impl ApprovalDecision {
+  // clean abort path for the caller when denied
+  pub fn into_result(self) -> Result<(), AutoGippityError> {
+    if self.approved {
+      return Ok(());
+    }
+    Err(AutoGippityError::UserAbort(self.reason))
+  }
+}
+
+// everything a policy needs to decide, the prompt is injected so tests never touch stdin
+pub struct ApprovalRequest<'a> {
+  pub policy: ApprovalPolicy,
+  // everything the build compiles, not only main.rs
+  pub sources: &'a [GeneratedSource],
+  // security review checks the allowlist policy accepts, eg. filesystem
+  pub allowed_patterns: &'a [String],
+  pub interactive: bool,
+  pub in_sandbox: bool,
+  // urls the project is meant to call, other hosts are findings
+  pub external_urls: &'a [String],
+  // deny high severity findings under every policy
+  pub block_high_severity: bool,
+}
+
+// the prompt is shown the security review summary
+pub fn decide(request: &ApprovalRequest, prompt: impl FnOnce(&str) -> bool) -> ApprovalDecision {
+  let findings: Vec<Finding> = review_files(request.sources, request.external_urls);
+  let blocking: Vec<String> = findings
+    .iter()
+    .filter(|finding| finding.severity == Severity::High)
+    .map(|finding| format!("{} in {}", finding.check, finding.location()))
+    .collect();
+
+  let (approved, reason): (bool, String) = match request.policy {
+    _ if request.block_high_severity && !blocking.is_empty() => (
+      false,
+      format!(
+        "security review found high severity issues: {}",
+        blocking.join(", ")
+      ),
+    ),
+    ApprovalPolicy::Interactive if !request.interactive => (
+      false,
+      "running AI written code needs confirmation, but input is disabled".to_string(),
+    ),
+    ApprovalPolicy::Interactive if prompt(&findings_summary(&findings)) => {
+      (true, "approved at the prompt".to_string())
+    }
+    ApprovalPolicy::Interactive => (false, "declined to run AI written backend code".to_string()),
+    ApprovalPolicy::Sandbox if request.in_sandbox => (true, "running inside a sandbox".to_string()),
+    ApprovalPolicy::Sandbox => (false, "not running inside a sandbox".to_string()),
+    ApprovalPolicy::Deny => (
+      false,
+      "approval policy denies running generated code".to_string(),
+    ),
+    ApprovalPolicy::Allowlist => {
+      let mut blocked: Vec<&str> = findings
+        .iter()
+        .map(|finding| finding.check.as_str())
+        .filter(|check| !request.allowed_patterns.iter().any(|allowed| allowed == check))
+        .collect();
+      blocked.sort();
+      blocked.dedup();
+      if blocked.is_empty() {
+        (true, "every security finding is allowlisted".to_string())
+      } else {
+        (
+          false,
+          format!(
+            "security review found checks that are not allowlisted: {}",
+            blocked.join(", ")
+          ),
+        )
+      }
+    }
+  };
+
+  ApprovalDecision {
+    policy: request.policy,
+    approved,
+    reason,
+    files: request.sources.iter().map(|source| source.path.clone()).collect(),
+    findings,
+  }
+}
+
+// decide using the real terminal prompt
+pub fn decide_with_prompt(request: &ApprovalRequest) -> ApprovalDecision {
+  decide(request, confirm_safe_code)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SERVER_CODE: &str =
+    "use std::fs;\nfn main() {\n  let path = db_path();\n  let _ = fs::write(path, \"{}\");\n}\n";
+
+  fn source(path: &str, code: &str) -> GeneratedSource {
+    GeneratedSource {
+      path: PathBuf::from(path),
+      code: code.to_string(),
+    }
+  }
+
+  fn server_sources() -> Vec<GeneratedSource> {
+    vec![source("src/main.rs", SERVER_CODE)]
+  }
+
+  fn request<'a>(
+    policy: ApprovalPolicy,
+    allowed_patterns: &'a [String],
+    sources: &'a [GeneratedSource],
+  ) -> ApprovalRequest<'a> {
+    ApprovalRequest {
+      policy,
+      sources,
+      allowed_patterns,
+      interactive: true,
+      in_sandbox: false,
+      external_urls: &[],
+      block_high_severity: false,
+    }
+  }
+
+  #[test]
+  fn test_allowlist_policy() {
+    let sources: Vec<GeneratedSource> = server_sources();
+    let allowed: Vec<String> = vec!["filesystem".to_string()];
+    let decision: ApprovalDecision = decide(&request(ApprovalPolicy::Allowlist, &allowed, &sources), |_| {
+      panic!("allowlist must not prompt")
+    });
+    assert!(decision.approved);
+
+    let decision: ApprovalDecision = decide(&request(ApprovalPolicy::Allowlist, &[], &sources), |_| {
+      panic!("allowlist must not prompt")
+    });
+    assert!(!decision.approved);
+    assert!(decision.reason.contains("filesystem"));
+  }
+
+  #[test]
+  fn test_allowlist_must_name_high_severity_findings() {
+    let risky_sources: Vec<GeneratedSource> = vec![source(
+      "src/main.rs",
+      "fn main() {\n  let _ = reqwest::get(\"https://evil.example.com/x\");\n  unsafe { libc::getpid() };\n}\n",
+    )];
+    // every high severity check has to be named, eg. an unexpected host
+    let allowed: Vec<String> = vec!["unsafe".to_string()];
+    let decision: ApprovalDecision = decide(&request(ApprovalPolicy::Allowlist, &allowed, &risky_sources), |_| {
+      panic!("allowlist must not prompt")
+    });
+    assert!(!decision.approved);
+    assert_eq!(
+      decision.reason,
+      "security review found checks that are not allowlisted: network"
+    );
+
+    let allowed: Vec<String> = vec!["network".to_string(), "unsafe".to_string()];
+    assert!(decide(&request(ApprovalPolicy::Allowlist, &allowed, &risky_sources), |_| false).approved);
+  }
+
+  #[test]
+  fn test_interactive_policy_never_prompts_without_input() {
+    let sources: Vec<GeneratedSource> = server_sources();
+    let mut no_input: ApprovalRequest = request(ApprovalPolicy::Interactive, &[], &sources);
+    no_input.interactive = false;
+
+    let decision: ApprovalDecision = decide(&no_input, |_| panic!("must not prompt"));
+    assert!(!decision.approved);
+    assert!(matches!(
+      decision.into_result(),
+      Err(AutoGippityError::UserAbort(_))
+    ));
+
+    let decision: ApprovalDecision = decide(&request(ApprovalPolicy::Interactive, &[], &sources), |summary| {
+      assert!(summary.contains("medium filesystem src/main.rs:4"));
+      true
+    });
+    assert!(decision.approved);
+  }
+
+  #[test]
+  fn test_sandbox_and_deny_policies() {
+    let sources: Vec<GeneratedSource> = server_sources();
+    let mut sandboxed: ApprovalRequest = request(ApprovalPolicy::Sandbox, &[], &sources);
+    assert!(!decide(&sandboxed, |_| true).approved);
+    sandboxed.in_sandbox = true;
+    assert!(decide(&sandboxed, |_| false).approved);
+
+    assert!(!decide(&request(ApprovalPolicy::Deny, &[], &sources), |_| true).approved);
+    assert_eq!(
+      ApprovalPolicy::from_str("allowlist"),
+      Ok(ApprovalPolicy::Allowlist)
+    );
+  }
+
+  #[test]
+  fn test_blocks_high_severity_findings() {
+    let risky_sources: Vec<GeneratedSource> = vec![source(
+      "src/main.rs",
+      "fn main() {\n  std::process::Command::new(\"sh\").status().unwrap();\n}\n",
+    )];
+    let mut risky: ApprovalRequest = request(ApprovalPolicy::Interactive, &[], &risky_sources);
+    let decision: ApprovalDecision = decide(&risky, |summary| summary.contains("high   process"));
+    assert!(decision.approved);
+    assert_eq!(decision.findings.len(), 1);
+
+    risky.block_high_severity = true;
+    let decision: ApprovalDecision = decide(&risky, |_| panic!("blocked code must not prompt"));
+    assert!(!decision.approved);
+    assert_eq!(
+      decision.reason,
+      "security review found high severity issues: process in src/main.rs:2"
+    );
+  }
+
+  #[test]
+  fn test_checks_every_generated_source() {
+    // the qa tests are compiled and run too, so their code needs the same approval
+    let sources: Vec<GeneratedSource> = vec![
+      source("src/main.rs", "fn main() {}\n"),
+      source("src/db.rs", SERVER_CODE),
+      source(
+        "src/qa_tests.rs",
+        "#[test]\nfn wipe() {\n  std::process::Command::new(\"rm\").status().unwrap();\n}\n",
+      ),
+    ];
+    let mut all: ApprovalRequest = request(ApprovalPolicy::Allowlist, &[], &sources);
+    let decision: ApprovalDecision = decide(&all, |_| panic!("allowlist must not prompt"));
+    assert!(!decision.approved);
+    assert_eq!(
+      decision.reason,
+      "security review found checks that are not allowlisted: filesystem, process"
+    );
+    assert_eq!(decision.findings.len(), 2);
+    assert_eq!(
+      decision.files,
+      vec![
+        PathBuf::from("src/main.rs"),
+        PathBuf::from("src/db.rs"),
+        PathBuf::from("src/qa_tests.rs")
+      ]
+    );
+
+    all.sources = &sources[..1];
+    assert!(decide(&all, |_| panic!("allowlist must not prompt")).approved);
+  }
+}
@@ -0,0 +1,205 @@
+use crate::helpers::endpoint_tests::EndpointResult;
+use crate::models::agents::agent_traits::RouteObject;
+use crate::models::general::api_types::HttpMethod;
+
+use reqwest::{Client, Method, RequestBuilder};
+use serde_json::{json, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const AUTH_PREFIX: &str = "/auth";
+
+// added to the generated project when it gets logins, for password hashes and session tokens
+pub const AUTH_DEPENDENCIES: [(&str, &str); 2] = [
+  ("argon2", r#"{ version = "0.5", features = ["std"] }"#),
+  ("uuid", r#"{ version = "1", features = ["v4"] }"#),
+];
+
+// what the generated server must implement, sent to the model as AUTH_CONTRACT and checked by run_auth_flow
+pub const AUTH_CONTRACT: &str = r#"POST /auth/register with JSON {"username": string, "password": string} creates the user and answers 201 with {"username": string}, or 409 if the username is taken.
+POST /auth/login with the same JSON answers 200 with {"token": string} when the password matches, else 401.
+POST /auth/logout with the header "Authorization: Bearer <token>" ends that session and answers 200 with {}.
+GET /auth/me with a valid bearer token answers 200 with {"username": string}, and 401 without a token, with an unknown token or after logout.
+Hash passwords with argon2 (argon2::Argon2::default() with a random SaltString from argon2::password_hash::rand_core::OsRng) and never store or return them in plain text.
+Create tokens with uuid::Uuid::new_v4() and keep sessions in a Mutex<HashMap<token, username>> in the app data.
+Every other route keeps working as before, without a token."#;
+
+pub fn is_auth_route(route: &str) -> bool {
+  route == AUTH_PREFIX || route.starts_with("/auth/")
+}
+
+// the contract's routes, so the frontend and openapi document know about them
+pub fn auth_routes() -> Vec<RouteObject> {
+  serde_json::from_value(json!([
+    {"route": "/auth/register", "is_route_dynamic": false, "method": "post",
+     "request_body": {"username": "string", "password": "string"}, "response": {"username": "string"}},
+    {"route": "/auth/login", "is_route_dynamic": false, "method": "post",
+     "request_body": {"username": "string", "password": "string"}, "response": {"token": "string"}},
+    {"route": "/auth/logout", "is_route_dynamic": false, "method": "post",
+     "request_body": "None", "response": "None"},
+    {"route": "/auth/me", "is_route_dynamic": false, "method": "get",
+     "request_body": "None", "response": {"username": "string"}},
+  ]))
+  .expect("auth routes are valid route objects")
+}
+
+// what a step expects back, a success or a rejected token
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expect {
+  Success,
+  Rejected,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AuthStep<'a> {
+  // shown in front of a failure, eg. "after logout"
+  name: &'a str,
+  method: HttpMethod,
+  route: &'a str,
+  expect: Expect,
+}
+
+async fn auth_request(
+  client: &Client,
+  base_url: &str,
+  step: AuthStep<'_>,
+  body: Option<&Value>,
+  token: Option<&str>,
+) -> (EndpointResult, Value) {
+  let url: String = format!("{}{}", base_url.trim_end_matches('/'), step.route);
+  let mut result: EndpointResult = EndpointResult {
+    method: step.method.to_string(),
+    route: step.route.to_string(),
+    url: url.clone(),
+    status: None,
+    passed: false,
+    error: None,
+    shape_errors: vec![],
+  };
+
+  let method: Method = match step.method {
+    HttpMethod::Get => Method::GET,
+    _ => Method::POST,
+  };
+  let mut request: RequestBuilder = client.request(method, &url);
+  if let Some(body) = body {
+    request = request.json(body);
+  }
+  if let Some(token) = token {
+    request = request.bearer_auth(token);
+  }
+
+  match request.send().await {
+    Ok(response) => {
+      let status: u16 = response.status().as_u16();
+      result.status = Some(status);
+      let reply: Value = response.json().await.unwrap_or(Value::Null);
+      let (passed, expected): (bool, &str) = match step.expect {
+        Expect::Success => ((200..300).contains(&status), "a 2xx status"),
+        Expect::Rejected => (status == 401 || status == 403, "401"),
+      };
+      result.passed = passed;
+      if !passed {
+        result.error = Some(format!("{}: expected {}, got {}", step.name, expected, status));
+      }
+      (result, reply)
+    }
+    Err(e) => {
+      result.error = Some(format!("{}: {}", step.name, e));
+      (result, Value::Null)
+    }
+  }
+}
+
+// register, log in, use the session, log out and check the token no longer works
+pub async fn run_auth_flow(client: &Client, base_url: &str) -> Vec<EndpointResult> {
+  let nanos: u128 = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|elapsed| elapsed.as_nanos())
+    .unwrap_or_default();
+  let username: String = format!("user_{}", nanos);
+  let credentials: Value = json!({ "username": username, "password": "correct horse battery" });
+  let wrong_credentials: Value = json!({ "username": username, "password": "wrong password" });
+  let step = |name, method, route, expect| AuthStep { name, method, route, expect };
+
+  let mut results: Vec<EndpointResult> = vec![];
+  let register: AuthStep = step("register", HttpMethod::Post, "/auth/register", Expect::Success);
+  let (result, _) = auth_request(client, base_url, register, Some(&credentials), None).await;
+  let registered: bool = result.passed;
+  results.push(result);
+  if !registered {
+    return results;
+  }
+
+  let wrong_login: AuthStep =
+    step("login with a wrong password", HttpMethod::Post, "/auth/login", Expect::Rejected);
+  let (result, _) = auth_request(client, base_url, wrong_login, Some(&wrong_credentials), None).await;
+  results.push(result);
+
+  let login: AuthStep = step("login", HttpMethod::Post, "/auth/login", Expect::Success);
+  let (mut result, reply) = auth_request(client, base_url, login, Some(&credentials), None).await;
+  let token: Option<String> = reply.get("token").and_then(Value::as_str).map(str::to_string);
+  if result.passed && token.is_none() {
+    result.passed = false;
+    result.error = Some(format!("login: expected {{\"token\": string}}, got {}", reply));
+  }
+  results.push(result);
+  let Some(token) = token else {
+    return results;
+  };
+
+  let anonymous: AuthStep = step("without a token", HttpMethod::Get, "/auth/me", Expect::Rejected);
+  let (result, _) = auth_request(client, base_url, anonymous, None, None).await;
+  results.push(result);
+
+  let me: AuthStep = step("with the login token", HttpMethod::Get, "/auth/me", Expect::Success);
+  let (mut result, reply) = auth_request(client, base_url, me, None, Some(&token)).await;
+  if result.passed && reply.get("username").and_then(Value::as_str) != Some(username.as_str()) {
+    result.passed = false;
+    result.error = Some(format!(
+      "with the login token: expected {{\"username\": \"{}\"}}, got {}",
+      username, reply
+    ));
+  }
+  results.push(result);
+
+  let logout: AuthStep = step("logout", HttpMethod::Post, "/auth/logout", Expect::Success);
+  let (result, _) = auth_request(client, base_url, logout, None, Some(&token)).await;
+  results.push(result);
+
+  let logged_out: AuthStep = step("after logout", HttpMethod::Get, "/auth/me", Expect::Rejected);
+  let (result, _) = auth_request(client, base_url, logged_out, None, Some(&token)).await;
+  results.push(result);
+  results
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::helpers::test_app::TestApp;
+  use crate::helpers::endpoint_tests::run_endpoint_tests;
+
+  #[tokio::test]
+  async fn test_auth_flow() {
+    let app: TestApp = TestApp::start().await;
+    let client: Client = Client::new();
+
+    let results: Vec<EndpointResult> = run_auth_flow(&client, &app.url("")).await;
+    assert_eq!(results.len(), 7);
+    assert!(results.iter().all(|result| result.passed), "{:?}", results);
+    assert_eq!(results[6].status, Some(401));
+
+    // a server without the routes stops after the failed registration
+    let results: Vec<EndpointResult> = run_auth_flow(&client, &app.url("/status/404")).await;
+    assert_eq!(results.len(), 1);
+    assert_eq!(
+      results[0].error.as_deref(),
+      Some("register: expected a 2xx status, got 404")
+    );
+    assert!(auth_routes().iter().all(|route| is_auth_route(&route.route)));
+
+    // endpoint tests hand the auth routes to the flow instead of a crud sequence
+    let results: Vec<EndpointResult> = run_endpoint_tests(&client, &app.url(""), &auth_routes()).await;
+    assert_eq!(results.len(), 7);
+    assert_eq!(results[1].error, None);
+  }
+}
@@ -0,0 +1,188 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TestFailure {
+  // eg. qa_tests::create_todo
+  pub name: String,
+  // panic message and assertion values, without the backtrace note
+  pub message: String,
+}
+
+// outcome of cargo test in the generated project
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum TestRun {
+  Passed { tests: usize },
+  Failed { failures: Vec<TestFailure> },
+  // nothing ran, in_tests when every error points into the test module
+  CompileError { in_tests: bool, errors: Vec<String> },
+}
+
+// eg. "test qa_tests::get_todo ... FAILED"
+fn failed_test_name(line: &str) -> Option<&str> {
+  line
+    .strip_prefix("test ")?
+    .strip_suffix(" ... FAILED")
+    .map(str::trim)
+}
+
+// the "---- name stdout ----" section printed for each failed test
+fn failure_message(stdout: &str, name: &str) -> String {
+  let header: String = format!("---- {} stdout ----", name);
+  stdout
+    .lines()
+    .skip_while(|line| line.trim() != header)
+    .skip(1)
+    .take_while(|line| !line.starts_with("---- ") && line.trim() != "failures:")
+    .map(str::trim)
+    .filter(|line| !line.is_empty() && !line.starts_with("note: run with"))
+    .collect::<Vec<&str>>()
+    .join("\n")
+}
+
+// rustc errors, each with its source location and notes, without the final summary
+fn compile_errors(stderr: &str) -> Vec<String> {
+  let mut errors: Vec<String> = vec![];
+  let mut current: Option<Vec<&str>> = None;
+  for line in stderr.lines() {
+    if line.starts_with("error") && !line.starts_with("error: could not compile") {
+      errors.extend(current.take().map(|block| block.join("\n")));
+      current = Some(vec![line]);
+    } else if line.trim().is_empty() || line.starts_with("warning") {
+      errors.extend(current.take().map(|block| block.join("\n")));
+    } else if let Some(block) = current.as_mut() {
+      block.push(line);
+    }
+  }
+  errors.extend(current.map(|block| block.join("\n")));
+  errors
+}
+
+fn passed_count(stdout: &str) -> usize {
+  stdout
+    .lines()
+    .filter_map(|line| line.strip_prefix("test result: "))
+    // eg. "ok. 3 passed; 0 failed; ..."
+    .filter_map(|summary| {
+      let passed: &str = summary.split(". ").nth(1)?.split(';').next()?;
+      passed.strip_suffix(" passed")?.parse::<usize>().ok()
+    })
+    .sum()
+}
+
+pub fn read_test_run(success: bool, stdout: &str, stderr: &str, tests_file: &str) -> TestRun {
+  let failures: Vec<TestFailure> = stdout
+    .lines()
+    .filter_map(failed_test_name)
+    .map(|name| TestFailure {
+      name: name.to_string(),
+      message: failure_message(stdout, name),
+    })
+    .collect();
+
+  if !failures.is_empty() {
+    return TestRun::Failed { failures };
+  }
+  if success {
+    return TestRun::Passed { tests: passed_count(stdout) };
+  }
+  if stdout.contains("test result: ") {
+    // eg. a test binary that crashed before printing its failures
+    let lines: Vec<&str> = stderr.lines().collect();
+    return TestRun::Failed {
+      failures: vec![TestFailure {
+        name: "cargo test".to_string(),
+        message: lines[lines.len().saturating_sub(20)..].join("\n"),
+      }],
+    };
+  }
+
+  let errors: Vec<String> = compile_errors(stderr);
+  let locations: Vec<&str> = errors
+    .iter()
+    .flat_map(|error| error.lines())
+    .filter_map(|line| line.trim().strip_prefix("--> "))
+    .collect();
+  let in_tests: bool = !locations.is_empty() && locations.iter().all(|location| location.starts_with(tests_file));
+  TestRun::CompileError { in_tests, errors }
+}
+
+// include the test module from main.rs, once
+pub fn with_test_module(main_code: &str, module: &str) -> String {
+  let declaration: String = format!("mod {};", module);
+  if main_code.lines().any(|line| line.trim() == declaration) {
+    return main_code.to_string();
+  }
+  format!("{}\n\n#[cfg(test)]\n{}\n", main_code.trim_end(), declaration)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const FAILED_RUN: &str = "
+running 2 tests
+test qa_tests::create_todo ... ok
+test qa_tests::get_todo ... FAILED
+
+failures:
+
+---- qa_tests::get_todo stdout ----
+
+thread 'qa_tests::get_todo' panicked at src/qa_tests.rs:30:5:
+assertion `left == right` failed
+  left: 404
+ right: 200
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+
+failures:
+    qa_tests::get_todo
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+";
+
+  #[test]
+  fn test_read_test_run() {
+    assert_eq!(
+      read_test_run(false, FAILED_RUN, "error: test failed, to rerun pass `--bin web_template`", "src/qa_tests.rs"),
+      TestRun::Failed {
+        failures: vec![TestFailure {
+          name: "qa_tests::get_todo".to_string(),
+          message: "thread 'qa_tests::get_todo' panicked at src/qa_tests.rs:30:5:\nassertion `left == right` failed\nleft: 404\nright: 200".to_string(),
+        }]
+      }
+    );
+    assert_eq!(
+      read_test_run(true, "test result: ok. 3 passed; 0 failed; 0 ignored\n", "", "src/qa_tests.rs"),
+      TestRun::Passed { tests: 3 }
+    );
+
+    let stderr: &str = "   Compiling web_template v0.1.0
+error[E0425]: cannot find function `list_todo` in this scope
+  --> src/qa_tests.rs:12:9
+   |
+12 |   let app = list_todo();
+   |             ^^^^^^^^^ not found in this scope
+
+error: could not compile `web_template` (bin \"web_template\" test) due to 1 previous error
+";
+    let TestRun::CompileError { in_tests, errors } = read_test_run(false, "", stderr, "src/qa_tests.rs") else {
+      panic!("expected a compile error");
+    };
+    assert!(in_tests);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with("error[E0425]: cannot find function `list_todo`"));
+    assert_eq!(
+      read_test_run(false, "", &stderr.replace("src/qa_tests.rs", "src/main.rs"), "src/qa_tests.rs"),
+      TestRun::CompileError { in_tests: false, errors: compile_errors(&stderr.replace("src/qa_tests.rs", "src/main.rs")) }
+    );
+  }
+
+  #[test]
+  fn test_with_test_module() {
+    let main_code: String = with_test_module("fn main() {}\n", "qa_tests");
+    assert_eq!(main_code, "fn main() {}\n\n#[cfg(test)]\nmod qa_tests;\n");
+    assert_eq!(with_test_module(&main_code, "qa_tests"), main_code);
+  }
+}
@@ -1,8 +1,118 @@
+use crate::helpers::approval::ApprovalPolicy;
+use crate::models::general::config::CliOverrides;
+use clap::{Parser, Subcommand};
 use crossterm::{
   style::{Color, ResetColor, SetForegroundColor},
   ExecutableCommand,
 };
 use std::io::{stdin, stdout};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(name = "auto_gippity", about = "Generate a web server backend from a prompt")]
+pub struct Cli {
+  /// TOML config file, defaults to ./auto_gippity.toml when present
+  #[arg(long, global = true)]
+  pub config: Option<PathBuf>,
+
+  /// Directory run checkpoints are written to
+  #[arg(long, global = true)]
+  pub output_dir: Option<PathBuf>,
+
+  /// Cargo project template copied into each run's workspace
+  #[arg(long, global = true)]
+  pub project_dir: Option<PathBuf>,
+
+  /// LLM provider: openai, anthropic, ollama or llamacpp
+  #[arg(long, global = true)]
+  pub provider: Option<String>,
+
+  /// LLM model name
+  #[arg(long, global = true)]
+  pub model: Option<String>,
+
+  /// Port the generated server is tested on
+  #[arg(long, global = true)]
+  pub port: Option<u16>,
+
+  /// Never wait on stdin, for scripts and CI
+  #[arg(long, global = true)]
+  pub non_interactive: bool,
+
+  /// Who approves running generated code: interactive, sandbox, deny or allowlist
+  #[arg(long, global = true)]
+  pub approval: Option<ApprovalPolicy>,
+
+  #[command(subcommand)]
+  pub command: Option<CliCommand>,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum CliCommand {
+  /// Build a new project, asking for the prompt when not given
+  New { prompt: Option<String> },
+  /// Continue a run from its last checkpoint
+  Resume { run_dir: PathBuf },
+  /// Start a new run and only scope it with the Solutions Architect
+  Scope { prompt: Option<String> },
+  /// Run only the database agent against the factsheet of an existing run
+  Database { run_dir: PathBuf },
+  /// Run only the backend agent against the factsheet of an existing run
+  Backend { run_dir: PathBuf },
+  /// Run only the auth agent against the backend code of a run
+  Auth { run_dir: PathBuf },
+  /// Write and run tests for the handlers of a run's backend code
+  Qa { run_dir: PathBuf },
+  /// Run only the frontend agent against the endpoints saved by a run
+  Frontend { run_dir: PathBuf },
+  /// Review the backend code of a run for dangerous patterns
+  Review { run_dir: PathBuf },
+  /// Start the generated server and check the endpoints saved by a run
+  TestEndpoints { run_dir: PathBuf },
+  /// Print a summary of a run
+  Report { run_dir: PathBuf },
+  /// Print the OpenAPI 3.1 document for the endpoints saved by a run
+  Openapi { run_dir: PathBuf },
+  /// List runs and their workspaces, newest first
+  List,
+  /// Remove old runs and their workspaces
+  Clean {
+    /// Newest runs to keep
+    #[arg(long, default_value_t = 5)]
+    keep: usize,
+    /// Only remove runs older than this many days
+    #[arg(long)]
+    older_than_days: Option<u64>,
+    /// Print the runs that would be removed without deleting them
+    #[arg(long)]
+    dry_run: bool,
+  },
+  /// Run the generated server inside the sandbox and relay its port over a unix socket
+  #[command(name = "sandbox-relay", hide = true)]
+  SandboxRelay {
+    #[arg(long)]
+    socket: PathBuf,
+    #[arg(long)]
+    port: u16,
+    #[arg(last = true, required = true)]
+    command: Vec<String>,
+  },
+}
+
+impl Cli {
+  pub fn overrides(&self) -> CliOverrides {
+    CliOverrides {
+      config_file: self.config.clone(),
+      non_interactive: self.non_interactive,
+      project_dir: self.project_dir.clone(),
+      runs_dir: self.output_dir.clone(),
+      provider: self.provider.clone(),
+      model: self.model.clone(),
+      port: self.port,
+      approval: self.approval,
+    }
+  }
+}
 
 #[derive(PartialEq, Debug)]
 pub enum PrintCommand {
@@ -42,7 +152,7 @@ pub fn get_user_response(question: &str) -> String {
 
   // print questions in colour
   stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
-  println!("");
+  println!();
   println!("{}", question);
 
   //reset colour
@@ -55,17 +165,18 @@ pub fn get_user_response(question: &str) -> String {
     .expect("failed to read response");
 
   // trim white space
-  return user_response.trim().to_string();
+  user_response.trim().to_string()
 }
 
 // get user response that code is safe to execute
-pub fn confirm_safe_code() -> bool {
+pub fn confirm_safe_code(review_summary: &str) -> bool {
   let mut stdout: std::io::Stdout = stdout();
 
   loop {
     stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
-    println!("");
+    println!();
     println!("WARNING: you are about to run code written entirely by AI.");
+    println!("{}", review_summary);
     println!("Review your code and confirm you wish to continue.");
 
     // reset color
@@ -80,11 +191,12 @@ pub fn confirm_safe_code() -> bool {
     // reset colour
     stdout.execute(ResetColor).unwrap();
 
-    // read user input
+    // read user input, a closed or broken stdin counts as declining
     let mut human_response: String = String::new();
-    stdin()
-      .read_line(&mut human_response)
-      .expect("Failed to read response");
+    match stdin().read_line(&mut human_response) {
+      Ok(0) | Err(_) => return false,
+      Ok(_) => {}
+    }
 
     // trimp whitespace and covert to lowercase
     let human_response: String = human_response.trim().to_lowercase();
@@ -104,6 +216,76 @@ pub fn confirm_safe_code() -> bool {
 mod tests {
   use super::*;
 
+  #[test]
+  fn test_parses_subcommands_and_global_flags() {
+    let cli: Cli = Cli::parse_from([
+      "auto_gippity",
+      "scope",
+      "todo app",
+      "--non-interactive",
+      "--output-dir",
+      "/tmp/runs",
+      "--approval",
+      "deny",
+    ]);
+
+    assert_eq!(
+      cli.command,
+      Some(CliCommand::Scope {
+        prompt: Some("todo app".to_string())
+      })
+    );
+    let overrides: CliOverrides = cli.overrides();
+    assert!(overrides.non_interactive);
+    assert_eq!(overrides.runs_dir, Some(PathBuf::from("/tmp/runs")));
+    assert_eq!(overrides.approval, Some(ApprovalPolicy::Deny));
+
+    let cli: Cli = Cli::parse_from(["auto_gippity", "test-endpoints", "runs/run-1"]);
+    assert_eq!(
+      cli.command,
+      Some(CliCommand::TestEndpoints {
+        run_dir: PathBuf::from("runs/run-1")
+      })
+    );
+  }
+
+  #[test]
+  fn test_parses_clean() {
+    let cli: Cli = Cli::parse_from(["auto_gippity", "clean", "--older-than-days", "7", "--dry-run"]);
+    assert_eq!(
+      cli.command,
+      Some(CliCommand::Clean {
+        keep: 5,
+        older_than_days: Some(7),
+        dry_run: true,
+      })
+    );
+  }
+
+  #[test]
+  fn test_parses_sandbox_relay() {
+    let cli: Cli = Cli::parse_from([
+      "auto_gippity",
+      "sandbox-relay",
+      "--socket",
+      "/tmp/server.sock",
+      "--port",
+      "8080",
+      "--",
+      "cargo",
+      "run",
+      "--offline",
+    ]);
+    assert_eq!(
+      cli.command,
+      Some(CliCommand::SandboxRelay {
+        socket: PathBuf::from("/tmp/server.sock"),
+        port: 8080,
+        command: vec!["cargo".to_string(), "run".to_string(), "--offline".to_string()],
+      })
+    );
+  }
+
   #[test]
   fn test_prints_agent_msg() {
     PrintCommand::AICall
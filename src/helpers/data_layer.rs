@@ -0,0 +1,315 @@
+use crate::models::general::data_model::{ColumnModel, ColumnType, DataModel, TableModel};
+
+// added to the generated project when it gets a database, sqlite is compiled in
+pub const SQLITE_DEPENDENCY: (&str, &str) = ("rusqlite", r#"{ version = "0.31", features = ["bundled"] }"#);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration {
+  // file name without .sql, eg. 0001_create_todo
+  pub name: String,
+  pub sql: String,
+}
+
+fn sql_type(column_type: ColumnType) -> &'static str {
+  match column_type {
+    ColumnType::Integer | ColumnType::Boolean => "INTEGER",
+    ColumnType::Real => "REAL",
+    ColumnType::Text => "TEXT",
+  }
+}
+
+fn rust_type(column: &ColumnModel) -> String {
+  let rust_type: &str = match column.column_type {
+    ColumnType::Integer => "i64",
+    ColumnType::Real => "f64",
+    ColumnType::Text => "String",
+    ColumnType::Boolean => "bool",
+  };
+  match column.nullable {
+    true => format!("Option<{}>", rust_type),
+    false => rust_type.to_string(),
+  }
+}
+
+// eg. todo_item becomes TodoItem
+fn struct_name(table: &TableModel) -> String {
+  table
+    .name
+    .split('_')
+    .map(|part| {
+      let mut chars = part.chars();
+      match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+      }
+    })
+    .collect()
+}
+
+fn create_table_sql(table: &TableModel) -> String {
+  let mut lines: Vec<String> = vec!["  \"id\" INTEGER PRIMARY KEY AUTOINCREMENT".to_string()];
+  for column in table.data_columns() {
+    let mut line: String = format!("  \"{}\" {}", column.name, sql_type(column.column_type));
+    if !column.nullable {
+      line.push_str(" NOT NULL");
+    }
+    if let Some(referenced) = &column.references {
+      line.push_str(&format!(" REFERENCES \"{}\"(\"id\")", referenced));
+    }
+    lines.push(line);
+  }
+  format!("CREATE TABLE \"{}\" (\n{}\n);\n", table.name, lines.join(",\n"))
+}
+
+// one migration per table, numbered in creation order
+pub fn migrations(model: &DataModel) -> Vec<Migration> {
+  model
+    .tables
+    .iter()
+    .enumerate()
+    .map(|(i, table)| Migration {
+      name: format!("{:04}_create_{}", i + 1, table.name),
+      sql: create_table_sql(table),
+    })
+    .collect()
+}
+
+fn table_code(table: &TableModel) -> String {
+  let name: &str = table.name.as_str();
+  let struct_name: String = struct_name(table);
+  let columns: Vec<&ColumnModel> = table.data_columns().collect();
+
+  let fields: String = columns
+    .iter()
+    .map(|column| format!("  pub {}: {},\n", column.name, rust_type(column)))
+    .collect();
+  let from_row: String = columns
+    .iter()
+    .enumerate()
+    .map(|(i, column)| format!("    {}: row.get({})?,\n", column.name, i + 1))
+    .collect();
+  let select_columns: String = std::iter::once("\"id\"".to_string())
+    .chain(columns.iter().map(|column| format!("\"{}\"", column.name)))
+    .collect::<Vec<String>>()
+    .join(", ");
+  let insert_columns: String = columns
+    .iter()
+    .map(|column| format!("\"{}\"", column.name))
+    .collect::<Vec<String>>()
+    .join(", ");
+  let placeholders: String = (1..=columns.len())
+    .map(|i| format!("?{}", i))
+    .collect::<Vec<String>>()
+    .join(", ");
+  let assignments: String = columns
+    .iter()
+    .enumerate()
+    .map(|(i, column)| format!("\"{}\" = ?{}", column.name, i + 1))
+    .collect::<Vec<String>>()
+    .join(", ");
+  let values: String = columns
+    .iter()
+    .map(|column| format!("{}.{}", name, column.name))
+    .collect::<Vec<String>>()
+    .join(", ");
+
+  format!(
+    r##"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct {struct_name} {{
+  #[serde(default)]
+  pub id: i64,
+{fields}}}
+
+fn {name}_from_row(row: &Row) -> Result<{struct_name}> {{
+  Ok({struct_name} {{
+    id: row.get(0)?,
+{from_row}  }})
+}}
+
+pub fn insert_{name}(conn: &Connection, {name}: &{struct_name}) -> Result<i64> {{
+  conn.execute(
+    r#"INSERT INTO "{name}" ({insert_columns}) VALUES ({placeholders})"#,
+    params![{values}],
+  )?;
+  Ok(conn.last_insert_rowid())
+}}
+
+pub fn get_{name}(conn: &Connection, id: i64) -> Result<Option<{struct_name}>> {{
+  conn
+    .query_row(
+      r#"SELECT {select_columns} FROM "{name}" WHERE "id" = ?1"#,
+      params![id],
+      {name}_from_row,
+    )
+    .optional()
+}}
+
+pub fn list_{name}(conn: &Connection) -> Result<Vec<{struct_name}>> {{
+  let mut statement = conn.prepare(r#"SELECT {select_columns} FROM "{name}" ORDER BY "id""#)?;
+  let rows = statement.query_map([], {name}_from_row)?;
+  rows.collect()
+}}
+
+pub fn update_{name}(conn: &Connection, {name}: &{struct_name}) -> Result<bool> {{
+  let changed: usize = conn.execute(
+    r#"UPDATE "{name}" SET {assignments} WHERE "id" = ?{id_param}"#,
+    params![{values}, {name}.id],
+  )?;
+  Ok(changed > 0)
+}}
+
+pub fn delete_{name}(conn: &Connection, id: i64) -> Result<bool> {{
+  let changed: usize = conn.execute(r#"DELETE FROM "{name}" WHERE "id" = ?1"#, params![id])?;
+  Ok(changed > 0)
+}}
+"##,
+    id_param = columns.len() + 1,
+  )
+}
+
+// rust module with the migrations and crud functions for every table, for src/db.rs
+pub fn data_access_code(model: &DataModel, migrations_dir: &str) -> String {
+  let migration_list: String = migrations(model)
+    .iter()
+    .map(|migration| {
+      format!(
+        "  (\"{}\", include_str!(\"../{}/{}.sql\")),\n",
+        migration.name, migrations_dir, migration.name
+      )
+    })
+    .collect();
+  let tables: String = model.tables.iter().map(table_code).collect();
+
+  format!(
+    r##"// generated from the data model, regenerate instead of editing
+use rusqlite::{{params, Connection, OptionalExtension, Result, Row}};
+use serde::{{Deserialize, Serialize}};
+
+const MIGRATIONS: &[(&str, &str)] = &[
+{migration_list}];
+
+// open the database file and bring it up to date
+pub fn open(path: &str) -> Result<Connection> {{
+  let conn: Connection = Connection::open(path)?;
+  conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+  migrate(&conn)?;
+  Ok(conn)
+}}
+
+// apply each migration once, in order
+pub fn migrate(conn: &Connection) -> Result<()> {{
+  conn.execute_batch(r#"CREATE TABLE IF NOT EXISTS "_migrations" ("name" TEXT PRIMARY KEY);"#)?;
+  for (name, sql) in MIGRATIONS {{
+    let applied: i64 = conn.query_row(
+      r#"SELECT COUNT(*) FROM "_migrations" WHERE "name" = ?1"#,
+      params![name],
+      |row| row.get(0),
+    )?;
+    if applied == 0 {{
+      conn.execute_batch(sql)?;
+      conn.execute(r#"INSERT INTO "_migrations" ("name") VALUES (?1)"#, params![name])?;
+    }}
+  }}
+  Ok(())
+}}
+{tables}"##
+  )
+}
+
+// add a crate to [dependencies] unless the manifest already has it
+pub fn add_dependency(cargo_toml: &str, name: &str, spec: &str) -> String {
+  let entry: String = format!("{} = {}", name, spec);
+  let mut in_dependencies: bool = false;
+  for line in cargo_toml.lines() {
+    let line: &str = line.trim();
+    if line.starts_with('[') {
+      in_dependencies = line == "[dependencies]";
+    } else if in_dependencies && line.split(['=', ' ', '.']).next() == Some(name) {
+      return cargo_toml.to_string();
+    }
+  }
+
+  match cargo_toml.find("[dependencies]") {
+    Some(at) => {
+      let insert_at: usize = cargo_toml[at..]
+        .find('\n')
+        .map(|end| at + end + 1)
+        .unwrap_or(cargo_toml.len());
+      let separator: &str = if insert_at == cargo_toml.len() && !cargo_toml.ends_with('\n') {
+        "\n"
+      } else {
+        ""
+      };
+      format!(
+        "{}{}{}\n{}",
+        &cargo_toml[..insert_at],
+        separator,
+        entry,
+        &cargo_toml[insert_at..]
+      )
+    }
+    None => format!("{}\n\n[dependencies]\n{}\n", cargo_toml.trim_end(), entry),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::helpers::llm_output::verify_rust_syntax;
+  use serde_json::json;
+
+  fn todo_model() -> DataModel {
+    serde_json::from_value(json!({
+      "tables": [
+        {"name": "user", "columns": [{"name": "id", "column_type": "integer"}, {"name": "email", "column_type": "text"}]},
+        {"name": "todo_item", "columns": [
+          {"name": "title", "column_type": "text"},
+          {"name": "done", "column_type": "boolean"},
+          {"name": "user_id", "column_type": "integer", "references": "user", "nullable": true}
+        ]}
+      ]
+    }))
+    .unwrap()
+  }
+
+  #[test]
+  fn test_migrations_from_model() {
+    let migrations: Vec<Migration> = migrations(&todo_model());
+    assert_eq!(migrations[0].name, "0001_create_user");
+    assert_eq!(migrations[1].name, "0002_create_todo_item");
+    assert_eq!(
+      migrations[1].sql,
+      "CREATE TABLE \"todo_item\" (\n  \"id\" INTEGER PRIMARY KEY AUTOINCREMENT,\n  \"title\" TEXT NOT NULL,\n  \"done\" INTEGER NOT NULL,\n  \"user_id\" INTEGER REFERENCES \"user\"(\"id\")\n);\n"
+    );
+  }
+
+  #[test]
+  fn test_data_access_code_is_valid_rust() {
+    let code: String = data_access_code(&todo_model(), "migrations");
+    verify_rust_syntax(&code).unwrap();
+    assert!(code.contains("(\"0002_create_todo_item\", include_str!(\"../migrations/0002_create_todo_item.sql\")),"));
+    assert!(code.contains("pub struct TodoItem {"));
+    assert!(code.contains("  pub user_id: Option<i64>,\n"));
+    assert!(code.contains(
+      "r#\"UPDATE \"todo_item\" SET \"title\" = ?1, \"done\" = ?2, \"user_id\" = ?3 WHERE \"id\" = ?4\"#"
+    ));
+    assert!(code.contains("params![todo_item.title, todo_item.done, todo_item.user_id, todo_item.id]"));
+  }
+
+  #[test]
+  fn test_add_dependency() {
+    let (name, spec): (&str, &str) = SQLITE_DEPENDENCY;
+    let manifest: &str = "[package]\nname = \"web_template\"\n\n[dependencies]\nserde = \"1\"\n";
+    let added: String = add_dependency(manifest, name, spec);
+    assert_eq!(
+      added,
+      format!("[package]\nname = \"web_template\"\n\n[dependencies]\nrusqlite = {}\nserde = \"1\"\n", spec)
+    );
+    assert_eq!(add_dependency(&added, name, spec), added);
+    assert_eq!(
+      add_dependency("[package]\nname = \"a\"\n", name, spec),
+      format!("[package]\nname = \"a\"\n\n[dependencies]\nrusqlite = {}\n", spec)
+    );
+  }
+}
@@ -0,0 +1,377 @@
+use crate::helpers::auth_flow::{is_auth_route, run_auth_flow};
+use crate::models::agents::agent_traits::RouteObject;
+use crate::models::general::api_types::{FieldType, HttpMethod};
+
+use reqwest::{Client, Method};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+// outcome of one request against the generated server
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EndpointResult {
+  pub method: String,
+  pub route: String,
+  pub url: String,
+  pub status: Option<u16>,
+  pub passed: bool,
+  // why it failed, eg. an unexpected status or a connection error
+  pub error: Option<String>,
+  // where the body differs from the route's response shape
+  pub shape_errors: Vec<String>,
+}
+
+// example value for a field type, eg. a number becomes 1
+pub fn sample_value(field_type: &FieldType) -> Value {
+  match field_type {
+    FieldType::Object(fields) => Value::Object(
+      fields
+        .iter()
+        .map(|(name, field)| (name.clone(), sample_value(field)))
+        .collect(),
+    ),
+    FieldType::Array(item) => match item.as_ref() {
+      FieldType::Unknown => Value::Array(vec![]),
+      item => Value::Array(vec![sample_value(item)]),
+    },
+    FieldType::Integer => Value::from(1),
+    FieldType::Number => Value::from(1.5),
+    FieldType::Bool => Value::Bool(true),
+    FieldType::String => Value::String("test".to_string()),
+    FieldType::Optional(inner) => sample_value(inner),
+    FieldType::Absent | FieldType::Unknown => Value::Null,
+  }
+}
+
+fn type_name(value: &Value) -> &'static str {
+  match value {
+    Value::Null => "null",
+    Value::Bool(_) => "bool",
+    Value::Number(_) => "number",
+    Value::String(_) => "string",
+    Value::Array(_) => "array",
+    Value::Object(_) => "object",
+  }
+}
+
+// differences between a response body and the expected shape, extra fields are allowed
+pub fn shape_mismatches(expected: &FieldType, actual: &Value, path: &str) -> Vec<String> {
+  let mismatch = |expected_name: &str| -> Vec<String> {
+    vec![format!(
+      "{}: expected {}, got {}",
+      path,
+      expected_name,
+      type_name(actual)
+    )]
+  };
+
+  match expected {
+    FieldType::Object(fields) => match actual {
+      Value::Object(actual_fields) => fields
+        .iter()
+        .flat_map(|(name, field)| {
+          let field_path: String = format!("{}.{}", path, name);
+          match actual_fields.get(name) {
+            Some(actual_field) => shape_mismatches(field, actual_field, &field_path),
+            None if field.is_optional() => vec![],
+            None => vec![format!("{}: missing", field_path)],
+          }
+        })
+        .collect(),
+      // list routes are often described by the shape of one element
+      Value::Array(items) => items
+        .iter()
+        .enumerate()
+        .flat_map(|(i, item)| shape_mismatches(expected, item, &format!("{}[{}]", path, i)))
+        .collect(),
+      _ => mismatch("object"),
+    },
+    FieldType::Array(item) => match actual {
+      Value::Array(actual_items) => actual_items
+        .iter()
+        .enumerate()
+        .flat_map(|(i, actual_item)| shape_mismatches(item, actual_item, &format!("{}[{}]", path, i)))
+        .collect(),
+      _ => mismatch("array"),
+    },
+    FieldType::Optional(_) if actual.is_null() => vec![],
+    FieldType::Optional(inner) => shape_mismatches(inner, actual, path),
+    FieldType::Integer | FieldType::Number if !actual.is_number() => mismatch("number"),
+    FieldType::String if !actual.is_string() => mismatch("string"),
+    FieldType::Bool if !actual.is_boolean() => mismatch("bool"),
+    // absent or unknown, nothing to check
+    _ => vec![],
+  }
+}
+
+// collection a route belongs to, eg. /item/{id} and /item are both /item
+fn resource_of(route: &str) -> &str {
+  match route.rsplit_once('/') {
+    Some((resource, last)) if last.starts_with('{') && last.ends_with('}') => resource,
+    _ => route,
+  }
+}
+
+// create first, then read, update and finally delete the same record
+fn crud_phase(route_object: &RouteObject) -> u8 {
+  match route_object.method {
+    HttpMethod::Post if resource_of(&route_object.route) == route_object.route => 0,
+    HttpMethod::Get | HttpMethod::Post => 1,
+    HttpMethod::Put | HttpMethod::Patch => 2,
+    HttpMethod::Delete => 3,
+  }
+}
+
+// replace {param} segments with values remembered from earlier requests, or 1
+pub fn fill_route(route: &str, params: &Map<String, Value>) -> String {
+  route
+    .split('/')
+    .map(|segment| {
+      let Some(name) = segment.strip_prefix('{').and_then(|rest| rest.strip_suffix('}')) else {
+        return segment.to_string();
+      };
+      match params.get(name).or_else(|| params.get("id")) {
+        Some(Value::String(value)) => value.clone(),
+        Some(value) => value.to_string(),
+        None => "1".to_string(),
+      }
+    })
+    .collect::<Vec<String>>()
+    .join("/")
+}
+
+// keep ids and other plain fields of a created record for the following routes
+fn remember(params: &mut Map<String, Value>, record: &Value) {
+  if let Value::Object(fields) = record {
+    for (name, value) in fields {
+      if value.is_string() || value.is_number() {
+        params.insert(name.clone(), value.clone());
+      }
+    }
+  }
+}
+
+fn request_method(method: HttpMethod) -> Method {
+  match method {
+    HttpMethod::Get => Method::GET,
+    HttpMethod::Post => Method::POST,
+    HttpMethod::Put => Method::PUT,
+    HttpMethod::Patch => Method::PATCH,
+    HttpMethod::Delete => Method::DELETE,
+  }
+}
+
+fn response_ok(status: u16) -> bool {
+  (200..300).contains(&status)
+}
+
+async fn run_endpoint(
+  client: &Client,
+  base_url: &str,
+  route_object: &RouteObject,
+  params: &mut Map<String, Value>,
+) -> EndpointResult {
+  let url: String = format!(
+    "{}{}",
+    base_url.trim_end_matches('/'),
+    fill_route(&route_object.route, params)
+  );
+  let mut result: EndpointResult = EndpointResult {
+    method: route_object.method.to_string(),
+    route: route_object.route.clone(),
+    url: url.clone(),
+    status: None,
+    passed: false,
+    error: None,
+    shape_errors: vec![],
+  };
+
+  let body: Value = sample_value(&route_object.request_body);
+  let mut request: reqwest::RequestBuilder = client.request(request_method(route_object.method), &url);
+  if !body.is_null() {
+    request = request.json(&body);
+  }
+
+  match request.send().await {
+    Ok(response) => {
+      let status: u16 = response.status().as_u16();
+      result.status = Some(status);
+      let reply: Value = response.json().await.unwrap_or(Value::Null);
+
+      if !response_ok(status) {
+        result.error = Some(format!("unexpected status {}", status));
+        return result;
+      }
+      if route_object.method == HttpMethod::Post {
+        remember(params, &body);
+        remember(params, &reply);
+      }
+
+      result.shape_errors = shape_mismatches(&route_object.response, &reply, "$");
+      result.passed = result.shape_errors.is_empty();
+      if !result.passed {
+        result.error = Some(format!(
+          "response does not match the schema: {}",
+          result.shape_errors.join("; ")
+        ));
+      }
+    }
+    Err(e) => result.error = Some(e.to_string()),
+  }
+  result
+}
+
+// exercise every route, grouped per resource and run as a crud sequence
+// auth routes need a session, they are covered by the auth flow instead
+pub async fn run_endpoint_tests(
+  client: &Client,
+  base_url: &str,
+  endpoints: &[RouteObject],
+) -> Vec<EndpointResult> {
+  let mut resources: Vec<(&str, Vec<&RouteObject>)> = vec![];
+  for route_object in endpoints.iter().filter(|route_object| !is_auth_route(&route_object.route)) {
+    let resource: &str = resource_of(&route_object.route);
+    match resources.iter_mut().find(|(name, _)| *name == resource) {
+      Some((_, routes)) => routes.push(route_object),
+      None => resources.push((resource, vec![route_object])),
+    }
+  }
+
+  let mut results: Vec<EndpointResult> = vec![];
+  for (_, mut routes) in resources {
+    routes.sort_by_key(|route_object| crud_phase(route_object));
+
+    let mut params: Map<String, Value> = Map::new();
+    for route_object in routes {
+      results.push(run_endpoint(client, base_url, route_object, &mut params).await);
+    }
+  }
+
+  if endpoints.iter().any(|route_object| is_auth_route(&route_object.route)) {
+    results.extend(run_auth_flow(client, base_url).await);
+  }
+  results
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::helpers::test_app::TestApp;
+  use serde_json::json;
+
+  fn shape(value: Value) -> FieldType {
+    FieldType::from(value)
+  }
+
+  fn route(method: HttpMethod, route: &str, request_body: Value) -> RouteObject {
+    RouteObject {
+      is_route_dynamic: route.contains('{'),
+      method,
+      request_body: shape(request_body),
+      response: FieldType::Absent,
+      route: route.to_string(),
+    }
+  }
+
+  #[test]
+  fn test_sample_values_and_routes() {
+    assert_eq!(
+      sample_value(&shape(json!({"id": "u64", "name": "string", "done": "bool", "tags": ["string"]}))),
+      json!({"id": 1, "name": "test", "done": true, "tags": ["test"]})
+    );
+    assert_eq!(sample_value(&FieldType::Absent), Value::Null);
+
+    let mut params: Map<String, Value> = Map::new();
+    assert_eq!(fill_route("/item/{id}", &params), "/item/1");
+    params.insert("id".to_string(), json!(7));
+    params.insert("slug".to_string(), json!("post-a"));
+    assert_eq!(fill_route("/item/{id}", &params), "/item/7");
+    assert_eq!(fill_route("/blog/{slug}", &params), "/blog/post-a");
+    assert_eq!(resource_of("/item/{id}"), "/item");
+  }
+
+  #[test]
+  fn test_shape_mismatches() {
+    let expected: FieldType =
+      shape(json!({"id": "number", "name": "string", "tags": ["string"], "extra": "None"}));
+    assert!(shape_mismatches(
+      &expected,
+      &json!({"id": 1, "name": "a", "tags": ["x"], "other": true}),
+      "$"
+    )
+    .is_empty());
+    assert_eq!(
+      shape_mismatches(&expected, &json!({"id": "1", "tags": [2]}), "$"),
+      vec![
+        "$.id: expected number, got string",
+        "$.name: missing",
+        "$.tags[0]: expected string, got number",
+      ]
+    );
+    // a list route described by one element
+    assert_eq!(
+      shape_mismatches(&shape(json!({"id": "number"})), &json!([{"id": 1}, {"id": null}]), "$"),
+      vec!["$[1].id: expected number, got null"]
+    );
+    assert!(shape_mismatches(&FieldType::Unknown, &json!("anything"), "$").is_empty());
+  }
+
+  #[test]
+  fn test_optional_fields_may_be_null_or_missing() {
+    let expected: FieldType = shape(json!({"id": "number", "note": "Option<String>"}));
+    assert!(shape_mismatches(&expected, &json!({"id": 1, "note": null}), "$").is_empty());
+    assert!(shape_mismatches(&expected, &json!({"id": 1}), "$").is_empty());
+    assert!(shape_mismatches(&expected, &json!({"id": 1, "note": "a"}), "$").is_empty());
+    assert_eq!(
+      shape_mismatches(&expected, &json!({"id": 1, "note": 2}), "$"),
+      vec!["$.note: expected string, got number"]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_runs_crud_sequence_in_order() {
+    let app: TestApp = TestApp::start().await;
+    let client: Client = Client::new();
+
+    // listed in the order an llm might print them, not the order they can pass in
+    let item: Value = json!({"name": "string", "completed": "bool"});
+    let endpoints: Vec<RouteObject> = vec![
+      route(HttpMethod::Delete, "/crud/item/{id}", json!("None")),
+      route(HttpMethod::Get, "/crud/item/{id}", json!("None")),
+      route(HttpMethod::Put, "/crud/item/{id}", item.clone()),
+      route(HttpMethod::Post, "/crud/item", item),
+      route(HttpMethod::Get, "/crud/item", json!("None")),
+      route(HttpMethod::Get, "/status/500", json!("None")),
+    ];
+
+    let mut endpoints: Vec<RouteObject> = endpoints;
+    endpoints[1].response = shape(json!({"id": "number", "name": "string", "completed": "bool"}));
+    endpoints[4].response = shape(json!({"id": "number", "completed": "string"}));
+
+    let results: Vec<EndpointResult> =
+      run_endpoint_tests(&client, &app.url(""), &endpoints).await;
+    let order: Vec<(&str, &str)> = results
+      .iter()
+      .map(|result| (result.method.as_str(), result.route.as_str()))
+      .collect();
+    assert_eq!(
+      order,
+      vec![
+        ("POST", "/crud/item"),
+        ("GET", "/crud/item/{id}"),
+        ("GET", "/crud/item"),
+        ("PUT", "/crud/item/{id}"),
+        ("DELETE", "/crud/item/{id}"),
+        ("GET", "/status/500"),
+      ]
+    );
+
+    assert!(results[..5].iter().all(|result| result.status.is_some_and(response_ok)));
+    assert!(results[1].url.ends_with("/crud/item/1"));
+    assert!(results[1].passed);
+    // the collection route answers with a list whose elements have a bool, not a string
+    assert!(!results[2].passed);
+    assert_eq!(results[2].shape_errors, vec!["$[0].completed: expected string, got bool"]);
+    assert!(!results[5].passed);
+    assert_eq!(results[5].status, Some(500));
+  }
+}
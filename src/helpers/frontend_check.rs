@@ -0,0 +1,338 @@
+use crate::helpers::auth_flow::is_auth_route;
+use crate::helpers::endpoint_tests::fill_route;
+use crate::models::agents::agent_traits::RouteObject;
+use crate::models::general::api_types::HttpMethod;
+
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+// an api request a page makes, with {param} where the path is built from a variable
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApiCall {
+  pub method: HttpMethod,
+  pub path: String,
+}
+
+// text of the call's arguments, up to the bracket closing fetch(
+fn call_arguments(source: &str) -> &str {
+  let mut depth: usize = 0;
+  let mut quote: Option<char> = None;
+  for (i, c) in source.char_indices() {
+    match (quote, c) {
+      (Some(open), c) if c == open => quote = None,
+      (Some(_), _) => {}
+      (None, '"' | '\'' | '`') => quote = Some(c),
+      (None, '(' | '{' | '[') => depth += 1,
+      (None, ')' | '}' | ']') if depth == 0 => return &source[..i],
+      (None, ')' | '}' | ']') => depth -= 1,
+      _ => {}
+    }
+  }
+  source
+}
+
+// first argument with string literals joined and variables as {name}
+fn url_argument(arguments: &str) -> String {
+  let mut url: String = String::new();
+  let mut chars = arguments.chars().peekable();
+  let mut variable: String = String::new();
+
+  let flush = |url: &mut String, variable: &mut String| {
+    if !variable.trim().is_empty() {
+      url.push_str(&format!("{{{}}}", variable.trim()));
+    }
+    variable.clear();
+  };
+
+  while let Some(c) = chars.next() {
+    match c {
+      '"' | '\'' | '`' => {
+        flush(&mut url, &mut variable);
+        while let Some(inner) = chars.next() {
+          if inner == c {
+            break;
+          }
+          if c == '`' && inner == '$' && chars.peek() == Some(&'{') {
+            chars.next();
+            let expression: String = chars.by_ref().take_while(|e| *e != '}').collect();
+            url.push_str(&format!("{{{}}}", expression.trim()));
+            continue;
+          }
+          url.push(inner);
+        }
+      }
+      ',' => break,
+      '+' => flush(&mut url, &mut variable),
+      c => variable.push(c),
+    }
+  }
+  flush(&mut url, &mut variable);
+  url
+}
+
+fn call_method(arguments: &str) -> HttpMethod {
+  let Some(at) = arguments.find("method") else {
+    return HttpMethod::Get;
+  };
+  arguments[at + "method".len()..]
+    .trim_start_matches(|c: char| c.is_whitespace() || c == ':' || c == '"' || c == '\'')
+    .split(|c: char| !c.is_ascii_alphabetic())
+    .next()
+    .and_then(|method| method.parse().ok())
+    .unwrap_or(HttpMethod::Get)
+}
+
+// fetch calls to this server, the base url prefix and query strings are dropped
+pub fn api_calls(page: &str) -> Vec<ApiCall> {
+  page
+    .match_indices("fetch(")
+    .filter_map(|(at, _)| {
+      let arguments: &str = call_arguments(&page[at + "fetch(".len()..]);
+      let url: String = url_argument(arguments);
+      if url.contains("://") {
+        return None;
+      }
+      let path: &str = &url[url.find('/')?..];
+      let path: &str = path.split(['?', '#']).next().unwrap_or(path);
+      let path: String = path
+        .split('/')
+        .map(|segment| match segment.contains('{') {
+          true => format!("{{{}}}", segment.trim_matches(|c| c == '{' || c == '}')),
+          false => segment.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("/");
+      Some(ApiCall {
+        method: call_method(arguments),
+        path: path.trim_end_matches('/').to_string(),
+      })
+    })
+    .collect()
+}
+
+fn is_param(segment: &str) -> bool {
+  segment.starts_with('{') && segment.ends_with('}')
+}
+
+// the extracted route a call is served by, parameters match any segment
+pub fn find_route<'a>(call: &ApiCall, endpoints: &'a [RouteObject]) -> Option<&'a RouteObject> {
+  let call_segments: Vec<&str> = call.path.split('/').collect();
+  endpoints.iter().find(|route_object| {
+    let route_segments: Vec<&str> = route_object.route.trim_end_matches('/').split('/').collect();
+    route_object.method == call.method
+      && route_segments.len() == call_segments.len()
+      && route_segments
+        .iter()
+        .zip(&call_segments)
+        .all(|(route, call)| route == call || is_param(route) || is_param(call))
+  })
+}
+
+// local files a page loads, only the page itself is generated
+fn local_assets(page: &str) -> Vec<String> {
+  ["src=\"", "href=\""]
+    .iter()
+    .flat_map(|attribute| {
+      page.match_indices(attribute).filter_map(|(at, _)| {
+        let value: &str = &page[at + attribute.len()..];
+        let value: &str = &value[..value.find('"')?];
+        let external: bool = value.is_empty()
+          || value.contains("://")
+          || ["//", "#", "data:", "mailto:", "javascript:"]
+            .iter()
+            .any(|prefix| value.starts_with(prefix));
+        match external {
+          true => None,
+          false => Some(value.to_string()),
+        }
+      })
+    })
+    .collect()
+}
+
+// what is wrong with a page before it is loaded against the server
+pub fn page_problems(page: &str, endpoints: &[RouteObject]) -> Vec<String> {
+  let mut problems: Vec<String> = vec![];
+  let lower: String = page.to_lowercase();
+  if !lower.contains("<html") || !lower.contains("<script") {
+    problems.push("the page must be a complete html document with an inline <script>".to_string());
+  }
+
+  for asset in local_assets(page) {
+    problems.push(format!(
+      "the page loads {}, which does not exist, inline all scripts and styles",
+      asset
+    ));
+  }
+
+  let calls: Vec<ApiCall> = api_calls(page);
+  if calls.is_empty() {
+    problems.push("the page never calls the API with fetch".to_string());
+  }
+
+  let known_routes: Vec<String> = endpoints
+    .iter()
+    .map(|route_object| format!("{} {}", route_object.method, route_object.route))
+    .collect();
+  for call in &calls {
+    if find_route(call, endpoints).is_none() {
+      problems.push(format!(
+        "fetch {} {} does not match any API endpoint, use one of: {}",
+        call.method,
+        call.path,
+        known_routes.join(", ")
+      ));
+    }
+  }
+  problems
+}
+
+// load the data a page shows on open, the get calls without path parameters
+pub async fn load_page_data(client: &Client, base_url: &str, calls: &[ApiCall]) -> Vec<String> {
+  let mut failures: Vec<String> = vec![];
+  for call in calls {
+    // auth routes answer 401 until the user logs in
+    if call.method != HttpMethod::Get || call.path.split('/').any(is_param) || is_auth_route(&call.path) {
+      continue;
+    }
+    let url: String = format!(
+      "{}{}",
+      base_url.trim_end_matches('/'),
+      fill_route(&call.path, &Map::new())
+    );
+    match client.get(&url).send().await {
+      Ok(response) if response.status().is_success() => {
+        if response.json::<Value>().await.is_err() {
+          failures.push(format!("GET {} did not answer with JSON", call.path));
+        }
+      }
+      Ok(response) => failures.push(format!(
+        "GET {} answered with status {}",
+        call.path,
+        response.status().as_u16()
+      )),
+      Err(e) => failures.push(format!("GET {} failed: {}", call.path, e)),
+    }
+  }
+  failures
+}
+
+// the backend serves the saved page at GET /, so it can reach the api on the same origin
+pub async fn served_page_problem(client: &Client, base_url: &str, page: &str) -> Option<String> {
+  let url: String = format!("{}/", base_url.trim_end_matches('/'));
+  match client.get(&url).send().await {
+    Ok(response) if response.status().is_success() => match response.text().await {
+      Ok(body) if body.trim() == page.trim() => None,
+      Ok(_) => Some("GET / did not answer with the generated static/index.html".to_string()),
+      Err(e) => Some(format!("GET / failed: {}", e)),
+    },
+    Ok(response) => Some(format!("GET / answered with status {}", response.status().as_u16())),
+    Err(e) => Some(format!("GET / failed: {}", e)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::helpers::test_app::TestApp;
+  use crate::models::general::api_types::FieldType;
+
+  const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<body>
+<ul id="items"></ul>
+<script>
+const API_BASE = window.API_BASE || "";
+async function load() {
+  const res = await fetch(`${API_BASE}/item?sort=name`);
+  render(await res.json());
+}
+function remove(id) {
+  return fetch(API_BASE + "/item/" + id, { method: "DELETE" });
+}
+function rename(id, name) {
+  return fetch(`${API_BASE}/item/${id}`, {
+    method: 'PUT',
+    body: JSON.stringify({ name: name }),
+  });
+}
+fetch("https://api.example.com/prices");
+load();
+</script>
+</body>
+</html>
+"#;
+
+  fn route(method: HttpMethod, route: &str) -> RouteObject {
+    RouteObject {
+      is_route_dynamic: route.contains('{'),
+      method,
+      request_body: FieldType::Absent,
+      response: FieldType::Unknown,
+      route: route.to_string(),
+    }
+  }
+
+  #[test]
+  fn test_finds_api_calls_in_page() {
+    let calls: Vec<ApiCall> = api_calls(PAGE);
+    assert_eq!(
+      calls,
+      vec![
+        ApiCall { method: HttpMethod::Get, path: "/item".to_string() },
+        ApiCall { method: HttpMethod::Delete, path: "/item/{id}".to_string() },
+        ApiCall { method: HttpMethod::Put, path: "/item/{id}".to_string() },
+      ]
+    );
+  }
+
+  #[test]
+  fn test_page_problems() {
+    let endpoints: Vec<RouteObject> = vec![
+      route(HttpMethod::Get, "/item"),
+      route(HttpMethod::Delete, "/item/{item_id}"),
+    ];
+    assert_eq!(
+      page_problems(PAGE, &endpoints),
+      vec!["fetch PUT /item/{id} does not match any API endpoint, use one of: GET /item, DELETE /item/{item_id}"]
+    );
+
+    let page: &str = "<html><head><script src=\"app.js\"></script></head></html>";
+    let problems: Vec<String> = page_problems(page, &endpoints);
+    assert!(problems[0].contains("app.js"));
+    assert!(problems[1].contains("never calls the API"));
+  }
+
+  #[tokio::test]
+  async fn test_loads_page_data_from_server() {
+    let app: TestApp = TestApp::start().await;
+    let calls: Vec<ApiCall> = vec![
+      ApiCall { method: HttpMethod::Get, path: "/crud/item".to_string() },
+      ApiCall { method: HttpMethod::Get, path: "/crud/item/{id}".to_string() },
+      ApiCall { method: HttpMethod::Post, path: "/crud/item".to_string() },
+      ApiCall { method: HttpMethod::Get, path: "/status/500".to_string() },
+    ];
+    let failures: Vec<String> = load_page_data(&Client::new(), &app.url(""), &calls).await;
+    assert_eq!(failures, vec!["GET /status/500 answered with status 500"]);
+  }
+
+  #[tokio::test]
+  async fn test_served_page_must_match_the_saved_page() {
+    let app: TestApp = TestApp::start().await;
+    let client: Client = Client::new();
+
+    app.set_page(PAGE);
+    assert_eq!(served_page_problem(&client, &app.url(""), PAGE).await, None);
+    assert_eq!(
+      served_page_problem(&client, &app.url(""), "<html><script>fetch('/todo')</script></html>").await,
+      Some("GET / did not answer with the generated static/index.html".to_string())
+    );
+
+    let empty: TestApp = TestApp::start().await;
+    assert_eq!(
+      served_page_problem(&client, &empty.url(""), PAGE).await,
+      Some("GET / answered with status 404".to_string())
+    );
+  }
+}
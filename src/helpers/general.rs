@@ -1,20 +1,23 @@
 use reqwest::Client;
+use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use std::fs;
-use dotenv::dotenv;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use crate::{apis::call_request::call_gpt, models::general::llm::Message};
+use crate::{
+  apis::{call_request::call_gpt, llm_provider::LlmConfig, retry::RetryPolicy, transcript::TranscriptConfig},
+  models::general::{
+    config::{LlmSettings, WorkspaceConfig},
+    error::AutoGippityError,
+    llm::Message,
+  },
+};
 
 use super::command_line::PrintCommand;
-
-const CODE_TEMPLATE_PATH: &str =
-  "../web_template/src/code_template.rs";
-pub const WEB_SEVER_PROJECT_PATH: &str =
-  "../web_template/";
-pub const EXEC_MAIN_PATH: &str =
-  "../web_template/src/main.rs";
-const API_SCHEMA_PATH: &str =
-  "../auto_gippity/schemas/api_schema.json";
+use super::data_layer::{add_dependency, Migration};
+use super::llm_output::extract_json;
+use super::output_schema::{schema_value, validate, OutputRules};
 
 pub fn extend_ai_function(ai_func: fn(&str) -> &'static str, func_input: &str) -> Message {
   let ai_function_str = ai_func(func_input);
@@ -34,45 +37,160 @@ pub fn extend_ai_function(ai_func: fn(&str) -> &'static str, func_input: &str) -
   }
 }
 
-// fn performs call to llm gpt -- decoded
+// call llm with the configured retry policy
+async fn call_gpt_with_retry(
+  messages: &[Message],
+  agent_position: &str,
+  llm_settings: &LlmSettings,
+) -> Result<String, AutoGippityError> {
+  let llm_config: LlmConfig = llm_settings.llm_config()?;
+  let transcript_config: TranscriptConfig = llm_settings.transcript_config();
+  let retry_policy: RetryPolicy = llm_settings.retry_policy();
+  let mut attempt: u32 = 1;
+
+  loop {
+    // get llm response
+    let llm_response_res: Result<String, AutoGippityError> =
+      call_gpt(messages.to_vec(), &llm_config, &transcript_config).await;
+
+    let e: AutoGippityError = match llm_response_res {
+      Ok(llm_resp) => return Ok(llm_resp),
+      Err(e) => e,
+    };
+
+    // give up on fatal errors, once attempts run out or when asked to wait too long
+    let delay: Option<Duration> = retry_policy.backoff(attempt, e.retry_after());
+    let Some(delay) = delay.filter(|_| e.is_retryable() && attempt < retry_policy.max_attempts) else {
+      let fail_msg: String = format!(
+        "LLM call failed on attempt {}/{}, giving up: {}",
+        attempt, retry_policy.max_attempts, e
+      );
+      PrintCommand::Issue.print_agent_message(agent_position, fail_msg.as_str());
+      return Err(e);
+    };
+
+    let retry_msg: String = format!(
+      "LLM call failed on attempt {}/{}, retrying in {:.1}s: {}",
+      attempt,
+      retry_policy.max_attempts,
+      delay.as_secs_f64(),
+      e
+    );
+    PrintCommand::Issue.print_agent_message(agent_position, retry_msg.as_str());
+
+    tokio::time::sleep(delay).await;
+    attempt += 1;
+  }
+}
+
+// fn performs call to llm gpt
 pub async fn ai_task_request(
   msg_context: String,
   agent_position: &str,
   agent_operation: &str,
   function_pass: for<'a> fn(&'a str) -> &'static str,
-) -> String {
+  llm_settings: &LlmSettings,
+) -> Result<String, AutoGippityError> {
   // extend ai function
   let extended_msg: Message = extend_ai_function(function_pass, &msg_context);
 
   // print current status
   PrintCommand::AICall.print_agent_message(agent_position, agent_operation);
 
-  // get llm response
-  let llm_response_res: Result<String, Box<dyn std::error::Error + Send>> =
-    call_gpt(vec![extended_msg.clone()]).await;
+  call_gpt_with_retry(&[extended_msg], agent_position, llm_settings).await
+}
 
-  // return success
-  match llm_response_res {
-    Ok(llm_resp) => llm_resp,
-    Err(_) => call_gpt(vec![extended_msg.clone()])
-      .await
-      .expect("failed twice to call openai"),
+// decode llm output, collecting syntax, schema and semantic violations
+fn decode_checked<T: DeserializeOwned + OutputRules>(
+  llm_response: &str,
+  schema: &serde_json::Value,
+) -> Result<T, Vec<String>> {
+  let value: serde_json::Value =
+    serde_json::from_str(extract_json(llm_response)).map_err(|e| vec![format!("invalid JSON: {}", e)])?;
+
+  let schema_violations: Vec<String> = validate(schema, &value);
+  if !schema_violations.is_empty() {
+    return Err(schema_violations);
+  }
+
+  let decoded: T = serde_json::from_value(value).map_err(|e| vec![e.to_string()])?;
+
+  let rule_violations: Vec<String> = decoded.rule_violations();
+  if !rule_violations.is_empty() {
+    return Err(rule_violations);
   }
+  Ok(decoded)
 }
 
-pub async fn ai_task_request_decoded<T: DeserializeOwned>(
+// show the model its own answer and what went wrong
+pub fn push_repair_turns(
+  messages: &mut Vec<Message>,
+  llm_response: String,
+  violations: &[String],
+  schema: &serde_json::Value,
+) {
+  messages.push(Message {
+    role: "assistant".to_string(),
+    content: llm_response,
+  });
+  messages.push(Message {
+    role: "user".to_string(),
+    content: format!(
+      "Your output was rejected for these reasons:\n- {}\n\
+      Print the function output again as raw JSON that satisfies this JSON Schema and the rules in the function description:\n{}\n\
+      No markdown code fences, no commentary.",
+      violations.join("\n- "),
+      schema
+    ),
+  });
+}
+
+// fn performs call to llm gpt -- decoded, re-prompting with the violations when the output is invalid
+pub async fn ai_task_request_decoded<T: DeserializeOwned + JsonSchema + OutputRules>(
   msg_context: String,
   agent_position: &str,
   agent_operation: &str,
   function_pass: for<'a> fn(&'a str) -> &'static str,
-) -> T {
-  let llm_response: String =
-    ai_task_request(msg_context, agent_position, agent_operation, function_pass).await;
+  llm_settings: &LlmSettings,
+) -> Result<T, AutoGippityError> {
+  let extended_msg: Message = extend_ai_function(function_pass, &msg_context);
+  let mut messages: Vec<Message> = vec![extended_msg];
+  let schema: serde_json::Value = schema_value::<T>();
+
+  PrintCommand::AICall.print_agent_message(agent_position, agent_operation);
 
-  let decoded_response: T = serde_json::from_str(llm_response.as_str())
-    .expect("failed to decode ai response from serde_json");
+  let max_attempts: u32 = llm_settings.decode_attempts.max(1);
+  let mut attempt: u32 = 1;
 
-  return decoded_response;
+  loop {
+    let llm_response: String = call_gpt_with_retry(&messages, agent_position, llm_settings).await?;
+
+    let violations: Vec<String> = match decode_checked::<T>(&llm_response, &schema) {
+      Ok(decoded) => return Ok(decoded),
+      Err(violations) => violations,
+    };
+
+    // log every violation against the agent that asked
+    for violation in &violations {
+      let issue_msg: String = format!(
+        "Invalid response for {} (attempt {}/{}): {}",
+        agent_operation, attempt, max_attempts, violation
+      );
+      PrintCommand::Issue.print_agent_message(agent_position, issue_msg.as_str());
+    }
+
+    if attempt >= max_attempts {
+      return Err(AutoGippityError::Decode(format!(
+        "failed to decode ai response for {} after {} attempts: {}",
+        agent_operation,
+        attempt,
+        violations.join("; ")
+      )));
+    }
+
+    push_repair_turns(&mut messages, llm_response, &violations, &schema);
+    attempt += 1;
+  }
 }
 
 // check url is valid
@@ -81,35 +199,179 @@ pub async fn check_status_code(client: &Client, url: &str) -> Result<u16, reqwes
   Ok(response.status().as_u16())
 }
 
+fn read_file(path: &Path, what: &str) -> Result<String, AutoGippityError> {
+  fs::read_to_string(path).map_err(|e| {
+    AutoGippityError::Runtime(format!("failed to read {} {}: {}", what, path.display(), e))
+  })
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), AutoGippityError> {
+  fs::write(path, contents)
+    .map_err(|e| AutoGippityError::Runtime(format!("failed to write {}: {}", path.display(), e)))
+}
+
+// copy a cargo project, leaving out its build output
+pub fn copy_project(source: &Path, destination: &Path) -> Result<(), AutoGippityError> {
+  let entries: fs::ReadDir = fs::read_dir(source).map_err(|e| {
+    AutoGippityError::Runtime(format!("failed to read project {}: {}", source.display(), e))
+  })?;
+  fs::create_dir_all(destination)?;
+
+  for entry in entries {
+    let entry: fs::DirEntry = entry?;
+    let target: PathBuf = destination.join(entry.file_name());
+    if entry.file_name() == "target" || entry.path() == destination {
+      continue;
+    }
+    if entry.file_type()?.is_dir() {
+      copy_project(&entry.path(), &target)?;
+    } else {
+      fs::copy(entry.path(), &target)?;
+    }
+  }
+  Ok(())
+}
+
 // get code template
-pub fn read_code_template_contents() -> String {
-  dotenv().ok();
-  let path: String = String::from(CODE_TEMPLATE_PATH);
-  fs::read_to_string(path).expect("failed to read code template")
+pub fn read_code_template_contents(workspace: &WorkspaceConfig) -> Result<String, AutoGippityError> {
+  read_file(&workspace.code_template_path(), "code template")
+}
+
+// get the data access module written by the database agent
+pub fn read_data_access_contents(workspace: &WorkspaceConfig) -> Result<String, AutoGippityError> {
+  read_file(&workspace.data_access_path(), "data access code")
 }
 
 // get exec main
-pub fn read_exec_main_contents() -> String {
-  let path: String = String::from(EXEC_MAIN_PATH);
-  fs::read_to_string(path).expect("failed to read exec main")
+pub fn read_exec_main_contents(workspace: &WorkspaceConfig) -> Result<String, AutoGippityError> {
+  read_file(&workspace.exec_main_path(), "exec main")
+}
+
+// a file the agents generated, its path relative to the workspace
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedSource {
+  pub path: PathBuf,
+  pub code: String,
+}
+
+// the rust sources cargo compiles: main.rs, plus db.rs and the qa tests once written
+pub fn read_generated_sources(workspace: &WorkspaceConfig) -> Result<Vec<GeneratedSource>, AutoGippityError> {
+  let mut sources: Vec<GeneratedSource> = vec![GeneratedSource {
+    path: workspace.exec_main.clone(),
+    code: read_exec_main_contents(workspace)?,
+  }];
+  for path in [&workspace.data_access, &workspace.qa_tests] {
+    let full_path: PathBuf = workspace.project_dir.join(path);
+    if full_path.is_file() {
+      sources.push(GeneratedSource {
+        path: path.clone(),
+        code: read_file(&full_path, "generated code")?,
+      });
+    }
+  }
+  Ok(sources)
+}
+
+// every file the run writes code into: the rust sources, the page and the migrations
+pub fn read_generated_files(workspace: &WorkspaceConfig) -> Result<Vec<GeneratedSource>, AutoGippityError> {
+  let mut files: Vec<GeneratedSource> = read_generated_sources(workspace)?;
+  if workspace.frontend_page_path().is_file() {
+    files.push(GeneratedSource {
+      path: workspace.frontend_page.clone(),
+      code: read_file(&workspace.frontend_page_path(), "frontend page")?,
+    });
+  }
+
+  let mut migrations: Vec<PathBuf> = match fs::read_dir(workspace.migrations_path()) {
+    Ok(entries) => entries
+      .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+      .filter(|path| path.extension().is_some_and(|extension| extension == "sql"))
+      .collect(),
+    Err(_) => vec![],
+  };
+  migrations.sort();
+  for migration in migrations {
+    let Some(file_name) = migration.file_name() else {
+      continue;
+    };
+    files.push(GeneratedSource {
+      path: workspace.migrations_dir.join(file_name),
+      code: read_file(&migration, "migration")?,
+    });
+  }
+  Ok(files)
 }
 
 // save new backend code
-pub fn save_backend_code(contents: &String) {
-  let path: String = String::from(EXEC_MAIN_PATH);
-  fs::write(path, contents).expect("Failed to write main.rs file");
+pub fn save_backend_code(workspace: &WorkspaceConfig, contents: &str) -> Result<(), AutoGippityError> {
+  write_file(&workspace.exec_main_path(), contents)
+}
+
+fn write_creating_dirs(path: &Path, contents: &str) -> Result<(), AutoGippityError> {
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  write_file(path, contents)
 }
 
 // save json api endpoint schema
-pub fn save_api_endpoints(api_endpoints: &String) {
-  let path: String = String::from(API_SCHEMA_PATH);
-  fs::write(path, api_endpoints).expect("failed to write api endpoints to file");
+pub fn save_api_endpoints(workspace: &WorkspaceConfig, api_endpoints: &str) -> Result<(), AutoGippityError> {
+  write_creating_dirs(&workspace.api_schema_path(), api_endpoints)
+}
+
+// save the openapi document next to it
+pub fn save_openapi_document(workspace: &WorkspaceConfig, document: &str) -> Result<(), AutoGippityError> {
+  write_creating_dirs(&workspace.openapi_path(), document)
+}
+
+// write the migrations, dropping .sql files left from an earlier data model
+pub fn save_migrations(workspace: &WorkspaceConfig, migrations: &[Migration]) -> Result<(), AutoGippityError> {
+  let dir: PathBuf = workspace.migrations_path();
+  if dir.is_dir() {
+    for entry in fs::read_dir(&dir)? {
+      let path: PathBuf = entry?.path();
+      if path.extension().is_some_and(|extension| extension == "sql") {
+        fs::remove_file(&path)?;
+      }
+    }
+  }
+  for migration in migrations {
+    write_creating_dirs(&dir.join(format!("{}.sql", migration.name)), &migration.sql)?;
+  }
+  Ok(())
+}
+
+// save the data access module next to main.rs
+pub fn save_data_access_code(workspace: &WorkspaceConfig, code: &str) -> Result<(), AutoGippityError> {
+  write_creating_dirs(&workspace.data_access_path(), code)
+}
+
+// add a crate to the generated project's Cargo.toml
+pub fn add_project_dependency(workspace: &WorkspaceConfig, name: &str, spec: &str) -> Result<(), AutoGippityError> {
+  let path: PathBuf = workspace.cargo_toml_path();
+  let manifest: String = read_file(&path, "project manifest")?;
+  let updated: String = add_dependency(&manifest, name, spec);
+  if updated != manifest {
+    write_file(&path, &updated)?;
+  }
+  Ok(())
+}
+
+// save the generated frontend page
+pub fn save_frontend_code(workspace: &WorkspaceConfig, page: &str) -> Result<(), AutoGippityError> {
+  write_creating_dirs(&workspace.frontend_page_path(), page)
+}
+
+pub fn save_qa_tests(workspace: &WorkspaceConfig, code: &str) -> Result<(), AutoGippityError> {
+  write_creating_dirs(&workspace.qa_tests_path(), code)
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::ai_functions::aifunc_architect::print_site_urls;
   use crate::ai_functions::aifunc_managing::convert_user_input_to_goal;
+  use crate::apis::mock_server::{MockLlmServer, MockReply};
 
   #[test]
   fn test_extending_ai_function() {
@@ -126,8 +388,10 @@ mod tests {
       "Managing Agent",
       "Defining user requirements",
       convert_user_input_to_goal,
+      &LlmSettings::test_cassette(),
     )
-    .await;
+    .await
+    .unwrap();
 
     dbg!(&res);
     assert!(res.len() > 20);
@@ -135,7 +399,80 @@ mod tests {
   
   #[test]
   fn test_read_code_template_contents() {
-    let msg = read_code_template_contents();
+    let msg = read_code_template_contents(&WorkspaceConfig::default()).unwrap();
     dbg!(&msg);
   }
+
+  #[tokio::test]
+  async fn test_decoded_request_strips_markdown_fences() {
+    let server: MockLlmServer = MockLlmServer::start().await;
+    server.on(
+      "print_site_urls",
+      vec![MockReply::Content(
+        "```json\n[\"https://api.binance.com/api/v3/exchangeInfo\"]\n```".to_string(),
+      )],
+    );
+
+    let urls: Vec<String> = ai_task_request_decoded::<Vec<String>>(
+        "crypto prices".to_string(),
+        "Solutions Architect",
+        "Finding urls",
+        print_site_urls,
+        &server.llm_settings(),
+      )
+      .await
+      .unwrap();
+
+    assert_eq!(urls, vec!["https://api.binance.com/api/v3/exchangeInfo"]);
+    assert_eq!(server.calls("print_site_urls"), 1);
+  }
+
+  #[tokio::test]
+  async fn test_decoded_request_reprompts_after_bad_json() {
+    let server: MockLlmServer = MockLlmServer::start().await;
+    server.on(
+      "print_site_urls",
+      vec![
+        MockReply::Content("I would use the binance api for this.".to_string()),
+        MockReply::Content("[\"https://api.binance.com/api/v3/exchangeInfo\"]".to_string()),
+      ],
+    );
+
+    let urls: Vec<String> = ai_task_request_decoded::<Vec<String>>(
+        "crypto prices".to_string(),
+        "Solutions Architect",
+        "Finding urls",
+        print_site_urls,
+        &server.llm_settings(),
+      )
+      .await
+      .unwrap();
+
+    assert_eq!(urls.len(), 1);
+    assert_eq!(server.calls("print_site_urls"), 2);
+  }
+
+  #[tokio::test]
+  async fn test_decoded_request_gives_up_after_bounded_attempts() {
+    let server: MockLlmServer = MockLlmServer::start().await;
+    server.on(
+      "print_site_urls",
+      vec![MockReply::Content("still not json".to_string())],
+    );
+
+    let res: Result<Vec<String>, AutoGippityError> = ai_task_request_decoded::<Vec<String>>(
+        "crypto prices".to_string(),
+        "Solutions Architect",
+        "Finding urls",
+        print_site_urls,
+        &server.llm_settings(),
+      )
+      .await;
+
+    assert!(matches!(res, Err(AutoGippityError::Decode(_))));
+    assert_eq!(
+      server.calls("print_site_urls"),
+      LlmSettings::default().decode_attempts as usize
+    );
+  }
 }
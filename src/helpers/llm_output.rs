@@ -0,0 +1,184 @@
+// pull the json value out of an llm reply that may be wrapped in fences or prose
+pub fn extract_json(raw: &str) -> &str {
+  let text: &str = fenced_blocks(raw)
+    .first()
+    .map(|(_, body)| *body)
+    .unwrap_or(raw)
+    .trim();
+
+  let Some(start) = text.find(['{', '[']) else {
+    return text;
+  };
+
+  let closing: char = if text[start..].starts_with('{') {
+    '}'
+  } else {
+    ']'
+  };
+
+  match text.rfind(closing) {
+    Some(end) if end > start => &text[start..=end],
+    _ => &text[start..],
+  }
+}
+
+// every ``` fenced block as (language tag, body)
+fn fenced_blocks(raw: &str) -> Vec<(&str, &str)> {
+  let mut blocks: Vec<(&str, &str)> = vec![];
+  let mut rest: &str = raw;
+
+  while let Some(start) = rest.find("```") {
+    let after_fence: &str = &rest[start + 3..];
+    let tag_end: usize = after_fence.find('\n').unwrap_or(after_fence.len());
+    let tag: &str = after_fence[..tag_end].trim();
+    let body: &str = &after_fence[(tag_end + 1).min(after_fence.len())..];
+
+    match body.find("```") {
+      Some(end) => {
+        blocks.push((tag, &body[..end]));
+        rest = &body[end + 3..];
+      }
+      None => {
+        blocks.push((tag, body));
+        break;
+      }
+    }
+  }
+  blocks
+}
+
+fn looks_like_rust_item(line: &str) -> bool {
+  let line: &str = line.trim_start();
+  [
+    "use ", "#[", "#![", "//", "/*", "fn ", "pub ", "mod ", "struct ", "enum ", "impl ", "impl<",
+    "trait ", "type ", "const ", "static ", "extern ", "async ",
+  ]
+  .iter()
+  .any(|prefix| line.starts_with(prefix))
+}
+
+// pull an html page out of an llm reply that may be wrapped in fences or prose
+pub fn extract_html(raw: &str) -> String {
+  let blocks: Vec<(&str, &str)> = fenced_blocks(raw);
+  let fenced: Option<&str> = blocks
+    .iter()
+    .find(|(tag, _)| *tag == "html")
+    .or_else(|| blocks.iter().max_by_key(|(_, body)| body.len()))
+    .map(|(_, body)| *body);
+  let text: &str = fenced.unwrap_or(raw).trim();
+
+  let lower: String = text.to_lowercase();
+  let start: usize = lower
+    .find("<!doctype")
+    .or_else(|| lower.find("<html"))
+    .unwrap_or(0);
+  let end: usize = lower
+    .rfind("</html>")
+    .map(|end| end + "</html>".len())
+    .filter(|end| *end > start)
+    .unwrap_or(text.len());
+  text[start..end].trim().to_string() + "\n"
+}
+
+// pull the rust source out of an llm reply that may be wrapped in fences or prose
+pub fn extract_rust_code(raw: &str) -> String {
+  let blocks: Vec<(&str, &str)> = fenced_blocks(raw);
+
+  // prefer a block tagged as rust, otherwise the largest block
+  let fenced: Option<&str> = blocks
+    .iter()
+    .find(|(tag, _)| *tag == "rust" || *tag == "rs")
+    .or_else(|| blocks.iter().max_by_key(|(_, body)| body.len()))
+    .map(|(_, body)| *body);
+
+  if let Some(code) = fenced {
+    return code.trim().to_string() + "\n";
+  }
+
+  // no fences, drop commentary before the first item and after the last closing line
+  let lines: Vec<&str> = raw.lines().collect();
+  let Some(first) = lines.iter().position(|line| looks_like_rust_item(line)) else {
+    return raw.trim().to_string() + "\n";
+  };
+  let last: usize = lines
+    .iter()
+    .rposition(|line| {
+      let line: &str = line.trim_end();
+      line.ends_with('}') || line.ends_with(';')
+    })
+    .filter(|last| *last >= first)
+    .unwrap_or(lines.len() - 1);
+
+  lines[first..=last].join("\n").trim().to_string() + "\n"
+}
+
+// confirm the code parses as a rust source file
+pub fn verify_rust_syntax(code: &str) -> Result<(), String> {
+  syn::parse_file(code)
+    .map(|_| ())
+    .map_err(|e| format!("generated code is not valid Rust: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_extract_json_passes_clean_json_through() {
+    assert_eq!(extract_json(r#"{"a": true}"#), r#"{"a": true}"#);
+    assert_eq!(extract_json(r#"["x", "y"]"#), r#"["x", "y"]"#);
+  }
+
+  #[test]
+  fn test_extract_json_strips_fences_and_prose() {
+    let raw: &str =
+      "Here is the scope:\n```json\n{\"a\": true}\n```\nLet me know if you need more.";
+    assert_eq!(extract_json(raw), "{\"a\": true}");
+
+    let raw: &str = "Sure! The urls are [\"https://a.com\"] as requested.";
+    assert_eq!(extract_json(raw), "[\"https://a.com\"]");
+  }
+
+  #[test]
+  fn test_extract_json_leaves_non_json_alone() {
+    assert_eq!(extract_json("  no json here "), "no json here");
+  }
+
+  #[test]
+  fn test_extract_html_strips_fences_and_prose() {
+    let page: &str = "<!DOCTYPE html>\n<html><body><script>fetch(\"/item\")</script></body></html>";
+    let raw: String = format!("Here is the page:\n```html\n{}\n```\nOpen it in a browser.", page);
+    assert_eq!(extract_html(&raw), format!("{}\n", page));
+
+    let raw: String = format!("Sure!\n{}\nThat is all.", page);
+    assert_eq!(extract_html(&raw), format!("{}\n", page));
+  }
+
+  const SERVER_CODE: &str =
+    "use actix_web::{web, App, HttpServer};\n\nfn main() {\n  println!(\"hi\");\n}\n";
+
+  #[test]
+  fn test_extract_rust_code_prefers_rust_fence() {
+    let raw: String = format!(
+      "Here is the shell command:\n```sh\ncargo run\n```\nAnd the code:\n```rust\n{}```\nEnjoy!",
+      SERVER_CODE
+    );
+    assert_eq!(extract_rust_code(&raw), SERVER_CODE);
+  }
+
+  #[test]
+  fn test_extract_rust_code_strips_unfenced_prose() {
+    let raw: String = format!(
+      "Sure, here is the updated server.\n\n{}\nThis compiles with actix-web 4.",
+      SERVER_CODE
+    );
+    assert_eq!(extract_rust_code(&raw), SERVER_CODE);
+  }
+
+  #[test]
+  fn test_verify_rust_syntax() {
+    assert!(verify_rust_syntax(SERVER_CODE).is_ok());
+    assert!(verify_rust_syntax("fn main() {\n  let x = ;\n").is_err());
+    assert!(verify_rust_syntax("Here is your code: fn main() {}").is_err());
+  }
+}
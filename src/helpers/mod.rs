@@ -1,2 +1,20 @@
+pub mod approval;
+pub mod auth_flow;
+pub mod cargo_tests;
 pub mod command_line;
+pub mod data_layer;
+pub mod endpoint_tests;
+pub mod frontend_check;
 pub mod general;
+pub mod llm_output;
+pub mod openapi;
+pub mod output_schema;
+pub mod port_relay;
+pub mod run_log;
+pub mod sandbox;
+pub mod security_review;
+pub mod server;
+#[cfg(test)]
+pub mod test_app;
+#[cfg(test)]
+pub mod test_support;
@@ -0,0 +1,207 @@
+use crate::models::agents::agent_traits::RouteObject;
+use crate::models::general::api_types::FieldType;
+
+use serde_json::{json, Map, Value};
+
+pub const OPENAPI_VERSION: &str = "3.1.0";
+
+// json schema (draft 2020-12, as used by openapi 3.1) for a body's field type
+pub fn field_schema(field_type: &FieldType) -> Value {
+  match field_type {
+    FieldType::Absent => json!({ "type": "null" }),
+    FieldType::Unknown => json!({}),
+    FieldType::Integer => json!({ "type": "integer" }),
+    FieldType::Number => json!({ "type": "number" }),
+    FieldType::String => json!({ "type": "string" }),
+    FieldType::Bool => json!({ "type": "boolean" }),
+    FieldType::Array(item) => json!({ "type": "array", "items": field_schema(item) }),
+    FieldType::Optional(inner) => json!({ "anyOf": [field_schema(inner), { "type": "null" }] }),
+    FieldType::Object(fields) => {
+      let properties: Map<String, Value> = fields
+        .iter()
+        .map(|(name, field)| (name.clone(), field_schema(field)))
+        .collect();
+      let required: Vec<&String> = fields
+        .iter()
+        .filter(|(_, field)| !field.is_optional())
+        .map(|(name, _)| name)
+        .collect();
+      if required.is_empty() {
+        json!({ "type": "object", "properties": properties })
+      } else {
+        json!({ "type": "object", "properties": properties, "required": required })
+      }
+    }
+  }
+}
+
+fn path_params(route: &str) -> Vec<&str> {
+  route
+    .split('/')
+    .filter_map(|segment| segment.strip_prefix('{').and_then(|rest| rest.strip_suffix('}')))
+    .collect()
+}
+
+// a path parameter named like a body field gets that field's type, else it is a string
+fn param_schema(name: &str, route_object: &RouteObject) -> Value {
+  [&route_object.request_body, &route_object.response]
+    .into_iter()
+    .find_map(|body| match body {
+      FieldType::Object(fields) => match fields.get(name) {
+        Some(field @ (FieldType::Integer | FieldType::Number | FieldType::String)) => {
+          Some(field_schema(field))
+        }
+        _ => None,
+      },
+      _ => None,
+    })
+    .unwrap_or_else(|| json!({ "type": "string" }))
+}
+
+// eg. get /item/{id} becomes get_item_id
+fn operation_id(route_object: &RouteObject) -> String {
+  let route_name: String = route_object
+    .route
+    .split(|c: char| !c.is_ascii_alphanumeric())
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<&str>>()
+    .join("_");
+  match route_name.is_empty() {
+    true => format!("{}_root", route_object.method.as_str()),
+    false => format!("{}_{}", route_object.method.as_str(), route_name),
+  }
+}
+
+fn json_content(field_type: &FieldType) -> Value {
+  json!({ "application/json": { "schema": field_schema(field_type) } })
+}
+
+fn operation(route_object: &RouteObject) -> Value {
+  let mut operation: Map<String, Value> = Map::new();
+  operation.insert("operationId".to_string(), json!(operation_id(route_object)));
+
+  let parameters: Vec<Value> = path_params(&route_object.route)
+    .into_iter()
+    .map(|name| {
+      json!({
+        "name": name,
+        "in": "path",
+        "required": true,
+        "schema": param_schema(name, route_object),
+      })
+    })
+    .collect();
+  if !parameters.is_empty() {
+    operation.insert("parameters".to_string(), Value::Array(parameters));
+  }
+
+  if route_object.request_body != FieldType::Absent {
+    operation.insert(
+      "requestBody".to_string(),
+      json!({ "required": true, "content": json_content(&route_object.request_body) }),
+    );
+  }
+
+  let mut response: Map<String, Value> = Map::new();
+  response.insert("description".to_string(), json!("Successful response"));
+  if route_object.response != FieldType::Absent {
+    response.insert("content".to_string(), json_content(&route_object.response));
+  }
+  operation.insert("responses".to_string(), json!({ "200": response }));
+
+  Value::Object(operation)
+}
+
+// openapi 3.1 document for the extracted endpoints, routes sharing a path share a path item
+// the server is only listed for a fixed port, port 0 picks a new one for every run
+pub fn openapi_document(description: &str, endpoints: &[RouteObject], port: u16) -> Value {
+  let mut paths: Map<String, Value> = Map::new();
+  for route_object in endpoints {
+    let path_item: &mut Value = paths
+      .entry(route_object.route.clone())
+      .or_insert_with(|| json!({}));
+    if let Value::Object(path_item) = path_item {
+      path_item.insert(route_object.method.as_str().to_string(), operation(route_object));
+    }
+  }
+
+  let mut document: Value = json!({
+    "openapi": OPENAPI_VERSION,
+    "info": {
+      "title": "Generated web server API",
+      "description": description,
+      "version": env!("CARGO_PKG_VERSION"),
+    },
+    "paths": paths,
+  });
+  if port != 0 {
+    document["servers"] = json!([{ "url": format!("http://127.0.0.1:{}", port) }]);
+  }
+  document
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::general::api_types::HttpMethod;
+
+  fn route(method: HttpMethod, route: &str, request_body: Value, response: Value) -> RouteObject {
+    RouteObject {
+      is_route_dynamic: route.contains('{'),
+      method,
+      request_body: FieldType::from(request_body),
+      response: FieldType::from(response),
+      route: route.to_string(),
+    }
+  }
+
+  #[test]
+  fn test_openapi_document_from_routes() {
+    let item: Value =
+      json!({"id": "u64", "name": "string", "tags": ["string"], "note": "None", "due": "Option<String>"});
+    let endpoints: Vec<RouteObject> = vec![
+      route(HttpMethod::Post, "/item", item.clone(), json!("None")),
+      route(HttpMethod::Get, "/item/{id}", json!("None"), item),
+      route(HttpMethod::Delete, "/item/{id}", json!("None"), json!("None")),
+      route(HttpMethod::Get, "/crypto/{symbol}", json!("None"), json!("not_provided")),
+    ];
+
+    let document: Value = openapi_document("a todo site", &endpoints, 0);
+    assert_eq!(document["openapi"], "3.1.0");
+    assert!(document.get("servers").is_none());
+    assert_eq!(document["info"]["description"], "a todo site");
+    assert_eq!(document["paths"].as_object().unwrap().len(), 3);
+
+    let create: &Value = &document["paths"]["/item"]["post"];
+    assert_eq!(create["operationId"], "post_item");
+    assert!(create.get("parameters").is_none());
+    let body: &Value = &create["requestBody"]["content"]["application/json"]["schema"];
+    assert_eq!(body["properties"]["id"], json!({"type": "integer"}));
+    assert_eq!(body["properties"]["tags"], json!({"type": "array", "items": {"type": "string"}}));
+    assert_eq!(body["required"], json!(["id", "name", "tags"]));
+    assert_eq!(
+      body["properties"]["due"],
+      json!({"anyOf": [{"type": "string"}, {"type": "null"}]})
+    );
+    assert!(create["responses"]["200"].get("content").is_none());
+
+    let read: &Value = &document["paths"]["/item/{id}"]["get"];
+    assert_eq!(
+      read["parameters"],
+      json!([{"name": "id", "in": "path", "required": true, "schema": {"type": "integer"}}])
+    );
+    assert!(read.get("requestBody").is_none());
+    assert_eq!(
+      read["responses"]["200"]["content"]["application/json"]["schema"]["properties"]["name"],
+      json!({"type": "string"})
+    );
+    assert_eq!(document["paths"]["/item/{id}"]["delete"]["operationId"], "delete_item_id");
+
+    let crypto: &Value = &document["paths"]["/crypto/{symbol}"]["get"];
+    assert_eq!(crypto["parameters"][0]["schema"], json!({"type": "string"}));
+    assert_eq!(crypto["responses"]["200"]["content"]["application/json"]["schema"], json!({}));
+
+    let fixed_port: Value = openapi_document("a todo site", &endpoints, 9090);
+    assert_eq!(fixed_port["servers"], json!([{"url": "http://127.0.0.1:9090"}]));
+  }
+}
@@ -0,0 +1,201 @@
+use schemars::{schema_for, JsonSchema};
+use serde_json::{Map, Value};
+
+// semantic checks on decoded llm output that a json schema cannot express
+pub trait OutputRules {
+  fn rule_violations(&self) -> Vec<String> {
+    vec![]
+  }
+}
+
+// list of external api urls from the solutions architect
+impl OutputRules for Vec<String> {
+  fn rule_violations(&self) -> Vec<String> {
+    self
+      .iter()
+      .enumerate()
+      .filter(|(_, url)| {
+        !(url.starts_with("http://") || url.starts_with("https://"))
+          || url.contains(char::is_whitespace)
+      })
+      .map(|(i, url)| format!("$[{}]: '{}' is not an http(s) url", i, url))
+      .collect()
+  }
+}
+
+// json schema generated from the rust type the output is decoded into
+pub fn schema_value<T: JsonSchema>() -> Value {
+  schema_for!(T).as_value().clone()
+}
+
+fn type_matches(expected: &str, instance: &Value) -> bool {
+  match expected {
+    "object" => instance.is_object(),
+    "array" => instance.is_array(),
+    "string" => instance.is_string(),
+    "boolean" => instance.is_boolean(),
+    "null" => instance.is_null(),
+    "number" => instance.is_number(),
+    "integer" => instance.is_i64() || instance.is_u64(),
+    _ => true,
+  }
+}
+
+fn resolve<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+  let pointer: &str = reference.strip_prefix('#')?;
+  root.pointer(pointer)
+}
+
+// validate the subset of json schema that schemars emits for our output types
+fn validate_at(root: &Value, schema: &Value, instance: &Value, path: &str, out: &mut Vec<String>) {
+  let schema: &Map<String, Value> = match schema {
+    Value::Bool(true) => return,
+    Value::Bool(false) => {
+      out.push(format!("{}: no value is allowed here", path));
+      return;
+    }
+    Value::Object(schema) => schema,
+    _ => return,
+  };
+
+  if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+    match resolve(root, reference) {
+      Some(target) => validate_at(root, target, instance, path, out),
+      None => out.push(format!(
+        "{}: unresolvable schema reference {}",
+        path, reference
+      )),
+    }
+  }
+
+  if let Some(expected) = schema.get("type") {
+    let allowed: Vec<&str> = match expected {
+      Value::String(single) => vec![single.as_str()],
+      Value::Array(many) => many.iter().filter_map(Value::as_str).collect(),
+      _ => vec![],
+    };
+    if !allowed.is_empty() && !allowed.iter().any(|t| type_matches(t, instance)) {
+      out.push(format!(
+        "{}: expected {}, found {}",
+        path,
+        allowed.join(" or "),
+        instance
+      ));
+      return;
+    }
+  }
+
+  if let Some(Value::Array(options)) = schema.get("enum") {
+    if !options.contains(instance) {
+      out.push(format!(
+        "{}: {} is not one of {}",
+        path,
+        instance,
+        Value::Array(options.clone())
+      ));
+    }
+  }
+
+  for key in ["anyOf", "oneOf"] {
+    if let Some(Value::Array(variants)) = schema.get(key) {
+      let matches_any: bool = variants.iter().any(|variant| {
+        let mut variant_out: Vec<String> = vec![];
+        validate_at(root, variant, instance, path, &mut variant_out);
+        variant_out.is_empty()
+      });
+      if !matches_any {
+        out.push(format!("{}: value does not match any allowed shape", path));
+      }
+    }
+  }
+
+  if let Value::Object(fields) = instance {
+    if let Some(Value::Array(required)) = schema.get("required") {
+      for name in required.iter().filter_map(Value::as_str) {
+        if !fields.contains_key(name) {
+          out.push(format!("{}: missing required field '{}'", path, name));
+        }
+      }
+    }
+
+    let properties: Option<&Map<String, Value>> =
+      schema.get("properties").and_then(Value::as_object);
+    if let Some(properties) = properties {
+      for (name, value) in fields {
+        let field_path: String = format!("{}.{}", path, name);
+        match properties.get(name) {
+          Some(field_schema) => validate_at(root, field_schema, value, &field_path, out),
+          None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+            out.push(format!("{}: unexpected field", field_path))
+          }
+          None => {}
+        }
+      }
+    }
+  }
+
+  if let (Value::Array(items), Some(item_schema)) = (instance, schema.get("items")) {
+    for (i, item) in items.iter().enumerate() {
+      validate_at(root, item_schema, item, &format!("{}[{}]", path, i), out);
+    }
+  }
+}
+
+pub fn validate(schema: &Value, instance: &Value) -> Vec<String> {
+  let mut violations: Vec<String> = vec![];
+  validate_at(schema, schema, instance, "$", &mut violations);
+  violations
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::agents::agent_traits::{ProjectScope, RouteObject};
+  use serde_json::json;
+
+  #[test]
+  fn test_project_scope_schema_requires_bools() {
+    let schema: Value = schema_value::<ProjectScope>();
+
+    let valid: Value = json!({
+      "is_crud_required": true,
+      "is_user_login_and_logout": false,
+      "is_external_urls_required": false
+    });
+    assert!(validate(&schema, &valid).is_empty());
+
+    let invalid: Value = json!({
+      "is_crud_required": "yes",
+      "is_user_login_and_logout": false
+    });
+    let violations: Vec<String> = validate(&schema, &invalid);
+    assert_eq!(violations.len(), 2);
+    assert!(violations
+      .iter()
+      .any(|v| v.contains("is_external_urls_required")));
+    assert!(violations
+      .iter()
+      .any(|v| v.starts_with("$.is_crud_required")));
+  }
+
+  #[test]
+  fn test_route_list_schema_follows_refs() {
+    let schema: Value = schema_value::<Vec<RouteObject>>();
+
+    let invalid: Value = json!([{ "route": "/item", "method": "get" }]);
+    let violations: Vec<String> = validate(&schema, &invalid);
+    assert!(violations.iter().any(|v| v.contains("is_route_dynamic")));
+    assert!(violations.iter().all(|v| v.starts_with("$[0]")));
+  }
+
+  #[test]
+  fn test_url_rules_reject_non_urls() {
+    let urls: Vec<String> = vec![
+      "https://api.binance.com/api/v3/exchangeInfo".to_string(),
+      "binance api".to_string(),
+    ];
+    let violations: Vec<String> = urls.rule_violations();
+    assert_eq!(violations.len(), 1);
+    assert!(violations[0].starts_with("$[1]"));
+  }
+}
@@ -0,0 +1,133 @@
+use crate::models::general::error::AutoGippityError;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::time::Duration;
+use tokio::io::copy_bidirectional;
+use tokio::net::{TcpListener, TcpStream, UnixListener, UnixStream};
+use tokio::process::{Child, Command};
+use tokio::task::JoinHandle;
+use tokio::time;
+
+const RELAY_POLL: Duration = Duration::from_millis(100);
+// sun_path holds 108 bytes including the trailing nul
+const MAX_SOCKET_PATH: usize = 107;
+
+pub fn check_socket_path(socket: &Path) -> Result<(), AutoGippityError> {
+  if socket.as_os_str().len() > MAX_SOCKET_PATH {
+    return Err(AutoGippityError::Config(format!(
+      "sandbox dir {} is too long for the server's relay socket, set [sandbox] dir to a shorter path",
+      socket.parent().unwrap_or(socket).display()
+    )));
+  }
+  Ok(())
+}
+
+// inside the sandbox: run the server and expose its loopback port on a unix socket in the workspace
+pub async fn serve_inside(socket: PathBuf, port: u16, command: Vec<String>) -> Result<ExitStatus, AutoGippityError> {
+  let (program, args): (&String, &[String]) = command
+    .split_first()
+    .ok_or_else(|| AutoGippityError::Config("sandbox relay needs a command to run".to_string()))?;
+  let mut child: Child = Command::new(program)
+    .args(args)
+    .kill_on_drop(true)
+    .spawn()
+    .map_err(|e| AutoGippityError::Runtime(format!("failed to run {}: {}", program, e)))?;
+
+  // the socket only appears once the server listens, so the host port does not open early
+  while TcpStream::connect(("127.0.0.1", port)).await.is_err() {
+    if let Some(status) = child.try_wait()? {
+      return Ok(status);
+    }
+    time::sleep(RELAY_POLL).await;
+  }
+
+  let _ = fs::remove_file(&socket);
+  let listener: UnixListener = UnixListener::bind(&socket)?;
+  let relay: JoinHandle<()> = tokio::spawn(async move {
+    while let Ok((mut inbound, _)) = listener.accept().await {
+      tokio::spawn(async move {
+        if let Ok(mut server) = TcpStream::connect(("127.0.0.1", port)).await {
+          let _ = copy_bidirectional(&mut inbound, &mut server).await;
+        }
+      });
+    }
+  });
+
+  let status: ExitStatus = child.wait().await?;
+  relay.abort();
+  let _ = fs::remove_file(&socket);
+  Ok(status)
+}
+
+// on the host: listen on the port once the sandboxed server is up and pass each connection through the socket
+pub fn forward_port(socket: PathBuf, port: u16) -> JoinHandle<()> {
+  tokio::spawn(async move {
+    while UnixStream::connect(&socket).await.is_err() {
+      time::sleep(RELAY_POLL).await;
+    }
+    // nothing listens when the port was taken meanwhile, readiness then times out
+    let Ok(listener) = TcpListener::bind(("127.0.0.1", port)).await else {
+      return;
+    };
+    while let Ok((mut inbound, _)) = listener.accept().await {
+      let socket: PathBuf = socket.clone();
+      tokio::spawn(async move {
+        if let Ok(mut sandboxed) = UnixStream::connect(&socket).await {
+          let _ = copy_bidirectional(&mut inbound, &mut sandboxed).await;
+        }
+      });
+    }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::helpers::server::free_port;
+  use crate::helpers::test_app::TestApp;
+  use crate::helpers::test_support::TempDir;
+
+  #[tokio::test]
+  async fn test_relays_host_port_through_socket() {
+    let app: TestApp = TestApp::start().await;
+    let app_port: u16 = app.port();
+    let temp: TempDir = TempDir::new("relay");
+    let socket: PathBuf = temp.join("server.sock");
+    assert!(check_socket_path(&socket).is_ok());
+    assert!(check_socket_path(&Path::new("/tmp").join("x".repeat(120))).is_err());
+
+    // the test app stands in for the server inside the sandbox
+    let inside: JoinHandle<Result<ExitStatus, AutoGippityError>> =
+      tokio::spawn(serve_inside(socket.clone(), app_port, vec!["sleep".to_string(), "30".to_string()]));
+    let host_port: u16 = free_port().unwrap();
+    let forward: JoinHandle<()> = forward_port(socket.clone(), host_port);
+
+    let url: String = format!("http://127.0.0.1:{}/status/201", host_port);
+    let mut status: Option<u16> = None;
+    for _ in 0..50 {
+      if let Ok(res) = reqwest::get(&url).await {
+        status = Some(res.status().as_u16());
+        break;
+      }
+      time::sleep(RELAY_POLL).await;
+    }
+    assert_eq!(status, Some(201));
+
+    forward.abort();
+    inside.abort();
+  }
+
+  #[tokio::test]
+  async fn test_returns_when_server_exits_before_listening() {
+    let temp: TempDir = TempDir::new("relay_exit");
+    let socket: PathBuf = temp.join("server.sock");
+    let port: u16 = free_port().unwrap();
+    let command: Vec<String> = vec!["sh".to_string(), "-c".to_string(), "exit 3".to_string()];
+
+    let status: ExitStatus = serve_inside(socket.clone(), port, command).await.unwrap();
+    assert_eq!(status.code(), Some(3));
+    assert!(!socket.exists());
+  }
+}
@@ -0,0 +1,97 @@
+use crate::models::general::error::AutoGippityError;
+
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Serialize)]
+struct RunLogEntry<'a> {
+  timestamp: u64,
+  agent: &'a str,
+  event: &'a str,
+  detail: Value,
+}
+
+// append only json lines log of decisions made during a run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunLog {
+  // nothing is written when there is no run, eg. in tests
+  path: Option<PathBuf>,
+}
+
+impl RunLog {
+  pub fn new(path: PathBuf) -> Self {
+    Self { path: Some(path) }
+  }
+
+  pub fn record<T: Serialize>(
+    &self,
+    agent: &str,
+    event: &str,
+    detail: &T,
+  ) -> Result<(), AutoGippityError> {
+    let Some(path) = &self.path else {
+      return Ok(());
+    };
+
+    let entry: RunLogEntry = RunLogEntry {
+      timestamp: SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0),
+      agent,
+      event,
+      detail: serde_json::to_value(detail)?,
+    };
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::helpers::test_support::TempDir;
+  use serde_json::json;
+  use std::fs;
+
+  #[test]
+  fn test_appends_json_lines() {
+    let temp: TempDir = TempDir::new("run_log");
+    let path: PathBuf = temp.join("run.log");
+
+    let run_log: RunLog = RunLog::new(path.clone());
+    run_log
+      .record(
+        "Backend Developer",
+        "approval",
+        &json!({ "approved": true }),
+      )
+      .unwrap();
+    run_log
+      .record(
+        "Backend Developer",
+        "approval",
+        &json!({ "approved": false }),
+      )
+      .unwrap();
+
+    let contents: String = fs::read_to_string(&path).unwrap();
+    let lines: Vec<Value> = contents
+      .lines()
+      .map(|line| serde_json::from_str(line).unwrap())
+      .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1]["detail"]["approved"], json!(false));
+    assert_eq!(lines[0]["event"], json!("approval"));
+
+    RunLog::default()
+      .record("Backend Developer", "approval", &json!({}))
+      .unwrap();
+  }
+}
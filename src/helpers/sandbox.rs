@@ -0,0 +1,559 @@
+use crate::helpers::general::copy_project;
+use crate::helpers::port_relay::{check_socket_path, forward_port};
+use crate::helpers::server::{ProcessGroup, ServerGuard};
+use crate::models::general::config::SandboxConfig;
+use crate::models::general::error::AutoGippityError;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tokio::time;
+
+// env variables passed through to sandboxed commands, everything else (eg. api keys) is dropped
+const KEPT_ENV: [&str; 8] = [
+  "PATH",
+  "HOME",
+  "USER",
+  "LANG",
+  "TERM",
+  "CARGO_HOME",
+  "RUSTUP_HOME",
+  "RUSTUP_TOOLCHAIN",
+];
+
+// how generated code is isolated while it is built and run
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxBackend {
+  // bubblewrap when installed, then firejail, then none
+  Auto,
+  Bubblewrap,
+  Firejail,
+  // copied workspace and resource limits only
+  None,
+}
+
+impl FromStr for SandboxBackend {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_lowercase().as_str() {
+      "auto" => Ok(Self::Auto),
+      "bubblewrap" | "bwrap" => Ok(Self::Bubblewrap),
+      "firejail" => Ok(Self::Firejail),
+      "none" | "off" => Ok(Self::None),
+      other => Err(format!(
+        "unknown sandbox backend '{}', expected one of auto, bubblewrap, firejail, none",
+        other
+      )),
+    }
+  }
+}
+
+// hidden subcommand that runs the server inside the sandbox and relays its port
+pub const RELAY_SUBCOMMAND: &str = "sandbox-relay";
+
+// network access for a sandboxed command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Network {
+  // loopback only, the command gets its own network namespace
+  Offline,
+  // only for cargo fetch, which downloads dependencies
+  Host,
+}
+
+// the part of an environment a sandboxed command may see
+fn kept_env(vars: impl Iterator<Item = (OsString, OsString)>) -> Vec<(OsString, OsString)> {
+  vars
+    .filter(|(name, _)| KEPT_ENV.iter().any(|kept| name == kept))
+    .collect()
+}
+
+// hide the user's files like bwrap's tmpfs, whitelisting keeps only the given paths under home
+fn firejail_home_args(home: &Path, needed: &[&Path]) -> Vec<OsString> {
+  let kept: Vec<OsString> = needed
+    .iter()
+    .copied()
+    .filter(|path| path.starts_with(home) && *path != home)
+    .map(|path| OsString::from(format!("--whitelist={}", path.display())))
+    .collect();
+  if kept.is_empty() {
+    vec![OsString::from(format!("--blacklist={}", home.display()))]
+  } else {
+    kept
+  }
+}
+
+pub fn find_on_path(program: &str) -> Option<PathBuf> {
+  let paths: OsString = env::var_os("PATH")?;
+  env::split_paths(&paths)
+    .map(|dir| dir.join(program))
+    .find(|candidate| candidate.is_file())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sandbox {
+  backend: SandboxBackend,
+  // copy of the project, the only writable place besides cargo's cache
+  root: PathBuf,
+  cpu_secs: u64,
+  memory_mb: u64,
+  build_timeout: Duration,
+}
+
+impl Sandbox {
+  pub fn new(config: &SandboxConfig, root: PathBuf) -> Self {
+    let backend: SandboxBackend = match config.backend {
+      SandboxBackend::Auto if find_on_path("bwrap").is_some() => SandboxBackend::Bubblewrap,
+      SandboxBackend::Auto if find_on_path("firejail").is_some() => SandboxBackend::Firejail,
+      SandboxBackend::Auto => SandboxBackend::None,
+      backend => backend,
+    };
+
+    Self {
+      backend,
+      root: std::path::absolute(&root).unwrap_or(root),
+      cpu_secs: config.cpu_secs,
+      memory_mb: config.memory_mb,
+      build_timeout: Duration::from_secs(config.build_timeout_secs),
+    }
+  }
+
+  pub fn backend(&self) -> SandboxBackend {
+    self.backend
+  }
+
+  pub fn root(&self) -> &Path {
+    &self.root
+  }
+
+  // the bound workspace is the one path both network namespaces share
+  fn relay_socket(&self) -> PathBuf {
+    self.root.join(".server.sock")
+  }
+
+  // true when filesystem and network isolation is applied, not only limits
+  pub fn is_isolated(&self) -> bool {
+    self.backend != SandboxBackend::None
+  }
+
+  // refresh the copy from the project, target/ is kept so builds stay incremental
+  pub fn sync_from(&self, project_dir: &Path) -> Result<(), AutoGippityError> {
+    fs::create_dir_all(&self.root)?;
+    for entry in fs::read_dir(&self.root)? {
+      let entry: fs::DirEntry = entry?;
+      if entry.file_name() == "target" {
+        continue;
+      }
+      if entry.file_type()?.is_dir() {
+        fs::remove_dir_all(entry.path())?;
+      } else {
+        fs::remove_file(entry.path())?;
+      }
+    }
+
+    copy_project(project_dir, &self.root)?;
+    Ok(())
+  }
+
+  // program and arguments that wrap a command with the isolation tool
+  fn wrapper_args(&self, network: Network) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec![];
+    let mut push = |values: &[&dyn AsRef<std::ffi::OsStr>]| {
+      args.extend(values.iter().map(|value| value.as_ref().to_os_string()))
+    };
+
+    let home: Option<PathBuf> = env::var_os("HOME")
+      .map(PathBuf::from)
+      .filter(|home| home != Path::new("/"));
+    let cargo_home: Option<PathBuf> = env::var_os("CARGO_HOME")
+      .map(PathBuf::from)
+      .or_else(|| home.as_ref().map(|home| home.join(".cargo")));
+    let rustup_home: Option<PathBuf> = env::var_os("RUSTUP_HOME")
+      .map(PathBuf::from)
+      .or_else(|| home.as_ref().map(|home| home.join(".rustup")));
+
+    match self.backend {
+      SandboxBackend::Bubblewrap => {
+        push(&[&"bwrap", &"--die-with-parent", &"--unshare-all"]);
+        if network == Network::Host {
+          push(&[&"--share-net"]);
+        }
+        push(&[
+          &"--ro-bind",
+          &"/",
+          &"/",
+          &"--dev",
+          &"/dev",
+          &"--proc",
+          &"/proc",
+          &"--tmpfs",
+          &"/tmp",
+        ]);
+        // hide the user's files, then put back only what cargo needs
+        if let Some(home) = &home {
+          push(&[&"--tmpfs", home]);
+        }
+        if let Some(rustup_home) = rustup_home.as_ref().filter(|dir| dir.exists()) {
+          push(&[&"--ro-bind", rustup_home, rustup_home]);
+        }
+        if let Some(cargo_home) = cargo_home.as_ref().filter(|dir| dir.exists()) {
+          push(&[&"--bind", cargo_home, cargo_home]);
+        }
+        // the relay is this binary, which may live under the hidden home
+        if let Ok(exe) = env::current_exe() {
+          push(&[&"--ro-bind", &exe, &exe]);
+        }
+        push(&[
+          &"--bind",
+          &self.root,
+          &self.root,
+          &"--chdir",
+          &self.root,
+          &"--",
+        ]);
+      }
+      SandboxBackend::Firejail => {
+        push(&[&"firejail", &"--quiet", &"--noprofile", &"--private-tmp"]);
+        if network == Network::Offline {
+          push(&[&"--net=none"]);
+        }
+        push(&[&"--read-only=/"]);
+        if let Some(home) = &home {
+          let exe: Option<PathBuf> = env::current_exe().ok();
+          let needed: Vec<&Path> = [rustup_home.as_deref(), cargo_home.as_deref(), exe.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter(|dir| dir.exists())
+            .chain([self.root.as_path()])
+            .collect();
+          args.extend(firejail_home_args(home, &needed));
+        }
+        if let Some(cargo_home) = cargo_home.as_ref().filter(|dir| dir.exists()) {
+          args.push(OsString::from(format!("--read-write={}", cargo_home.display())));
+        }
+        args.push(OsString::from(format!("--read-write={}", self.root.display())));
+        args.push(OsString::from("--"));
+      }
+      SandboxBackend::Auto | SandboxBackend::None => {}
+    }
+    args
+  }
+
+  // isolated command with limits applied, runs inside the copied workspace
+  pub fn command(&self, program: &str, args: &[&str], network: Network) -> Command {
+    let mut wrapped: Vec<OsString> = self.wrapper_args(network);
+    wrapped.push(OsString::from(program));
+    wrapped.extend(args.iter().map(OsString::from));
+
+    let mut command: Command = Command::new(&wrapped[0]);
+    command
+      .args(&wrapped[1..])
+      .current_dir(&self.root)
+      .env_clear()
+      .envs(kept_env(env::vars_os()))
+      .kill_on_drop(true);
+
+    #[cfg(unix)]
+    {
+      let cpu_secs: u64 = self.cpu_secs;
+      let memory_bytes: u64 = self.memory_mb.saturating_mul(1024 * 1024);
+      // safety: only async signal safe setpgid and setrlimit calls between fork and exec
+      unsafe {
+        command.pre_exec(move || {
+          // own process group, so the whole tree can be stopped with one signal
+          if libc::setpgid(0, 0) != 0 {
+            return Err(std::io::Error::last_os_error());
+          }
+          if cpu_secs > 0 {
+            let limit: libc::rlimit = libc::rlimit {
+              rlim_cur: cpu_secs as libc::rlim_t,
+              rlim_max: cpu_secs as libc::rlim_t,
+            };
+            if libc::setrlimit(libc::RLIMIT_CPU, &limit) != 0 {
+              return Err(std::io::Error::last_os_error());
+            }
+          }
+          if memory_bytes > 0 {
+            let limit: libc::rlimit = libc::rlimit {
+              rlim_cur: memory_bytes as libc::rlim_t,
+              rlim_max: memory_bytes as libc::rlim_t,
+            };
+            if libc::setrlimit(libc::RLIMIT_AS, &limit) != 0 {
+              return Err(std::io::Error::last_os_error());
+            }
+          }
+          Ok(())
+        });
+      }
+    }
+    command
+  }
+
+  // build without network, dependencies are fetched first with only network access allowed
+  pub async fn cargo_build(&self) -> Result<Output, AutoGippityError> {
+    self.cargo_offline("build").await
+  }
+
+  // compile and run the project's tests, the same way as a build
+  pub async fn cargo_test(&self) -> Result<Output, AutoGippityError> {
+    self.cargo_offline("test").await
+  }
+
+  async fn cargo_offline(&self, subcommand: &str) -> Result<Output, AutoGippityError> {
+    let build_args: &[&str] = if self.is_isolated() {
+      // fetch only downloads, it runs no build scripts or generated code
+      let fetch: Output = self
+        .command("cargo", &["fetch"], Network::Host)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| AutoGippityError::Runtime(format!("failed to run cargo fetch: {}", e)))?;
+      if !fetch.status.success() {
+        return Err(AutoGippityError::Runtime(format!(
+          "cargo fetch failed: {}",
+          String::from_utf8_lossy(&fetch.stderr)
+        )));
+      }
+      &[subcommand, "--offline"]
+    } else {
+      &[subcommand]
+    };
+
+    let build: Child = self
+      .command("cargo", build_args, Network::Offline)
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|e| AutoGippityError::Runtime(format!("failed to run cargo {}: {}", subcommand, e)))?;
+    // rustc and build scripts are stopped with cargo on timeout or ctrl-c
+    let _build_group: ProcessGroup = ProcessGroup::of(&build);
+
+    match time::timeout(self.build_timeout, build.wait_with_output()).await {
+      Ok(output) => {
+        output.map_err(|e| AutoGippityError::Runtime(format!("failed to run cargo {}: {}", subcommand, e)))
+      }
+      Err(_) => Err(AutoGippityError::Runtime(format!(
+        "cargo {} timed out after {} seconds",
+        subcommand,
+        self.build_timeout.as_secs()
+      ))),
+    }
+  }
+
+  // the server gets loopback only, the relay puts its port on the host's 127.0.0.1
+  fn server_command(&self, port: u16) -> Result<Command, AutoGippityError> {
+    if !self.is_isolated() {
+      return Ok(self.command("cargo", &["run", "--offline"], Network::Offline));
+    }
+
+    let relay: PathBuf = env::current_exe()?;
+    let socket: PathBuf = self.relay_socket();
+    check_socket_path(&socket)?;
+    let _ = fs::remove_file(&socket);
+
+    let port: String = port.to_string();
+    let args: [&str; 9] = [
+      RELAY_SUBCOMMAND,
+      "--socket",
+      &socket.to_string_lossy(),
+      "--port",
+      &port,
+      "--",
+      "cargo",
+      "run",
+      "--offline",
+    ];
+    Ok(self.command(&relay.to_string_lossy(), &args, Network::Offline))
+  }
+
+  // start the built server on port, stopped with its relay when the guard goes
+  pub fn cargo_run(&self, port: u16) -> Result<ServerGuard, AutoGippityError> {
+    let server: Child = self
+      .server_command(port)?
+      .env("PORT", port.to_string())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|e| AutoGippityError::Runtime(format!("failed to run cargo run: {}", e)))?;
+
+    let guard: ServerGuard = ServerGuard::new(server);
+    if !self.is_isolated() {
+      return Ok(guard);
+    }
+    Ok(guard.with_relay(forward_port(self.relay_socket(), port)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::helpers::test_support::TempDir;
+
+  fn sandbox(backend: SandboxBackend, root: &str) -> Sandbox {
+    let config: SandboxConfig = SandboxConfig {
+      backend,
+      ..SandboxConfig::default()
+    };
+    Sandbox::new(&config, PathBuf::from(root))
+  }
+
+  fn args_of(sandbox: &Sandbox, network: Network) -> Vec<String> {
+    sandbox
+      .wrapper_args(network)
+      .iter()
+      .map(|arg| arg.to_string_lossy().to_string())
+      .collect()
+  }
+
+  #[test]
+  fn test_bubblewrap_args() {
+    let sandbox: Sandbox = sandbox(SandboxBackend::Bubblewrap, "/tmp/box");
+
+    let offline: Vec<String> = args_of(&sandbox, Network::Offline);
+    assert_eq!(offline[0], "bwrap");
+    assert!(offline.contains(&"--unshare-all".to_string()));
+    assert!(!offline.contains(&"--share-net".to_string()));
+    assert!(offline.windows(3).any(|w| w == ["--bind", "/tmp/box", "/tmp/box"]));
+    assert_eq!(offline.last().unwrap(), "--");
+
+    // only cargo fetch asks for host networking
+    let host: Vec<String> = args_of(&sandbox, Network::Host);
+    assert!(host.contains(&"--share-net".to_string()));
+  }
+
+  fn server_args(sandbox: &Sandbox) -> Vec<String> {
+    let command: Command = sandbox.server_command(8080).unwrap();
+    let std_command: &std::process::Command = command.as_std();
+    std::iter::once(std_command.get_program())
+      .chain(std_command.get_args())
+      .map(|arg| arg.to_string_lossy().to_string())
+      .collect()
+  }
+
+  #[test]
+  fn test_server_stays_offline_behind_relay() {
+    let bubblewrap: Vec<String> = server_args(&sandbox(SandboxBackend::Bubblewrap, "/tmp/box"));
+    assert!(bubblewrap.contains(&"--unshare-all".to_string()));
+    assert!(!bubblewrap.contains(&"--share-net".to_string()));
+    let relay: usize = bubblewrap.iter().position(|arg| arg == RELAY_SUBCOMMAND).unwrap();
+    assert_eq!(
+      bubblewrap[relay..],
+      [RELAY_SUBCOMMAND, "--socket", "/tmp/box/.server.sock", "--port", "8080", "--", "cargo", "run", "--offline"]
+    );
+
+    let firejail: Vec<String> = server_args(&sandbox(SandboxBackend::Firejail, "/tmp/box"));
+    assert!(firejail.contains(&"--net=none".to_string()));
+    assert!(firejail.contains(&RELAY_SUBCOMMAND.to_string()));
+
+    // without isolation there is no namespace to relay out of
+    let none: Vec<String> = server_args(&sandbox(SandboxBackend::None, "/tmp/box"));
+    assert_eq!(none, ["cargo", "run", "--offline"]);
+
+    let long_root: String = format!("/tmp/{}", "x".repeat(120));
+    assert!(sandbox(SandboxBackend::Bubblewrap, &long_root).server_command(8080).is_err());
+  }
+
+  #[test]
+  fn test_firejail_and_none_args() {
+    let firejail: Sandbox = sandbox(SandboxBackend::Firejail, "/tmp/box");
+    let offline: Vec<String> = args_of(&firejail, Network::Offline);
+    assert!(offline.contains(&"--net=none".to_string()));
+    assert!(offline.contains(&"--read-write=/tmp/box".to_string()));
+    assert!(!args_of(&firejail, Network::Host).contains(&"--net=none".to_string()));
+
+    let home: &Path = Path::new("/home/dev");
+    let cargo_home: &Path = Path::new("/home/dev/.cargo");
+    assert_eq!(
+      firejail_home_args(home, &[cargo_home, Path::new("/tmp/box")]),
+      vec![OsString::from("--whitelist=/home/dev/.cargo")]
+    );
+    assert_eq!(
+      firejail_home_args(home, &[Path::new("/opt/cargo"), Path::new("/tmp/box")]),
+      vec![OsString::from("--blacklist=/home/dev")]
+    );
+
+    let none: Sandbox = sandbox(SandboxBackend::None, "/tmp/box");
+    assert!(none.wrapper_args(Network::Offline).is_empty());
+    assert!(!none.is_isolated());
+    assert_eq!(
+      SandboxBackend::from_str("bwrap"),
+      Ok(SandboxBackend::Bubblewrap)
+    );
+  }
+
+  #[test]
+  fn test_sync_keeps_target_and_drops_stale_files() {
+    let base: TempDir = TempDir::new("sandbox");
+    let project_dir: PathBuf = base.join("project");
+    fs::create_dir_all(project_dir.join("src")).unwrap();
+    fs::create_dir_all(project_dir.join("target")).unwrap();
+    fs::write(project_dir.join("Cargo.toml"), "[package]\n").unwrap();
+    fs::write(project_dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(project_dir.join("target/big"), "artifact").unwrap();
+
+    let sandbox: Sandbox = sandbox(SandboxBackend::None, base.join("box").to_str().unwrap());
+    fs::create_dir_all(sandbox.root().join("target")).unwrap();
+    fs::write(sandbox.root().join("target/cached"), "cached").unwrap();
+    fs::write(sandbox.root().join("stale.rs"), "").unwrap();
+
+    sandbox.sync_from(&project_dir).unwrap();
+
+    assert_eq!(
+      fs::read_to_string(sandbox.root().join("src/main.rs")).unwrap(),
+      "fn main() {}\n"
+    );
+    assert!(sandbox.root().join("target/cached").exists());
+    assert!(!sandbox.root().join("target/big").exists());
+    assert!(!sandbox.root().join("stale.rs").exists());
+  }
+
+  #[test]
+  fn test_kept_env_drops_secrets() {
+    let vars: Vec<(OsString, OsString)> = vec![
+      (OsString::from("PATH"), OsString::from("/usr/bin")),
+      (OsString::from("AUTO_GIPPITY_SANDBOX_SECRET"), OsString::from("leaked")),
+      (OsString::from("OPEN_AI_KEY"), OsString::from("sk-test")),
+      (OsString::from("CARGO_HOME"), OsString::from("/cargo")),
+    ];
+
+    let kept: Vec<(OsString, OsString)> = kept_env(vars.into_iter());
+    assert_eq!(
+      kept,
+      [
+        (OsString::from("PATH"), OsString::from("/usr/bin")),
+        (OsString::from("CARGO_HOME"), OsString::from("/cargo")),
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_command_runs_in_root_without_secrets() {
+    let base: TempDir = TempDir::new("sandbox_env");
+    let sandbox: Sandbox = sandbox(SandboxBackend::None, base.path().to_str().unwrap());
+
+    // cargo sets this for the test process, the command must not see it
+    let output: Output = sandbox
+      .command(
+        "sh",
+        &["-c", "pwd; echo ${CARGO_MANIFEST_DIR:-none}; ulimit -t"],
+        Network::Offline,
+      )
+      .output()
+      .await
+      .unwrap();
+    let stdout: String = String::from_utf8_lossy(&output.stdout).to_string();
+    let lines: Vec<&str> = stdout.lines().collect();
+
+    assert_eq!(lines[0], sandbox.root().to_str().unwrap());
+    assert_eq!(lines[1], "none");
+    assert_eq!(lines[2], SandboxConfig::default().cpu_secs.to_string());
+  }
+}
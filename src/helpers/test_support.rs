@@ -0,0 +1,200 @@
+use crate::helpers::approval::ApprovalPolicy;
+use crate::helpers::sandbox::SandboxBackend;
+use crate::models::agents::agent_traits::{FactSheet, ProjectScope};
+use crate::models::general::config::Config;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// empty directory for one test, removed when dropped so a failing test does not leave it behind
+pub struct TempDir {
+  path: PathBuf,
+}
+
+impl TempDir {
+  // name keeps tests apart, the process id keeps parallel test runs apart
+  pub fn new(name: &str) -> Self {
+    let path: PathBuf =
+      std::env::temp_dir().join(format!("auto_gippity_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    Self { path }
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn join<P: AsRef<Path>>(&self, relative: P) -> PathBuf {
+    self.path.join(relative)
+  }
+}
+
+impl Drop for TempDir {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir_all(&self.path);
+  }
+}
+
+// factsheet with only the description set, tests fill in the rest with struct update syntax
+pub fn factsheet(project_description: &str) -> FactSheet {
+  FactSheet {
+    project_description: project_description.to_string(),
+    project_scope: None,
+    external_urls: None,
+    database_schema: None,
+    backend_code: None,
+    api_endpoint_schema: None,
+    frontend_code: None,
+    security_findings: None,
+  }
+}
+
+// stand-in for ../web_template, a std only server so generated code builds without actix.
+// the synthetic cassette was written by hand against it, not recorded from a provider
+const TEMPLATE_MANIFEST: &str = r#"[package]
+name = "web_template"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+serde = { version = "1", features = ["derive"] }
+serde_json = "1"
+"#;
+
+const TEMPLATE_SERVER: &str = r#"use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+pub struct Request {
+  pub method: String,
+  pub path: String,
+  pub authorization: Option<String>,
+  pub body: String,
+}
+
+pub struct Response {
+  pub status: u16,
+  pub content_type: &'static str,
+  pub body: String,
+}
+
+fn json_response(status: u16, body: Value) -> Response {
+  Response {
+    status,
+    content_type: "application/json",
+    body: body.to_string(),
+  }
+}
+
+pub fn handle(request: &Request) -> Response {
+  match (request.method.as_str(), request.path.as_str()) {
+    ("GET", "/health") => json_response(200, json!({ "status": "ok" })),
+    _ => json_response(404, json!({ "error": "not found" })),
+  }
+}
+
+fn read_request(stream: &TcpStream) -> std::io::Result<Request> {
+  let mut reader = BufReader::new(stream);
+  let mut request_line = String::new();
+  reader.read_line(&mut request_line)?;
+  let mut parts = request_line.split_whitespace();
+  let method: String = parts.next().unwrap_or_default().to_string();
+  let target: &str = parts.next().unwrap_or("/");
+  let path: String = target.split('?').next().unwrap_or("/").to_string();
+
+  let mut content_length: usize = 0;
+  let mut authorization: Option<String> = None;
+  loop {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+      break;
+    }
+    if let Some((name, value)) = line.split_once(':') {
+      match name.trim().to_ascii_lowercase().as_str() {
+        "content-length" => content_length = value.trim().parse().unwrap_or(0),
+        "authorization" => authorization = Some(value.trim().to_string()),
+        _ => {}
+      }
+    }
+  }
+
+  let mut body = vec![0; content_length];
+  reader.read_exact(&mut body)?;
+  Ok(Request {
+    method,
+    path,
+    authorization,
+    body: String::from_utf8_lossy(&body).to_string(),
+  })
+}
+
+fn write_response(mut stream: &TcpStream, response: &Response) -> std::io::Result<()> {
+  write!(
+    stream,
+    "HTTP/1.1 {} OK
+Content-Type: {}
+Content-Length: {}
+Access-Control-Allow-Origin: *
+Connection: close
+
+{}",
+    response.status,
+    response.content_type,
+    response.body.len(),
+    response.body
+  )
+}
+
+fn main() -> std::io::Result<()> {
+  let port: String = std::env::var("PORT").unwrap_or_else(|_| "8080".to_string());
+  let listener = TcpListener::bind(format!("127.0.0.1:{}", port))?;
+  for stream in listener.incoming() {
+    let stream: TcpStream = stream?;
+    if let Ok(request) = read_request(&stream) {
+      let _ = write_response(&stream, &handle(&request));
+    }
+  }
+  Ok(())
+}
+"#;
+
+// write the stand-in template into project_dir, with the example server as both template and main.rs
+pub fn web_template(project_dir: &Path) {
+  fs::create_dir_all(project_dir.join("src")).unwrap();
+  fs::write(project_dir.join("Cargo.toml"), TEMPLATE_MANIFEST).unwrap();
+  fs::write(project_dir.join("src/code_template.rs"), TEMPLATE_SERVER).unwrap();
+  fs::write(project_dir.join("src/main.rs"), TEMPLATE_SERVER).unwrap();
+}
+
+// build and run generated code without asking, unsandboxed as the relay is not part of the test binary
+pub fn unattended(mut config: Config) -> Config {
+  config.interactive = false;
+  config.approval.policy = ApprovalPolicy::Allowlist;
+  config.sandbox.backend = SandboxBackend::None;
+  config
+}
+
+pub fn scope(is_crud_required: bool, is_user_login_and_logout: bool) -> Option<ProjectScope> {
+  Some(ProjectScope {
+    is_crud_required,
+    is_user_login_and_logout,
+    is_external_urls_required: false,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_temp_dir_is_removed_on_drop() {
+    let temp: TempDir = TempDir::new("temp_dir_drop");
+    let path: PathBuf = temp.path().to_path_buf();
+    fs::write(temp.join("file"), "contents").unwrap();
+    assert!(path.join("file").is_file());
+
+    drop(temp);
+    assert!(!path.exists());
+  }
+}
@@ -11,19 +11,142 @@ mod apis;
 mod helpers;
 mod models;
 
-use helpers::command_line::get_user_response;
+use clap::Parser;
+use helpers::command_line::{get_user_response, Cli, CliCommand, PrintCommand};
+use helpers::port_relay::serve_inside;
+use helpers::server::request_shutdown;
 
+use models::agents::agent_architect::AgentSolutionArchitect;
+use models::agents::agent_auth::AgentAuthDeveloper;
+use models::agents::agent_backend::AgentBackendDeveloper;
+use models::agents::agent_database::AgentDatabaseDeveloper;
+use models::agents::agent_frontend::AgentFrontendDeveloper;
+use models::agents::agent_qa::AgentQaEngineer;
+use models::agents::agent_security::AgentSecurityReviewer;
+use models::agents_manager::checkpoint::{clean_runs, RunDir};
 use models::agents_manager::managing_agent::ManagingAgent;
+use models::general::config::Config;
+use models::general::error::AutoGippityError;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// how long ctrl-c waits for the run to stop its server before aborting it
+const SHUTDOWN_WAIT: Duration = Duration::from_secs(30);
+
+// prompt from the command line, or asked for when input is allowed
+fn user_request(prompt: Option<String>, config: &Config) -> Result<String, AutoGippityError> {
+  match prompt {
+    Some(prompt) => Ok(prompt),
+    None if config.interactive => Ok(get_user_response("what website are we building today?")),
+    None => Err(AutoGippityError::Config(
+      "a prompt is required when running non-interactively".to_string(),
+    )),
+  }
+}
+
+async fn new_project(prompt: Option<String>, config: Config) -> Result<ManagingAgent, AutoGippityError> {
+  let user_req: String = user_request(prompt, &config)?;
+  let run_dir: RunDir = RunDir::create(&config.workspace.runs_dir)?;
+  ManagingAgent::new(user_req, run_dir, config).await
+}
+
+fn open_project(run_path: &Path, config: Config) -> Result<ManagingAgent, AutoGippityError> {
+  ManagingAgent::resume(RunDir::open(run_path)?, config)
+}
+
+// run one agent of a saved run again from its start, checkpointing as it goes
+async fn rerun_agent(run_path: &Path, config: Config, position: &str) -> Result<(), AutoGippityError> {
+  let mut managing_agent: ManagingAgent = open_project(run_path, config)?;
+  managing_agent.restart_agent(position);
+  managing_agent.execute_agent(position).await
+}
+
+async fn run() -> Result<(), AutoGippityError> {
+  let cli: Cli = Cli::parse();
+
+  // runs inside the sandbox, where the user's config is hidden
+  if let Some(CliCommand::SandboxRelay { socket, port, command }) = cli.command {
+    let status: std::process::ExitStatus = serve_inside(socket, port, command).await?;
+    std::process::exit(status.code().unwrap_or(1));
+  }
+
+  let config: Config = Config::load(&cli.overrides())?;
+
+  match cli.command.unwrap_or(CliCommand::New { prompt: None }) {
+    CliCommand::New { prompt } => new_project(prompt, config).await?.execute_project().await,
+    CliCommand::Resume { run_dir } => {
+      let resume_msg: String = format!("Resuming run from {}", run_dir.display());
+      PrintCommand::AICall.print_agent_message("Project Manager", resume_msg.as_str());
+      open_project(&run_dir, config)?.execute_project().await
+    }
+    CliCommand::Scope { prompt } => {
+      new_project(prompt, config)
+        .await?
+        .execute_agent(AgentSolutionArchitect::POSITION)
+        .await
+    }
+    CliCommand::Database { run_dir } => rerun_agent(&run_dir, config, AgentDatabaseDeveloper::POSITION).await,
+    CliCommand::Backend { run_dir } => rerun_agent(&run_dir, config, AgentBackendDeveloper::POSITION).await,
+    CliCommand::Auth { run_dir } => rerun_agent(&run_dir, config, AgentAuthDeveloper::POSITION).await,
+    CliCommand::Qa { run_dir } => rerun_agent(&run_dir, config, AgentQaEngineer::POSITION).await,
+    CliCommand::Frontend { run_dir } => rerun_agent(&run_dir, config, AgentFrontendDeveloper::POSITION).await,
+    CliCommand::Review { run_dir } => rerun_agent(&run_dir, config, AgentSecurityReviewer::POSITION).await,
+    CliCommand::TestEndpoints { run_dir } => open_project(&run_dir, config)?.test_endpoints().await,
+    CliCommand::Report { run_dir } => {
+      println!("{}", open_project(&run_dir, config)?.report());
+      Ok(())
+    }
+    CliCommand::Openapi { run_dir } => {
+      let document: serde_json::Value = open_project(&run_dir, config)?.openapi()?;
+      println!("{}", serde_json::to_string_pretty(&document)?);
+      Ok(())
+    }
+    CliCommand::List => {
+      for run_dir in RunDir::list(&config.workspace.runs_dir)? {
+        println!("{}", run_dir.summary());
+      }
+      Ok(())
+    }
+    CliCommand::Clean {
+      keep,
+      older_than_days,
+      dry_run,
+    } => {
+      let older_than: Option<Duration> =
+        older_than_days.map(|days| Duration::from_secs(days * 24 * 60 * 60));
+      let removed: Vec<PathBuf> =
+        clean_runs(&config.workspace.runs_dir, keep, older_than, dry_run)?;
+      for path in &removed {
+        println!("{} {}", if dry_run { "would remove" } else { "removed" }, path.display());
+      }
+      Ok(())
+    }
+    CliCommand::SandboxRelay { .. } => unreachable!("handled before the config is loaded"),
+  }
+}
 
 #[tokio::main]
 async fn main() {
-  let user_req: String = get_user_response("what website are we building today?");
+  // run on its own task so ctrl-c is still noticed while it blocks on stdin
+  let mut run_task: tokio::task::JoinHandle<Result<(), AutoGippityError>> = tokio::spawn(run());
 
-  let mut managing_agent: ManagingAgent = ManagingAgent::new(user_req)
-    .await
-    .expect("Error Creating Agent");
-
-  managing_agent.execute_project().await;
+  let res: Result<(), AutoGippityError> = tokio::select! {
+    joined = &mut run_task => joined.unwrap_or_else(|e| Err(AutoGippityError::Runtime(e.to_string()))),
+    _ = tokio::signal::ctrl_c() => {
+      // a running server is stopped with its grace period, the run then returns
+      request_shutdown();
+      if tokio::time::timeout(SHUTDOWN_WAIT, &mut run_task).await.is_err() {
+        // still blocked (eg. on stdin), dropping its guards kills any build or server
+        run_task.abort();
+        let _ = run_task.await;
+      }
+      Err(AutoGippityError::UserAbort("interrupted".to_string()))
+    }
+  };
 
-  // dbg!(managing_agent);
+  if let Err(e) = res {
+    let report: String = format!("Run failed ({}): {}", e.kind(), e);
+    PrintCommand::Issue.print_agent_message("Project Manager", report.as_str());
+    std::process::exit(e.exit_code());
+  }
 }
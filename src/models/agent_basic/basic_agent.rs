@@ -1,7 +1,8 @@
 use crate::models::agent_basic::basic_traits::BasicTraits;
 use crate::models::general::llm::Message;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum AgentState {
   Discovery,
   Working,
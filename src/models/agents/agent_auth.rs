@@ -0,0 +1,325 @@
+use crate::ai_functions::aifunc_auth::print_auth_code;
+use crate::ai_functions::aifunc_backend::print_fixed_code;
+use crate::helpers::auth_flow::{auth_routes, is_auth_route, AUTH_CONTRACT, AUTH_DEPENDENCIES};
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::endpoint_tests::EndpointResult;
+use crate::helpers::general::{
+  add_project_dependency, ai_task_request, read_exec_main_contents, read_generated_sources,
+  save_api_endpoints, save_backend_code, save_openapi_document, GeneratedSource,
+};
+use crate::helpers::llm_output::{extract_rust_code, verify_rust_syntax};
+use crate::helpers::openapi::openapi_document;
+use crate::helpers::run_log::RunLog;
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_backend::AgentBackendDeveloper;
+use crate::models::agents::agent_traits::{FactSheet, FixLoopState, RouteObject, SpecialFunctions};
+use crate::models::general::config::Config;
+use crate::models::general::error::AutoGippityError;
+
+use async_trait::async_trait;
+use std::process::Output;
+
+#[derive(Debug)]
+pub struct AgentAuthDeveloper {
+  attributes: BasicAgent,
+  config: Config,
+  run_log: RunLog,
+  fix_loop: FixLoopState,
+}
+
+impl AgentAuthDeveloper {
+  pub const POSITION: &'static str = "Auth Developer";
+
+  pub fn new(config: Config, run_log: RunLog) -> Self {
+    let attributes: BasicAgent = BasicAgent::new(
+      "Adds user signup, login and logout to the backend".to_string(),
+      Self::POSITION.to_string(),
+    );
+
+    Self {
+      attributes,
+      config,
+      run_log,
+      fix_loop: FixLoopState::default(),
+    }
+  }
+
+  // save generated code only if it parses, like the backend agent
+  fn accept_auth_code(&mut self, factsheet: &mut FactSheet, ai_response: &str) -> Result<bool, AutoGippityError> {
+    let code: String = extract_rust_code(ai_response);
+    match verify_rust_syntax(&code) {
+      Ok(()) => {
+        save_backend_code(&self.config.workspace, &code)?;
+        factsheet.backend_code = Some(code);
+        self.fix_loop.rejected_code = None;
+        Ok(true)
+      }
+      Err(parse_err) => {
+        self.fix_loop.rejected_code = Some(code);
+        self.accept_check_results("syntax check", vec![parse_err])
+      }
+    }
+  }
+
+  // build errors and failed auth steps are sent back to be fixed
+  fn accept_check_results(&mut self, check: &str, problems: Vec<String>) -> Result<bool, AutoGippityError> {
+    self.run_log.record(
+      &self.attributes.position,
+      "auth_checks",
+      &serde_json::json!({ "check": check, "problems": problems }),
+    )?;
+    if problems.is_empty() {
+      return Ok(true);
+    }
+
+    for problem in &problems {
+      let problem_msg: String = format!("Auth {} failed: {}", check, problem);
+      PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), problem_msg.as_str());
+    }
+
+    self
+      .fix_loop
+      .record_bug(problems.join("\n"), self.config.backend.bug_limit, "auth code")?;
+    Ok(false)
+  }
+
+  async fn call_auth_code(&mut self, factsheet: &mut FactSheet) -> Result<bool, AutoGippityError> {
+    let backend_code: String = read_exec_main_contents(&self.config.workspace)?;
+    let msg_context: String = format!(
+      "CODE_INPUT: {} \n AUTH_CONTRACT: {} \n",
+      backend_code, AUTH_CONTRACT
+    );
+
+    let ai_response: String = ai_task_request(
+      msg_context,
+      &self.attributes.position,
+      get_function_string!(print_auth_code),
+      print_auth_code,
+      &self.config.llm,
+    )
+    .await?;
+
+    self.accept_auth_code(factsheet, &ai_response)
+  }
+
+  async fn call_fix_auth_code(&mut self, factsheet: &mut FactSheet) -> Result<bool, AutoGippityError> {
+    let broken_code: Option<String> = self.fix_loop.take_broken_code(&factsheet.backend_code);
+    let msg_context: String = format!(
+      "BROKEN_CODE: {:?} \n AUTH_CONTRACT: {} \n ERROR_BUGS: {:?} \n
+            THIS FUNCTION ONLY OUTPUTS CODE. JUST OUTPUT THE CODE",
+      broken_code, AUTH_CONTRACT, self.fix_loop.bug_errors
+    );
+
+    let ai_response: String = ai_task_request(
+      msg_context,
+      &self.attributes.position,
+      get_function_string!(print_fixed_code),
+      print_fixed_code,
+      &self.config.llm,
+    )
+    .await?;
+
+    self.accept_auth_code(factsheet, &ai_response)
+  }
+
+  // build with the backend agent's sandbox, then run its endpoint tests with the auth flow added
+  async fn test_auth(&mut self, factsheet: &mut FactSheet) -> Result<bool, AutoGippityError> {
+    let backend: AgentBackendDeveloper =
+      AgentBackendDeveloper::new(self.config.clone(), self.run_log.clone());
+    let sources: Vec<GeneratedSource> = read_generated_sources(&self.config.workspace)?;
+    backend.confirm_run(&sources, factsheet.external_urls.as_deref().unwrap_or_default())?;
+
+    PrintCommand::UnitTest.print_agent_message(
+      self.attributes.position.as_str(),
+      "Auth Code Unit Testing: Building Project...",
+    );
+    let build_output: Output = backend.build().await?;
+    if !build_output.status.success() {
+      let error_str: String = String::from_utf8_lossy(&build_output.stderr).to_string();
+      return self.accept_check_results("build", vec![error_str]);
+    }
+
+    let mut endpoints: Vec<RouteObject> = factsheet
+      .api_endpoint_schema
+      .iter()
+      .flatten()
+      .filter(|route_object| !is_auth_route(&route_object.route))
+      .cloned()
+      .collect();
+    endpoints.extend(auth_routes());
+
+    let results: Vec<EndpointResult> = backend.test_endpoints(&endpoints).await?;
+    let failures: Vec<String> = results
+      .iter()
+      .filter(|result| !result.passed)
+      .map(|result| {
+        format!(
+          "{} {}: {}",
+          result.method,
+          result.route,
+          result.error.as_deref().unwrap_or("failed")
+        )
+      })
+      .collect();
+    if !self.accept_check_results("endpoint tests", failures)? {
+      return Ok(false);
+    }
+
+    save_api_endpoints(&self.config.workspace, &serde_json::to_string_pretty(&endpoints)?)?;
+    let openapi: serde_json::Value = openapi_document(
+      &factsheet.project_description,
+      &endpoints,
+      self.config.backend.port,
+    );
+    save_openapi_document(&self.config.workspace, &serde_json::to_string_pretty(&openapi)?)?;
+    factsheet.api_endpoint_schema = Some(endpoints);
+    Ok(true)
+  }
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentAuthDeveloper {
+  fn get_attributes_from_agent(&self) -> &BasicAgent {
+    &self.attributes
+  }
+
+  fn get_attributes_mut_from_agent(&mut self) -> &mut BasicAgent {
+    &mut self.attributes
+  }
+
+  fn fix_loop_state(&self) -> Option<FixLoopState> {
+    Some(self.fix_loop.clone())
+  }
+
+  fn restore_fix_loop_state(&mut self, saved: FixLoopState) {
+    self.fix_loop = saved;
+  }
+
+  async fn step(&mut self, factsheet: &mut FactSheet) -> Result<(), AutoGippityError> {
+    let login_required: bool = factsheet
+      .project_scope
+      .as_ref()
+      .is_some_and(|scope| scope.is_user_login_and_logout);
+    if !login_required {
+      PrintCommand::AICall.print_agent_message(
+        self.attributes.position.as_str(),
+        "Project does not need user logins, skipping auth",
+      );
+      self.attributes.state = AgentState::Finished;
+      return Ok(());
+    }
+    if factsheet.backend_code.is_none() {
+      PrintCommand::Issue.print_agent_message(
+        self.attributes.position.as_str(),
+        "No backend code on the factsheet, skipping auth",
+      );
+      self.attributes.state = AgentState::Finished;
+      return Ok(());
+    }
+
+    match &self.attributes.state {
+      AgentState::Discovery => {
+        for (name, spec) in AUTH_DEPENDENCIES {
+          add_project_dependency(&self.config.workspace, name, spec)?;
+        }
+        self.attributes.state = match self.call_auth_code(factsheet).await? {
+          true => AgentState::UnitTesting,
+          false => AgentState::Working,
+        };
+      }
+      AgentState::Working => {
+        // unparseable code stays in Working for another fix
+        self.attributes.state = match self.call_fix_auth_code(factsheet).await? {
+          true => AgentState::UnitTesting,
+          false => AgentState::Working,
+        };
+      }
+      AgentState::UnitTesting => {
+        if !self.test_auth(factsheet).await? {
+          self.attributes.state = AgentState::Working;
+          return Ok(());
+        }
+
+        PrintCommand::UnitTest.print_agent_message(
+          self.attributes.position.as_str(),
+          "Auth testing complete...",
+        );
+        self.attributes.state = AgentState::Finished;
+      }
+      _ => {}
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::apis::mock_server::{MockLlmServer, MockReply};
+  use crate::helpers::test_support::{factsheet, scope, TempDir};
+  use std::fs;
+  use std::path::PathBuf;
+
+  fn todo_factsheet(is_user_login_and_logout: bool) -> FactSheet {
+    FactSheet {
+      project_scope: scope(true, is_user_login_and_logout),
+      backend_code: Some("fn main() {}".to_string()),
+      ..factsheet("build a website where users keep todos")
+    }
+  }
+
+  #[tokio::test]
+  async fn test_skips_when_login_not_required() {
+    let server: MockLlmServer = MockLlmServer::start().await;
+    let mut agent: AgentAuthDeveloper = AgentAuthDeveloper::new(server.config(), RunLog::default());
+    let mut factsheet: FactSheet = todo_factsheet(false);
+
+    agent.execute(&mut factsheet).await.unwrap();
+    assert_eq!(agent.attributes.state, AgentState::Finished);
+    assert_eq!(server.calls("print_auth_code"), 0);
+    assert_eq!(factsheet.backend_code.as_deref(), Some("fn main() {}"));
+  }
+
+  #[tokio::test]
+  async fn test_adds_auth_and_fixes_rejected_code() {
+    let temp: TempDir = TempDir::new("auth");
+    let project_dir: PathBuf = temp.path().to_path_buf();
+    fs::create_dir_all(project_dir.join("src")).unwrap();
+    fs::write(project_dir.join("Cargo.toml"), "[package]\nname = \"web_template\"\n\n[dependencies]\n").unwrap();
+    fs::write(project_dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+
+    let mut config: Config = Config::default();
+    config.workspace.project_dir = project_dir.clone();
+
+    let server: MockLlmServer = MockLlmServer::start().await;
+
+    config.llm = server.llm_settings();
+    server.on("print_auth_code", vec![MockReply::Content("fn main( {".to_string())]);
+    server.on(
+      "print_fixed_code",
+      vec![MockReply::Content(
+        "```rust\nfn main() { let _ = std::env::var(\"PORT\"); }\n```".to_string(),
+      )],
+    );
+
+    let mut agent: AgentAuthDeveloper = AgentAuthDeveloper::new(config, RunLog::default());
+    let mut factsheet: FactSheet = todo_factsheet(true);
+
+    // unparseable code is not written and goes back to be fixed
+    agent.step(&mut factsheet).await.unwrap();
+    assert_eq!(agent.attributes.state, AgentState::Working);
+    assert_eq!(agent.fix_loop.bug_count, 1);
+    assert_eq!(fs::read_to_string(project_dir.join("src/main.rs")).unwrap(), "fn main() {}\n");
+    let manifest: String = fs::read_to_string(project_dir.join("Cargo.toml")).unwrap();
+    assert!(manifest.contains("argon2 = ") && manifest.contains("uuid = "));
+
+    agent.step(&mut factsheet).await.unwrap();
+    assert_eq!(agent.attributes.state, AgentState::UnitTesting);
+    assert!(fs::read_to_string(project_dir.join("src/main.rs"))
+      .unwrap()
+      .contains("\"PORT\""));
+    assert!(factsheet.backend_code.as_ref().unwrap().starts_with("fn main() { let _ ="));
+  }
+}
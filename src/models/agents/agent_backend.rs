@@ -1,7 +1,8 @@
 use crate::helpers::{
-  command_line::confirm_safe_code,
+  approval::{decide_with_prompt, running_in_sandbox, ApprovalDecision, ApprovalPolicy, ApprovalRequest},
   general::{
-    check_status_code, read_code_template_contents, save_api_endpoints, save_backend_code,
+    read_code_template_contents, read_data_access_contents, save_api_endpoints, save_backend_code,
+    save_openapi_document,
   },
 };
 use crate::{
@@ -9,46 +10,133 @@ use crate::{
     print_backend_webserver_code, print_fixed_code, print_improved_webserver_code,
     print_rest_api_endpoints,
   },
-  helpers::general::read_exec_main_contents,
+  helpers::general::{read_exec_main_contents, read_generated_sources, GeneratedSource},
 };
 
 use crate::helpers::command_line::PrintCommand;
-use crate::helpers::general::{ai_task_request, WEB_SEVER_PROJECT_PATH};
+use crate::helpers::endpoint_tests::{run_endpoint_tests, EndpointResult};
+use crate::helpers::general::{ai_task_request, ai_task_request_decoded};
+use crate::helpers::llm_output::{extract_rust_code, verify_rust_syntax};
+use crate::helpers::openapi::openapi_document;
+use crate::helpers::run_log::RunLog;
+use crate::helpers::sandbox::Sandbox;
+use crate::helpers::security_review::findings_summary;
+use crate::helpers::server::{
+  free_port, shutdown_signal, unless_shutdown, wait_until_ready, ServerGuard, ServerOutput,
+};
 use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
-use crate::models::agents::agent_traits::{FactSheet, RouteObject, SpecialFunctions};
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_traits::{FactSheet, FixLoopState, RouteObject, SpecialFunctions};
+use crate::models::general::config::Config;
+use crate::models::general::error::AutoGippityError;
 
 use async_trait::async_trait;
 use reqwest::Client;
-use std::process::{Command, Stdio};
+use std::future::Future;
 use std::time::Duration;
-use std::{fmt::format, fs};
-use tokio::time;
 
 #[derive(Debug)]
 pub struct AgentBackendDeveloper {
   attributes: BasicAgent,
-  bug_errors: Option<String>,
-  bug_count: u8,
+  config: Config,
+  run_log: RunLog,
+  // generated code is only ever built and run inside this copy
+  sandbox: Sandbox,
+  fix_loop: FixLoopState,
 }
 
 impl AgentBackendDeveloper {
-  pub fn new() -> Self {
-    let attributes: BasicAgent = BasicAgent {
-      objective: "Develops backend code for webserver and json database".to_string(),
-      position: "Backend Developer".to_string(),
-      state: AgentState::Discovery,
-      memory: vec![],
-    };
+  pub const POSITION: &'static str = "Backend Developer";
+
+  pub fn new(config: Config, run_log: RunLog) -> Self {
+    let attributes: BasicAgent = BasicAgent::new(
+      "Develops backend code for webserver and json database".to_string(),
+      Self::POSITION.to_string(),
+    );
+
+    let sandbox: Sandbox = Sandbox::new(&config.sandbox, config.sandbox_dir());
 
     Self {
       attributes,
-      bug_errors: None,
-      bug_count: 0,
+      config,
+      run_log,
+      sandbox,
+      fix_loop: FixLoopState::default(),
+    }
+  }
+
+  // save generated code only if it parses, otherwise keep the previous main.rs and ask for a fix
+  fn accept_backend_code(
+    &mut self,
+    factsheet: &mut FactSheet,
+    ai_response: &str,
+  ) -> Result<(), AutoGippityError> {
+    let code: String = extract_rust_code(ai_response);
+
+    match verify_rust_syntax(&code) {
+      Ok(()) => {
+        if !code.contains("\"PORT\"") {
+          PrintCommand::Issue.print_agent_message(
+            self.attributes.position.as_str(),
+            "Generated code does not read the PORT env variable, endpoint tests may not reach it",
+          );
+        }
+        save_backend_code(&self.config.workspace, &code)?;
+        factsheet.backend_code = Some(code);
+        self.fix_loop.rejected_code = None;
+        Ok(())
+      }
+      Err(parse_err) => {
+        let err_msg: String = format!(
+          "Rejected generated code, keeping previous version: {}",
+          parse_err
+        );
+        PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), err_msg.as_str());
+
+        self.fix_loop.rejected_code = Some(code);
+        self
+          .fix_loop
+          .record_bug(parse_err, self.config.backend.bug_limit, "backend code")
+      }
+    }
+  }
+
+  // failing endpoints are runtime bugs, sent back to be fixed like build errors
+  fn accept_endpoint_results(&mut self, results: &[EndpointResult]) -> Result<bool, AutoGippityError> {
+    let failures: Vec<String> = results
+      .iter()
+      .filter(|result| !result.passed)
+      .map(|result| {
+        format!(
+          "{} {}: {}",
+          result.method,
+          result.route,
+          result.error.as_deref().unwrap_or("failed")
+        )
+      })
+      .collect();
+    if failures.is_empty() {
+      return Ok(true);
+    }
+
+    self.fix_loop.failed_test_runs += 1;
+    self.fix_loop.bug_count += 1;
+    self.fix_loop.bug_errors = Some(format!(
+      "The code builds, but these endpoint tests failed against the running server:\n{}",
+      failures.join("\n")
+    ));
+
+    if self.fix_loop.failed_test_runs > self.config.backend.bug_limit {
+      return Err(AutoGippityError::Build(format!(
+        "endpoint tests kept failing, last failures:\n{}",
+        failures.join("\n")
+      )));
     }
+    Ok(false)
   }
 
-  async fn call_initial_backend_code(&mut self, factsheet: &mut FactSheet) {
-    let code_template_str: String = read_code_template_contents();
+  async fn call_initial_backend_code(&mut self, factsheet: &mut FactSheet) -> Result<(), AutoGippityError> {
+    let code_template_str: String = read_code_template_contents(&self.config.workspace)?;
 
     // concat instruction
     let mut msg_context: String = format!(
@@ -57,20 +145,26 @@ impl AgentBackendDeveloper {
       code_template_str, factsheet.project_description
     );
 
+    // the handlers persist through the data layer the database agent wrote
+    if factsheet.database_schema.is_some() {
+      let data_access_str: String = read_data_access_contents(&self.config.workspace)?;
+      msg_context.push_str(&format!(" DATA_ACCESS_LAYER: {} \n", data_access_str));
+    }
+
     let ai_response: String = ai_task_request(
       msg_context,
       &self.attributes.position,
       get_function_string!(print_backend_webserver_code),
       print_backend_webserver_code,
+      &self.config.llm,
     )
-    .await;
+    .await?;
 
-    save_backend_code(&ai_response);
-    factsheet.backend_code = Some(ai_response);
+    self.accept_backend_code(factsheet, &ai_response)
   }
 
-  async fn call_improved_backend_code(&mut self, factsheet: &mut FactSheet) {
-    let mut msg_context: String = format!(
+  async fn call_improved_backend_code(&mut self, factsheet: &mut FactSheet) -> Result<(), AutoGippityError> {
+    let msg_context: String = format!(
       "CODE_TEMPLATE: {:?} \n PROJECT_DESCRIPTION: {:?} \n",
       factsheet.backend_code, factsheet
     );
@@ -80,18 +174,20 @@ impl AgentBackendDeveloper {
       &self.attributes.position,
       get_function_string!(print_improved_webserver_code),
       print_improved_webserver_code,
+      &self.config.llm,
     )
-    .await;
+    .await?;
 
-    save_backend_code(&ai_response);
-    factsheet.backend_code = Some(ai_response);
+    self.accept_backend_code(factsheet, &ai_response)
   }
 
-  async fn call_fix_code_bugs(&mut self, factsheet: &mut FactSheet) {
-    let mut msg_context: String = format!(
+  async fn call_fix_code_bugs(&mut self, factsheet: &mut FactSheet) -> Result<(), AutoGippityError> {
+    let broken_code: Option<String> = self.fix_loop.take_broken_code(&factsheet.backend_code);
+
+    let msg_context: String = format!(
       "BROKEN_CODE: {:?} \n ERROR_BUGS: {:?} \n
             THIS FUNCTION ONLY OUTPUTS CODE. JUST OUTPUT THE CODE",
-      factsheet.backend_code, factsheet
+      broken_code, self.fix_loop.bug_errors
     );
 
     let ai_response: String = ai_task_request(
@@ -99,210 +195,349 @@ impl AgentBackendDeveloper {
       &self.attributes.position,
       get_function_string!(print_fixed_code),
       print_fixed_code,
+      &self.config.llm,
     )
-    .await;
+    .await?;
+
+    self.accept_backend_code(factsheet, &ai_response)
+  }
 
-    save_backend_code(&ai_response);
-    factsheet.backend_code = Some(ai_response);
+  // bugs found by another agent, eg. failing qa tests, go through the same fix prompt as build errors
+  pub async fn fix_reported_bugs(
+    &mut self,
+    factsheet: &mut FactSheet,
+    bug_errors: String,
+  ) -> Result<bool, AutoGippityError> {
+    self.fix_loop.bug_errors = Some(bug_errors);
+    self.call_fix_code_bugs(factsheet).await?;
+    Ok(self.fix_loop.rejected_code.is_none())
   }
 
-  async fn call_extract_rest_api_endpoints(&self) -> String {
-    let backend_code: String = read_exec_main_contents();
+  async fn call_extract_rest_api_endpoints(&self) -> Result<Vec<RouteObject>, AutoGippityError> {
+    let backend_code: String = read_exec_main_contents(&self.config.workspace)?;
 
     //structure message context
     let msg_context: String = format!("CODE_INPUT: {}", backend_code);
 
-    let ai_response: String = ai_task_request(
+    ai_task_request_decoded::<Vec<RouteObject>>(
       msg_context,
       &self.attributes.position,
       get_function_string!(print_rest_api_endpoints),
       print_rest_api_endpoints,
+      &self.config.llm,
     )
-    .await;
+    .await
+  }
+
+  // guard: approval before building or running ai written code, recorded in the run log
+  pub fn confirm_run(
+    &self,
+    sources: &[GeneratedSource],
+    external_urls: &[String],
+  ) -> Result<(), AutoGippityError> {
+    let request: ApprovalRequest = ApprovalRequest {
+      policy: self.config.approval.policy,
+      sources,
+      allowed_patterns: &self.config.approval.allowed_patterns,
+      interactive: self.config.interactive,
+      in_sandbox: running_in_sandbox() || self.sandbox.is_isolated(),
+      external_urls,
+      block_high_severity: self.config.approval.block_high_severity,
+    };
 
-    ai_response
+    let prompting: bool = request.policy == ApprovalPolicy::Interactive && request.interactive;
+    if prompting {
+      PrintCommand::UnitTest.print_agent_message(
+        self.attributes.position.as_str(),
+        "Backend Code Unit testing: Requesting user input",
+      );
+    }
+
+    let decision: ApprovalDecision = decide_with_prompt(&request);
+    // the prompt already showed the review
+    if !prompting && !decision.findings.is_empty() {
+      PrintCommand::Issue.print_agent_message(
+        self.attributes.position.as_str(),
+        findings_summary(&decision.findings).as_str(),
+      );
+    }
+    let decision_msg: String = format!(
+      "Approval policy {:?} {}: {}",
+      decision.policy,
+      if decision.approved { "approved" } else { "denied" },
+      decision.reason
+    );
+    let print_command: PrintCommand = if decision.approved {
+      PrintCommand::UnitTest
+    } else {
+      PrintCommand::Issue
+    };
+    print_command.print_agent_message(self.attributes.position.as_str(), decision_msg.as_str());
+
+    self
+      .run_log
+      .record(&self.attributes.position, "approval", &decision)?;
+    decision.into_result()
   }
-}
 
-#[async_trait]
-impl SpecialFunctions for AgentBackendDeveloper {
-  fn get_attributes_from_agent(&self) -> &BasicAgent {
-    &self.attributes
+  // copy the project into the sandbox before anything generated is executed
+  fn prepare_sandbox(&self) -> Result<(), AutoGippityError> {
+    self.sandbox.sync_from(&self.config.workspace.project_dir)?;
+
+    if !self.sandbox.is_isolated() {
+      PrintCommand::Issue.print_agent_message(
+        self.attributes.position.as_str(),
+        "No bubblewrap or firejail found, generated code runs with resource limits only",
+      );
+    }
+
+    let sandbox_detail: serde_json::Value = serde_json::json!({
+      "backend": self.sandbox.backend(),
+      "root": self.sandbox.root(),
+    });
+    self
+      .run_log
+      .record(&self.attributes.position, "sandbox", &sandbox_detail)
   }
 
-  async fn execute(&mut self, factsheet: &mut FactSheet) -> Result<(), Box<dyn std::error::Error>> {
-    while self.attributes.state != AgentState::Finished {
-      match &self.attributes.state {
-        AgentState::Discovery => {
-          self.call_initial_backend_code(factsheet).await;
-          self.attributes.state = AgentState::Working;
-          continue;
-        }
-        AgentState::Working => {
-          if self.bug_count == 0 {
-            self.call_improved_backend_code(factsheet).await;
-          } else {
-            self.call_fix_code_bugs(factsheet).await;
-          }
-          self.attributes.state = AgentState::UnitTesting;
-          continue;
+  // build the current workspace inside the sandbox, offline
+  pub async fn build(&self) -> Result<std::process::Output, AutoGippityError> {
+    self.prepare_sandbox()?;
+    self.sandbox.cargo_build().await
+  }
+
+  // run cargo test on the current workspace inside the sandbox, offline
+  pub async fn run_tests(&self) -> Result<std::process::Output, AutoGippityError> {
+    self.prepare_sandbox()?;
+    self.sandbox.cargo_test().await
+  }
+
+  // start the generated server from the sandbox and wait until it listens, returning its port
+  pub async fn start_server(&self) -> Result<(ServerGuard, u16), AutoGippityError> {
+    self.prepare_sandbox()?;
+
+    let port: u16 = match self.config.backend.port {
+      0 => free_port()?,
+      port => port,
+    };
+    let start_msg: String = format!(
+      "Backend Code Unit Testing: Starting web server with PORT={}...",
+      port
+    );
+    PrintCommand::UnitTest.print_agent_message(self.attributes.position.as_str(), start_msg.as_str());
+
+    // execute running server, the guard stops it on every early return
+    let mut run_backend_server: ServerGuard = self.sandbox.cargo_run(port)?;
+    let server_output: ServerOutput =
+      ServerOutput::stream(run_backend_server.child_mut(), self.attributes.position.as_str());
+
+    // wait for the port instead of a fixed sleep, compiling can take a while
+    let startup_timeout: Duration = Duration::from_secs(self.config.backend.startup_timeout_secs);
+    let ready: Result<Duration, AutoGippityError> =
+      unless_shutdown(shutdown_signal(), wait_until_ready(run_backend_server.child_mut(), port, startup_timeout))
+        .await
+        .and_then(|ready| ready);
+    match ready {
+      Ok(waited) => {
+        let ready_msg: String = format!(
+          "Backend Code Unit Testing: Server ready after {:.1} seconds...",
+          waited.as_secs_f32()
+        );
+        PrintCommand::UnitTest
+          .print_agent_message(self.attributes.position.as_str(), ready_msg.as_str());
+        Ok((run_backend_server, port))
+      }
+      Err(e) => {
+        run_backend_server.shutdown(self.shutdown_grace()).await?;
+        if matches!(e, AutoGippityError::UserAbort(_)) {
+          return Err(e);
         }
-        AgentState::UnitTesting => {
-          // guard: ensure ai safety
-          PrintCommand::UnitTest.print_agent_message(
-            self.attributes.position.as_str(),
-            "Backend Code Unit testing: Requesting user input",
-          );
+        Err(AutoGippityError::Runtime(format!(
+          "{}, last server output:\n{}",
+          e,
+          server_output.tail()
+        )))
+      }
+    }
+  }
 
-          let is_safe_code = confirm_safe_code();
+  fn shutdown_grace(&self) -> Duration {
+    Duration::from_secs(self.config.backend.shutdown_grace_secs)
+  }
 
-          if !is_safe_code {
-            panic!("Better go work on some AI alignment instead...");
-          }
+  // run work against the started server, then stop it, also when ctrl-c comes first
+  pub async fn with_server<F: Future>(
+    &self,
+    run_backend_server: ServerGuard,
+    work: F,
+  ) -> Result<F::Output, AutoGippityError> {
+    let output: Result<F::Output, AutoGippityError> = unless_shutdown(shutdown_signal(), work).await;
+    self.stop_server(run_backend_server).await;
+    output
+  }
 
-          //build and test code
-          PrintCommand::UnitTest.print_agent_message(
-            self.attributes.position.as_str(),
-            "Backend Code Unit Testing: Building Project...",
-          );
+  pub async fn stop_server(&self, run_backend_server: ServerGuard) {
+    if let Err(e) = run_backend_server.shutdown(self.shutdown_grace()).await {
+      let kill_msg: String = format!("Failed to stop server on completion: {}", e);
+      PrintCommand::Issue
+        .print_agent_message(self.attributes.position.as_str(), kill_msg.as_str());
+    }
+  }
 
-          // build code
-          let build_backend_server: std::process::Output = Command::new("cargo")
-            .arg("build")
-            .current_dir(WEB_SEVER_PROJECT_PATH)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .expect("failed to run backend application");
-
-          //determine if build errors
-          if build_backend_server.status.success() {
-            self.bug_count = 0;
-            PrintCommand::UnitTest.print_agent_message(
-              self.attributes.position.as_str(),
-              "Backend Code Unit Testing: Test server build successful...",
-            );
-          } else {
-            let error_arr: Vec<u8> = build_backend_server.stderr;
-            let error_str: String = String::from_utf8(error_arr).unwrap();
-
-            //update error stats
-            self.bug_count += 1;
-            self.bug_errors = Some(error_str);
-
-            // exit if too many bugs
-            if self.bug_count > 2 {
-              PrintCommand::Issue.print_agent_message(
-                self.attributes.position.as_str(),
-                "Backend Code Unit Testing: Too many bugs found in code",
-              );
-              panic!("Error: Too many bugs");
-            }
+  // client for requests against the test server
+  pub fn http_client(&self) -> Result<Client, AutoGippityError> {
+    Client::builder()
+      .timeout(Duration::from_secs(self.config.backend.request_timeout_secs))
+      .build()
+      .map_err(|e| AutoGippityError::Runtime(e.to_string()))
+  }
 
-            // Pass back to working
-            self.attributes.state = AgentState::Working;
-            continue;
-          }
+  // start the generated server and exercise each endpoint, recording pass or fail per route
+  pub async fn test_endpoints(
+    &self,
+    endpoints: &[RouteObject],
+  ) -> Result<Vec<EndpointResult>, AutoGippityError> {
+    let (run_backend_server, port): (ServerGuard, u16) = self.start_server().await?;
+    let client: Client = self.http_client()?;
+
+    // every route, with crud sequences for resources that have them
+    let base_url: String = format!("http://127.0.0.1:{}", port);
+    let results: Vec<EndpointResult> = self
+      .with_server(run_backend_server, run_endpoint_tests(&client, &base_url, endpoints))
+      .await?;
+    for result in &results {
+      let result_msg: String = match &result.error {
+        None => format!("PASS {} {}", result.method, result.url),
+        Some(error) => format!("FAIL {} {}: {}", result.method, result.url, error),
+      };
+      let print_command: PrintCommand = if result.passed {
+        PrintCommand::UnitTest
+      } else {
+        PrintCommand::Issue
+      };
+      print_command.print_agent_message(self.attributes.position.as_str(), result_msg.as_str());
+    }
+    self
+      .run_log
+      .record(&self.attributes.position, "endpoint_tests", &results)?;
 
-          /*
-             Extract and test
-             rest api endpoints
-          */
+    PrintCommand::UnitTest.print_agent_message(
+      self.attributes.position.as_str(),
+      "Backend testing complete...",
+    );
+    Ok(results)
+  }
+}
 
-          //extract api endpoints
-          let api_endpoints_str: String = self.call_extract_rest_api_endpoints().await;
+#[async_trait]
+impl SpecialFunctions for AgentBackendDeveloper {
+  fn get_attributes_from_agent(&self) -> &BasicAgent {
+    &self.attributes
+  }
 
-          // convert api endpoints into values
-          let api_endpoints: Vec<RouteObject> = serde_json::from_str(api_endpoints_str.as_str())
-            .expect("failed to decode api endpoints");
+  fn get_attributes_mut_from_agent(&mut self) -> &mut BasicAgent {
+    &mut self.attributes
+  }
+
+  fn fix_loop_state(&self) -> Option<FixLoopState> {
+    Some(self.fix_loop.clone())
+  }
 
-          let check_endpoints: Vec<RouteObject> = api_endpoints
-            .iter()
-            .filter(|&route_object| {
-              route_object.method == "get" && route_object.is_route_dynamic == "false"
-            })
-            .cloned()
-            .collect();
+  fn restore_fix_loop_state(&mut self, saved: FixLoopState) {
+    self.fix_loop = saved;
+  }
 
-          // store api endpoints
-          factsheet.api_endpoint_schema = Some(check_endpoints.clone());
+  async fn step(&mut self, factsheet: &mut FactSheet) -> Result<(), AutoGippityError> {
+    match &self.attributes.state {
+      AgentState::Discovery => {
+        self.call_initial_backend_code(factsheet).await?;
+        self.attributes.state = AgentState::Working;
+      }
+      AgentState::Working => {
+        if self.fix_loop.bug_count == 0 {
+          self.call_improved_backend_code(factsheet).await?;
+        } else {
+          self.call_fix_code_bugs(factsheet).await?;
+        }
 
+        // unparseable code was not saved, ask for a fix before building
+        if self.fix_loop.rejected_code.is_none() {
+          self.attributes.state = AgentState::UnitTesting;
+        }
+      }
+      AgentState::UnitTesting => {
+        // guard: ensure ai safety
+        let sources: Vec<GeneratedSource> = read_generated_sources(&self.config.workspace)?;
+        self.confirm_run(&sources, factsheet.external_urls.as_deref().unwrap_or_default())?;
+
+        //build and test code
+        PrintCommand::UnitTest.print_agent_message(
+          self.attributes.position.as_str(),
+          "Backend Code Unit Testing: Building Project...",
+        );
+
+        // build code
+        let build_backend_server: std::process::Output = self.build().await?;
+
+        //determine if build errors
+        if build_backend_server.status.success() {
+          self.fix_loop.bug_count = 0;
           PrintCommand::UnitTest.print_agent_message(
             self.attributes.position.as_str(),
-            "Backend Code Unit Testing: Starting web server...",
+            "Backend Code Unit Testing: Test server build successful...",
           );
+        } else {
+          let error_arr: Vec<u8> = build_backend_server.stderr;
+          let error_str: String = String::from_utf8_lossy(&error_arr).to_string();
+
+          //update error stats, exit if too many bugs
+          let limit_res: Result<(), AutoGippityError> =
+            self.fix_loop.record_bug(error_str, self.config.backend.bug_limit, "backend code");
+          if limit_res.is_err() {
+            PrintCommand::Issue.print_agent_message(
+              self.attributes.position.as_str(),
+              "Backend Code Unit Testing: Too many bugs found in code",
+            );
+            return limit_res;
+          }
 
-          // execute running server
-          let mut run_backend_server: std::process::Child = Command::new("cargo")
-            .arg("run")
-            .current_dir(WEB_SEVER_PROJECT_PATH)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .expect("Failed to run backend application");
+          // Pass back to working
+          self.attributes.state = AgentState::Working;
+          return Ok(());
+        }
 
-          // let user know testing on server will take place soon
-          PrintCommand::UnitTest.print_agent_message(
-            self.attributes.position.as_str(),
-            "Backend Code Unit Testing: Launching tests on server in 5 seconds...",
-          );
+        /*
+           Extract and test
+           rest api endpoints
+        */
 
-          let seconds_sleep: Duration = Duration::from_secs(5);
-          time::sleep(seconds_sleep).await;
-
-          // check status code
-          for endpoint in check_endpoints {
-            // confirm url testing
-            let testing_msg: String = format!("Testing endpoint '{}'...", endpoint.route);
-            PrintCommand::UnitTest
-              .print_agent_message(self.attributes.position.as_str(), testing_msg.as_str());
-
-            // create client
-            let client = Client::builder()
-              .timeout(Duration::from_secs(5))
-              .build()
-              .unwrap();
-
-            let url: String = format! {"http://localhost:8080{}", endpoint.route};
-            match check_status_code(&client, &url).await {
-              Ok(status_code) => {
-                if status_code != 200 {
-                  let err_msg: String = format!(
-                    "WARNING: Failed to call backend url endpoint {}",
-                    endpoint.route
-                  );
-                  PrintCommand::Issue
-                    .print_agent_message(self.attributes.position.as_str(), err_msg.as_str());
-                }
-              }
-              Err(e) => {
-                // kill $(lsof -t -i::8080)
-                run_backend_server
-                  .kill()
-                  .expect("Failed to kill backend web server on faiture");
-
-                let err_msg: String = format!("Error checking Backend {}", e);
-                PrintCommand::Issue
-                  .print_agent_message(self.attributes.position.as_str(), err_msg.as_str());
-              }
-            }
-          }
+        //extract api endpoints
+        let api_endpoints: Vec<RouteObject> = self.call_extract_rest_api_endpoints().await?;
+        let api_endpoints_str: String = serde_json::to_string_pretty(&api_endpoints)?;
 
-          save_api_endpoints(&api_endpoints_str);
+        // store api endpoints
+        factsheet.api_endpoint_schema = Some(api_endpoints.clone());
 
-          PrintCommand::UnitTest.print_agent_message(
+        let results: Vec<EndpointResult> = self.test_endpoints(&api_endpoints).await?;
+        if !self.accept_endpoint_results(&results)? {
+          PrintCommand::Issue.print_agent_message(
             self.attributes.position.as_str(),
-            "Backend testing complete...",
+            "Backend Code Unit Testing: Endpoint tests failed, sending them back as bugs",
           );
-
-          run_backend_server
-            .kill()
-            .expect("Failed to kill server on completion");
-
-          self.attributes.state = AgentState::Finished;
+          self.attributes.state = AgentState::Working;
+          return Ok(());
         }
-        _ => {}
+        save_api_endpoints(&self.config.workspace, &api_endpoints_str)?;
+        let openapi: serde_json::Value = openapi_document(
+          &factsheet.project_description,
+          &api_endpoints,
+          self.config.backend.port,
+        );
+        save_openapi_document(&self.config.workspace, &serde_json::to_string_pretty(&openapi)?)?;
+
+        self.attributes.state = AgentState::Finished;
       }
+      _ => {}
     }
 
     Ok(())
@@ -312,10 +547,25 @@ impl SpecialFunctions for AgentBackendDeveloper {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::apis::mock_server::{MockLlmServer, MockReply};
+  use crate::helpers::test_support::{factsheet, unattended, web_template, TempDir};
+  use crate::models::general::config::LlmSettings;
+  use std::fs;
+  use std::path::PathBuf;
 
   #[tokio::test]
   async fn testing_writing_backend_code() {
-    let mut agent: AgentBackendDeveloper = AgentBackendDeveloper::new();
+    let temp: TempDir = TempDir::new("backend_cassette");
+    let project_dir: PathBuf = temp.join("workspace");
+    web_template(&project_dir);
+
+    let mut config: Config = unattended(Config {
+      llm: LlmSettings::test_cassette(),
+      ..Config::default()
+    });
+    config.workspace.project_dir = project_dir.clone();
+    config.workspace.runs_dir = temp.join("runs");
+    let mut agent: AgentBackendDeveloper = AgentBackendDeveloper::new(config, RunLog::default());
 
     let factsheet_str = r#"
             {
@@ -338,6 +588,137 @@ mod tests {
     agent
       .execute(&mut factsheet)
       .await
-      .expect("failed to execute backend developer")
+      .expect("failed to execute backend developer");
+
+    // the hand written server from the synthetic cassette was built, run and passed its endpoint tests
+    let endpoints: Vec<RouteObject> = factsheet.api_endpoint_schema.unwrap();
+    assert_eq!(endpoints[0].route, "/time");
+    assert!(fs::read_to_string(project_dir.join("src/main.rs")).unwrap().contains("\"/time\""));
+    assert!(project_dir.join("schemas/openapi.json").is_file());
+  }
+
+  #[test]
+  fn test_rejects_unparseable_code_without_saving() {
+    let mut agent: AgentBackendDeveloper = AgentBackendDeveloper::new(Config::default(), RunLog::default());
+    let mut factsheet: FactSheet = FactSheet {
+      backend_code: Some("fn main() {}\n".to_string()),
+      ..factsheet("")
+    };
+
+    let ai_response: &str = "Here you go:\n```rust\nfn main() {\n  let x = ;\n```";
+    agent.accept_backend_code(&mut factsheet, ai_response).unwrap();
+
+    assert_eq!(factsheet.backend_code, Some("fn main() {}\n".to_string()));
+    assert_eq!(
+      agent.fix_loop.rejected_code,
+      Some("fn main() {\n  let x = ;\n".to_string())
+    );
+    assert_eq!(agent.fix_loop.bug_count, 1);
+    assert!(agent.fix_loop.bug_errors.unwrap().contains("not valid Rust"));
+  }
+
+  #[tokio::test]
+  async fn test_writes_code_into_configured_workspace() {
+    let temp: TempDir = TempDir::new("workspace");
+    let project_dir: PathBuf = temp.path().to_path_buf();
+    fs::create_dir_all(project_dir.join("src")).unwrap();
+    fs::write(
+      project_dir.join("src/code_template.rs"),
+      "fn main() {}\n",
+    )
+    .unwrap();
+
+    let mut config: Config = Config::default();
+    config.workspace.project_dir = project_dir.clone();
+
+    let server: MockLlmServer = MockLlmServer::start().await;
+
+    config.llm = server.llm_settings();
+    server.on(
+      "print_backend_webserver_code",
+      vec![MockReply::Content(
+        "```rust\nfn main() {\n  println!(\"v1\");\n}\n```".to_string(),
+      )],
+    );
+    server.on(
+      "print_improved_webserver_code",
+      vec![MockReply::Content(
+        "fn main() {\n  println!(\"v2\");\n}\n".to_string(),
+      )],
+    );
+
+    let mut agent: AgentBackendDeveloper = AgentBackendDeveloper::new(config, RunLog::default());
+    let mut factsheet: FactSheet = factsheet("hello world");
+
+    agent.step(&mut factsheet).await.unwrap();
+    assert_eq!(
+      fs::read_to_string(project_dir.join("src/main.rs")).unwrap(),
+      "fn main() {\n  println!(\"v1\");\n}\n"
+    );
+
+    agent.step(&mut factsheet).await.unwrap();
+    assert_eq!(agent.attributes.state, AgentState::UnitTesting);
+    assert!(fs::read_to_string(project_dir.join("src/main.rs"))
+      .unwrap()
+      .contains("v2"));
+  }
+
+  #[tokio::test]
+  async fn test_denied_approval_aborts_and_is_logged() {
+    let temp: TempDir = TempDir::new("denied");
+    let project_dir: PathBuf = temp.path().to_path_buf();
+    fs::create_dir_all(project_dir.join("src")).unwrap();
+    fs::write(project_dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+
+    let mut config: Config = Config::default();
+    config.workspace.project_dir = project_dir.clone();
+    config.approval.policy = ApprovalPolicy::Deny;
+
+    let log_path: PathBuf = project_dir.join("run.log");
+    let mut agent: AgentBackendDeveloper =
+      AgentBackendDeveloper::new(config, RunLog::new(log_path.clone()));
+    agent.attributes.state = AgentState::UnitTesting;
+    let mut factsheet: FactSheet = factsheet("");
+
+    let res: Result<(), AutoGippityError> = agent.step(&mut factsheet).await;
+    assert!(matches!(res, Err(AutoGippityError::UserAbort(_))));
+
+    let log: String = fs::read_to_string(&log_path).unwrap();
+    assert!(log.contains("\"event\":\"approval\""));
+    assert!(log.contains("\"approved\":false"));
+  }
+
+  #[test]
+  fn test_failing_endpoints_become_bugs() {
+    let mut agent: AgentBackendDeveloper = AgentBackendDeveloper::new(Config::default(), RunLog::default());
+    let mut result: EndpointResult = EndpointResult {
+      method: "GET".to_string(),
+      route: "/item/{id}".to_string(),
+      url: "http://127.0.0.1:1/item/1".to_string(),
+      status: Some(200),
+      passed: true,
+      error: None,
+      shape_errors: vec![],
+    };
+    assert!(agent.accept_endpoint_results(&[result.clone()]).unwrap());
+    assert_eq!(agent.fix_loop.bug_count, 0);
+
+    result.passed = false;
+    result.shape_errors = vec!["$.id: expected number, got string".to_string()];
+    result.error = Some("response does not match the schema: $.id: expected number, got string".to_string());
+    assert!(!agent.accept_endpoint_results(&[result.clone()]).unwrap());
+    assert_eq!(agent.fix_loop.bug_count, 1);
+    assert!(agent
+      .fix_loop
+      .bug_errors
+      .as_ref()
+      .unwrap()
+      .contains("GET /item/{id}: response does not match the schema: $.id: expected number"));
+
+    // a successful build resets bug_count, the test run limit still applies
+    agent.fix_loop.bug_count = 0;
+    assert!(!agent.accept_endpoint_results(&[result.clone()]).unwrap());
+    let res: Result<bool, AutoGippityError> = agent.accept_endpoint_results(&[result]);
+    assert!(matches!(res, Err(AutoGippityError::Build(_))));
   }
 }
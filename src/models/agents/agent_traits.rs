@@ -1,18 +1,28 @@
+use crate::helpers::output_schema::OutputRules;
+use crate::helpers::security_review::Finding;
+#[cfg(test)]
+use crate::models::agent_basic::basic_agent::AgentState;
 use crate::models::agent_basic::basic_agent::BasicAgent;
+use crate::models::general::api_types::{lenient_bool, lenient_bool_schema, FieldType, HttpMethod};
+use crate::models::general::data_model::DataModel;
+use crate::models::general::error::AutoGippityError;
 use async_trait::async_trait;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct RouteObject {
-  pub is_route_dynamic: String,
-  pub method: String,
-  pub request_body: serde_json::Value,
-  pub response: serde_json::Value,
+  #[serde(deserialize_with = "lenient_bool")]
+  #[schemars(schema_with = "lenient_bool_schema")]
+  pub is_route_dynamic: bool,
+  pub method: HttpMethod,
+  pub request_body: FieldType,
+  pub response: FieldType,
   pub route: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 
 pub struct ProjectScope {
   pub is_crud_required: bool,
@@ -20,19 +30,141 @@ pub struct ProjectScope {
   pub is_external_urls_required: bool,
 }
 
+impl OutputRules for ProjectScope {
+  fn rule_violations(&self) -> Vec<String> {
+    if self.is_crud_required || self.is_user_login_and_logout || self.is_external_urls_required {
+      return vec![];
+    }
+    vec!["$: at least one of the bool results must be true".to_string()]
+  }
+}
+
+impl OutputRules for Vec<RouteObject> {
+  fn rule_violations(&self) -> Vec<String> {
+    let mut violations: Vec<String> = vec![];
+
+    for (i, route_object) in self.iter().enumerate() {
+      if !route_object.route.starts_with('/') {
+        violations.push(format!("$[{}].route: '{}' must start with '/'", i, route_object.route));
+      }
+
+      let expected_dynamic: bool = route_object.route.contains('{');
+      if route_object.is_route_dynamic != expected_dynamic {
+        violations.push(format!(
+          "$[{}].is_route_dynamic: must be {} for route '{}'",
+          i, expected_dynamic, route_object.route
+        ));
+      }
+    }
+
+    if self.is_empty() {
+      violations.push("$: at least one route must be listed".to_string());
+    }
+    violations
+  }
+}
+
+// progress of an agent's build and fix loop, saved with its checkpoint
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct FixLoopState {
+  pub bug_count: u8,
+  pub bug_errors: Option<String>,
+  // code that failed the syntax check, it is never written to the workspace
+  pub rejected_code: Option<String>,
+  // test runs with failing endpoints, not reset by a successful build
+  pub failed_test_runs: u8,
+  // the qa engineer's last test module
+  pub test_code: Option<String>,
+  // the backend fixes the qa engineer asked for, so their limit spans qa rounds
+  pub backend_fix: Option<Box<FixLoopState>>,
+}
+
+impl FixLoopState {
+  // count a failed attempt and keep its errors for the fix prompt, giving up past the bug limit
+  pub fn record_bug(&mut self, bug_errors: String, bug_limit: u8, what: &str) -> Result<(), AutoGippityError> {
+    self.bug_count += 1;
+    self.bug_errors = Some(bug_errors);
+    if self.bug_count > bug_limit {
+      return Err(AutoGippityError::Build(format!(
+        "too many bugs found in {}, last errors:\n{}",
+        what,
+        self.bug_errors.as_deref().unwrap_or_default()
+      )));
+    }
+    Ok(())
+  }
+
+  // a rejected response is the broken code, it never replaced the saved version
+  pub fn take_broken_code(&mut self, saved_code: &Option<String>) -> Option<String> {
+    self.rejected_code.take().or_else(|| saved_code.clone())
+  }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct FactSheet {
   pub project_description: String,
   pub project_scope: Option<ProjectScope>,
   pub external_urls: Option<Vec<String>>,
+  // sqlite tables, only designed when crud is required
+  #[serde(default)]
+  pub database_schema: Option<DataModel>,
   pub backend_code: Option<String>,
   pub api_endpoint_schema: Option<Vec<RouteObject>>,
+  pub frontend_code: Option<String>,
+  // last security review of the generated files
+  #[serde(default)]
+  pub security_findings: Option<Vec<Finding>>,
 }
 
 #[async_trait]
-pub trait SpecialFunctions: Debug {
+pub trait SpecialFunctions: Debug + Send + Sync {
   // ussed so the manager can get attributes from agents
   fn get_attributes_from_agent(&self) -> &BasicAgent;
 
-  async fn execute(&mut self, factsheet: &mut FactSheet) -> Result<(), Box<dyn std::error::Error>>;
+  // used so the manager can restore a checkpointed state
+  fn get_attributes_mut_from_agent(&mut self) -> &mut BasicAgent;
+
+  // fix loop progress for the checkpoint, none when the agent has nothing to resume
+  fn fix_loop_state(&self) -> Option<FixLoopState> {
+    None
+  }
+
+  fn restore_fix_loop_state(&mut self, _saved: FixLoopState) {}
+
+  // run the work for the current state and move on to the next state
+  async fn step(&mut self, factsheet: &mut FactSheet) -> Result<(), AutoGippityError>;
+
+  // run the agent to completion without checkpoints, the manager steps agents itself
+  #[cfg(test)]
+  async fn execute(&mut self, factsheet: &mut FactSheet) -> Result<(), AutoGippityError> {
+    while self.get_attributes_from_agent().state != AgentState::Finished {
+      self.step(factsheet).await?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_fix_loop_gives_up_past_the_bug_limit() {
+    let mut fix_loop: FixLoopState = FixLoopState::default();
+    fix_loop.record_bug("error[E0425]: cannot find value `db`".to_string(), 1, "backend code").unwrap();
+    assert_eq!(fix_loop.bug_count, 1);
+    assert_eq!(fix_loop.bug_errors.as_deref(), Some("error[E0425]: cannot find value `db`"));
+
+    fix_loop.rejected_code = Some("fn main( {}".to_string());
+    let saved_code: Option<String> = Some("fn main() {}".to_string());
+    assert_eq!(fix_loop.take_broken_code(&saved_code).as_deref(), Some("fn main( {}"));
+    assert_eq!(fix_loop.take_broken_code(&saved_code), saved_code);
+
+    // the state a checkpoint saves picks the loop up where it stopped
+    let mut resumed: FixLoopState =
+      serde_json::from_value(serde_json::to_value(&fix_loop).unwrap()).unwrap();
+    assert_eq!(resumed, fix_loop);
+    let res: Result<(), AutoGippityError> = resumed.record_bug("still broken".to_string(), 1, "backend code");
+    assert!(matches!(res, Err(AutoGippityError::Build(msg)) if msg.contains("too many bugs found in backend code")));
+  }
 }
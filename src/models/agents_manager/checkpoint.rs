@@ -0,0 +1,348 @@
+use crate::helpers::general::copy_project;
+use crate::helpers::run_log::RunLog;
+use crate::models::agent_basic::basic_agent::AgentState;
+use crate::models::agents::agent_traits::{FactSheet, FixLoopState};
+use crate::models::general::error::AutoGippityError;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+const RUN_LOG_FILE: &str = "run.log";
+const WORKSPACE_DIR: &str = "workspace";
+const SANDBOX_DIR: &str = "sandbox";
+
+fn now_secs() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|since| since.as_secs())
+    .unwrap_or(0)
+}
+
+// last saved state of one agent, matched back up by position on resume
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AgentCheckpoint {
+  pub position: String,
+  pub state: AgentState,
+  // none for agents without a fix loop
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub fix_loop: Option<FixLoopState>,
+}
+
+// everything needed to continue a run without repeating llm calls
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Checkpoint {
+  pub factsheet: FactSheet,
+  pub agents: Vec<AgentCheckpoint>,
+}
+
+fn is_run_dir(path: &Path) -> bool {
+  path.join(CHECKPOINT_FILE).is_file()
+}
+
+// directory holding the checkpoints of a single run
+#[derive(Debug, Clone)]
+pub struct RunDir {
+  path: PathBuf,
+}
+
+impl RunDir {
+  // new run directory named after the current time, eg. runs/run-1700000000
+  pub fn create(base: &Path) -> Result<Self, AutoGippityError> {
+    let secs: u64 = now_secs();
+
+    let mut path: PathBuf = base.join(format!("run-{}", secs));
+    let mut suffix: u32 = 1;
+    while path.exists() {
+      path = base.join(format!("run-{}-{}", secs, suffix));
+      suffix += 1;
+    }
+
+    fs::create_dir_all(&path).map_err(|e| {
+      AutoGippityError::Runtime(format!(
+        "failed to create run directory {}: {}",
+        path.display(),
+        e
+      ))
+    })?;
+    Ok(Self { path })
+  }
+
+  // existing run directory to resume from
+  pub fn open(path: &Path) -> Result<Self, AutoGippityError> {
+    if !is_run_dir(path) {
+      return Err(AutoGippityError::Config(format!(
+        "{} is not a run directory, {} is missing",
+        path.display(),
+        CHECKPOINT_FILE
+      )));
+    }
+    Ok(Self {
+      path: path.to_path_buf(),
+    })
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn run_log(&self) -> RunLog {
+    RunLog::new(self.path.join(RUN_LOG_FILE))
+  }
+
+  // this run's own copy of the project template, all generated code goes here
+  pub fn workspace_path(&self) -> PathBuf {
+    self.path.join(WORKSPACE_DIR)
+  }
+
+  pub fn sandbox_path(&self) -> PathBuf {
+    self.path.join(SANDBOX_DIR)
+  }
+
+  // copy the template when the run starts, the template itself is never modified
+  pub fn create_workspace(&self, template: &Path) -> Result<PathBuf, AutoGippityError> {
+    let workspace: PathBuf = self.workspace_path();
+    copy_project(template, &workspace).map_err(|e| {
+      let _ = fs::remove_dir_all(&workspace);
+      AutoGippityError::Config(format!(
+        "failed to copy project template {}: {}",
+        template.display(),
+        e
+      ))
+    })?;
+    Ok(workspace)
+  }
+
+  // seconds since the epoch, from the directory name or else its modified time
+  pub fn created_secs(&self) -> u64 {
+    let name: String = self
+      .path
+      .file_name()
+      .map(|name| name.to_string_lossy().to_string())
+      .unwrap_or_default();
+    name
+      .strip_prefix("run-")
+      .and_then(|rest| rest.split('-').next())
+      .and_then(|secs| secs.parse().ok())
+      .or_else(|| {
+        fs::metadata(&self.path)
+          .and_then(|meta| meta.modified())
+          .ok()
+          .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+          .map(|since| since.as_secs())
+      })
+      .unwrap_or(0)
+  }
+
+  // every run directory under base, newest first
+  pub fn list(base: &Path) -> Result<Vec<Self>, AutoGippityError> {
+    if !base.is_dir() {
+      return Ok(vec![]);
+    }
+
+    let mut runs: Vec<Self> = vec![];
+    for entry in fs::read_dir(base)? {
+      let path: PathBuf = entry?.path();
+      if path.is_dir() && is_run_dir(&path) {
+        runs.push(Self { path });
+      }
+    }
+    runs.sort_by_key(|run| std::cmp::Reverse((run.created_secs(), run.path.clone())));
+    Ok(runs)
+  }
+
+  // one line per run for the list command
+  pub fn summary(&self) -> String {
+    let age_hours: u64 = now_secs().saturating_sub(self.created_secs()) / 3600;
+    let (description, finished): (String, String) = match self.load() {
+      Ok(checkpoint) => {
+        let done: usize = checkpoint
+          .agents
+          .iter()
+          .filter(|agent| agent.state == AgentState::Finished)
+          .count();
+        (
+          checkpoint.factsheet.project_description,
+          format!("{}/{} agents finished", done, checkpoint.agents.len()),
+        )
+      }
+      Err(e) => (e.to_string(), "unreadable".to_string()),
+    };
+    let first_line: &str = description.lines().next().unwrap_or("").trim();
+
+    format!(
+      "{}  {}h ago  {}  {}",
+      self.path.display(),
+      age_hours,
+      finished,
+      first_line
+    )
+  }
+
+  // write to a temp file and rename so a crash never leaves a half written checkpoint
+  fn write_json<T: Serialize>(&self, file: &str, value: &T) -> Result<(), AutoGippityError> {
+    let target: PathBuf = self.path.join(file);
+    let tmp: PathBuf = self.path.join(format!("{}.tmp", file));
+
+    fs::write(&tmp, serde_json::to_string_pretty(value)?)?;
+    fs::rename(&tmp, &target)
+      .map_err(|e| AutoGippityError::Runtime(format!("failed to save {}: {}", target.display(), e)))
+  }
+
+  fn read_json<T: DeserializeOwned>(&self, file: &str) -> Result<T, AutoGippityError> {
+    let target: PathBuf = self.path.join(file);
+    let contents: String = fs::read_to_string(&target).map_err(|e| {
+      AutoGippityError::Runtime(format!("failed to read {}: {}", target.display(), e))
+    })?;
+    serde_json::from_str(&contents).map_err(|e| {
+      AutoGippityError::Decode(format!("invalid checkpoint {}: {}", target.display(), e))
+    })
+  }
+
+  // factsheet and agent states share one file so they are always replaced together
+  pub fn save(&self, checkpoint: &Checkpoint) -> Result<(), AutoGippityError> {
+    self.write_json(CHECKPOINT_FILE, checkpoint)
+  }
+
+  pub fn load(&self) -> Result<Checkpoint, AutoGippityError> {
+    self.read_json(CHECKPOINT_FILE)
+  }
+}
+
+// remove all but the newest keep runs, only those older than older_than when given
+pub fn clean_runs(
+  base: &Path,
+  keep: usize,
+  older_than: Option<Duration>,
+  dry_run: bool,
+) -> Result<Vec<PathBuf>, AutoGippityError> {
+  let cutoff: u64 = older_than
+    .map(|age| now_secs().saturating_sub(age.as_secs()))
+    .unwrap_or(u64::MAX);
+
+  let mut removed: Vec<PathBuf> = vec![];
+  for run in RunDir::list(base)?.into_iter().skip(keep) {
+    if run.created_secs() > cutoff {
+      continue;
+    }
+    if !dry_run {
+      fs::remove_dir_all(run.path()).map_err(|e| {
+        AutoGippityError::Runtime(format!("failed to remove {}: {}", run.path().display(), e))
+      })?;
+    }
+    removed.push(run.path);
+  }
+  Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::helpers::test_support::{factsheet, TempDir};
+
+  #[test]
+  fn test_checkpoint_round_trip() {
+    let temp: TempDir = TempDir::new("checkpoint_round_trip");
+    let base: PathBuf = temp.path().to_path_buf();
+    let run_dir: RunDir = RunDir::create(&base).unwrap();
+
+    let checkpoint: Checkpoint = Checkpoint {
+      factsheet: FactSheet {
+        external_urls: Some(vec![]),
+        backend_code: Some("fn main() {}\n".to_string()),
+        ..factsheet("build a website that returns the time")
+      },
+      agents: vec![
+        AgentCheckpoint {
+          position: "Solutions Architect".to_string(),
+          state: AgentState::UnitTesting,
+          fix_loop: None,
+        },
+        AgentCheckpoint {
+          position: "Backend Developer".to_string(),
+          state: AgentState::Working,
+          fix_loop: Some(FixLoopState {
+            bug_count: 2,
+            bug_errors: Some("error[E0425]: cannot find value `db`".to_string()),
+            rejected_code: Some("fn main( {}".to_string()),
+            failed_test_runs: 1,
+            ..FixLoopState::default()
+          }),
+        },
+      ],
+    };
+    run_dir.save(&checkpoint).unwrap();
+
+    let reopened: RunDir = RunDir::open(run_dir.path()).unwrap();
+    assert_eq!(reopened.load().unwrap(), checkpoint);
+    assert!(!run_dir.path().join("checkpoint.json.tmp").exists());
+  }
+
+  #[test]
+  fn test_open_rejects_non_run_directory() {
+    let temp: TempDir = TempDir::new("not_a_run");
+    let base: PathBuf = temp.path().to_path_buf();
+
+    let res: Result<RunDir, AutoGippityError> = RunDir::open(&base);
+    assert!(matches!(res, Err(AutoGippityError::Config(_))));
+  }
+
+  #[test]
+  fn test_workspace_is_a_copy_of_the_template() {
+    let temp: TempDir = TempDir::new("run_workspace");
+    let base: PathBuf = temp.path().to_path_buf();
+    let template: PathBuf = base.join("template");
+    fs::create_dir_all(template.join("src")).unwrap();
+    fs::create_dir_all(template.join("target")).unwrap();
+    fs::write(template.join("src/main.rs"), "fn main() {}\n").unwrap();
+
+    let run_dir: RunDir = RunDir::create(&base.join("runs")).unwrap();
+    let workspace: PathBuf = run_dir.create_workspace(&template).unwrap();
+    assert_eq!(workspace, run_dir.workspace_path());
+    fs::write(workspace.join("src/main.rs"), "fn main() { generated(); }\n").unwrap();
+
+    // generated code only ever changes the copy
+    assert_eq!(
+      fs::read_to_string(template.join("src/main.rs")).unwrap(),
+      "fn main() {}\n"
+    );
+    assert!(!workspace.join("target").exists());
+
+    let missing: Result<PathBuf, AutoGippityError> = RunDir::create(&base.join("runs"))
+      .unwrap()
+      .create_workspace(&base.join("no_template"));
+    assert!(matches!(missing, Err(AutoGippityError::Config(_))));
+  }
+
+  #[test]
+  fn test_clean_keeps_newest_runs() {
+    let temp: TempDir = TempDir::new("clean_runs");
+    let base: PathBuf = temp.path().to_path_buf();
+    let now: u64 = now_secs();
+    for secs in [now - 10 * 86400, now - 2 * 86400, now - 60] {
+      let run_dir: RunDir = RunDir {
+        path: base.join(format!("run-{}", secs)),
+      };
+      fs::create_dir_all(run_dir.path()).unwrap();
+      fs::write(run_dir.path().join(CHECKPOINT_FILE), "{}").unwrap();
+    }
+    fs::create_dir_all(base.join("sandbox")).unwrap();
+
+    let runs: Vec<RunDir> = RunDir::list(&base).unwrap();
+    assert_eq!(runs.len(), 3);
+    assert_eq!(runs[0].created_secs(), now - 60);
+
+    let week: Option<Duration> = Some(Duration::from_secs(7 * 86400));
+    let planned: Vec<PathBuf> = clean_runs(&base, 1, week, true).unwrap();
+    assert_eq!(planned, vec![base.join(format!("run-{}", now - 10 * 86400))]);
+    assert_eq!(RunDir::list(&base).unwrap().len(), 3);
+
+    let removed: Vec<PathBuf> = clean_runs(&base, 1, None, false).unwrap();
+    assert_eq!(removed.len(), 2);
+    assert_eq!(RunDir::list(&base).unwrap().len(), 1);
+    assert!(base.join("sandbox").exists());
+  }
+}
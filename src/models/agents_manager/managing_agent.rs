@@ -1,36 +1,84 @@
 use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
-use crate::models::agents::agent_traits::{FactSheet, SpecialFunctions};
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_traits::{FactSheet, RouteObject, SpecialFunctions};
+use crate::models::agents_manager::checkpoint::{AgentCheckpoint, Checkpoint, RunDir};
+use crate::models::general::config::Config;
+use crate::models::general::llm::Message;
 
 use crate::ai_functions::aifunc_managing::convert_user_input_to_goal;
-use crate::helpers::general::ai_task_request;
+use crate::helpers::general::{ai_task_request, read_generated_sources, GeneratedSource};
 use crate::models::agents::agent_architect::AgentSolutionArchitect;
+use crate::models::agents::agent_auth::AgentAuthDeveloper;
 use crate::models::agents::agent_backend::AgentBackendDeveloper;
-use crate::models::general::llm::Message;
+use crate::models::agents::agent_database::AgentDatabaseDeveloper;
+use crate::models::agents::agent_frontend::AgentFrontendDeveloper;
+use crate::models::agents::agent_qa::AgentQaEngineer;
+use crate::models::agents::agent_security::AgentSecurityReviewer;
+use crate::models::general::error::AutoGippityError;
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::endpoint_tests::EndpointResult;
+use crate::helpers::openapi::openapi_document;
+use crate::helpers::security_review::findings_summary;
 
 #[derive(Debug)]
 pub struct ManagingAgent {
   attributes: BasicAgent,
   factsheet: FactSheet,
   agents: Vec<Box<dyn SpecialFunctions>>,
+  config: Config,
+  run_dir: RunDir,
+  // agent states loaded from a checkpoint, applied once the agents are created
+  resumed_agents: Vec<AgentCheckpoint>,
 }
 
 impl ManagingAgent {
-  pub async fn new(user_req: String) -> Result<Self, Box<dyn std::error::Error>> {
-    let position: String = "Project Manager".to_string();
-
-    let attributes: BasicAgent = BasicAgent {
-      objective: "Manage agents who are building an excellent website for the user".to_string(),
-      position: position.clone(),
-      state: AgentState::Discovery,
-      memory: vec![],
-    };
+  fn manager_attributes() -> BasicAgent {
+    BasicAgent::new(
+      "Manage agents who are building an excellent website for the user".to_string(),
+      "Project Manager".to_string(),
+    )
+  }
+
+  // point the agents at this run's own copy of the template
+  fn run_config(run_dir: &RunDir, mut config: Config) -> Config {
+    config.workspace.project_dir = run_dir.workspace_path();
+    if config.sandbox.dir.is_none() {
+      config.sandbox.dir = Some(run_dir.sandbox_path());
+    }
+    config
+  }
+
+  pub async fn new(
+    user_req: String,
+    run_dir: RunDir,
+    config: Config,
+  ) -> Result<Self, AutoGippityError> {
+    let mut attributes: BasicAgent = Self::manager_attributes();
+    let position: String = attributes.get_position().clone();
+    run_dir.create_workspace(&config.workspace.project_dir)?;
+    let config: Config = Self::run_config(&run_dir, config);
+
     let project_description: String = ai_task_request(
-      user_req,
+      user_req.clone(),
       &position,
       get_function_string!(convert_user_input_to_goal),
       convert_user_input_to_goal,
+      &config.llm,
     )
-    .await;
+    .await?;
+
+    // keep the request and the goal it became in the run log
+    attributes.memory.push(Message {
+      role: "user".to_string(),
+      content: user_req,
+    });
+    attributes.memory.push(Message {
+      role: "assistant".to_string(),
+      content: project_description.clone(),
+    });
+    run_dir
+      .run_log()
+      .record(&position, "goal", attributes.get_memory())?;
 
     let agents: Vec<Box<dyn SpecialFunctions>> = vec![];
 
@@ -38,14 +86,67 @@ impl ManagingAgent {
       project_description,
       project_scope: None,
       external_urls: None,
+      database_schema: None,
       backend_code: None,
       api_endpoint_schema: None,
+      frontend_code: None,
+      security_findings: None,
     };
 
-    Ok(Self {
+    let managing_agent: ManagingAgent = Self {
       attributes,
       agents,
       factsheet,
+      config,
+      run_dir,
+      resumed_agents: vec![],
+    };
+
+    // the goal is already a paid llm call, keep it
+    managing_agent.save_checkpoint()?;
+    let run_msg: String = format!(
+      "Saving checkpoints to {}, generating into {}",
+      managing_agent.run_dir.path().display(),
+      managing_agent.config.workspace.project_dir.display()
+    );
+    PrintCommand::AICall.print_agent_message(position.as_str(), run_msg.as_str());
+
+    Ok(managing_agent)
+  }
+
+  // load a previous run from its last checkpoint without calling the llm again
+  pub fn resume(run_dir: RunDir, config: Config) -> Result<Self, AutoGippityError> {
+    let checkpoint: Checkpoint = run_dir.load()?;
+    let config: Config = Self::run_config(&run_dir, config);
+    let attributes: BasicAgent = Self::manager_attributes();
+
+    Ok(Self {
+      attributes,
+      agents: vec![],
+      factsheet: checkpoint.factsheet,
+      config,
+      run_dir,
+      resumed_agents: checkpoint.agents,
+    })
+  }
+
+  fn save_checkpoint(&self) -> Result<(), AutoGippityError> {
+    let agents: Vec<AgentCheckpoint> = self
+      .agents
+      .iter()
+      .map(|agent| {
+        let agent_info: &BasicAgent = agent.get_attributes_from_agent();
+        AgentCheckpoint {
+          position: agent_info.get_position().clone(),
+          state: agent_info.get_state().clone(),
+          fix_loop: agent.fix_loop_state(),
+        }
+      })
+      .collect();
+
+    self.run_dir.save(&Checkpoint {
+      factsheet: self.factsheet.clone(),
+      agents,
     })
   }
 
@@ -54,38 +155,360 @@ impl ManagingAgent {
   }
 
   fn create_agents(&mut self) {
-    self.add_agent(Box::new(AgentSolutionArchitect::new()));
-    self.add_agent(Box::new(AgentBackendDeveloper::new()));
+    self.agents.clear();
+    self.add_agent(Box::new(AgentSolutionArchitect::new(self.config.clone())));
+    self.add_agent(Box::new(AgentDatabaseDeveloper::new(
+      self.config.clone(),
+      self.run_dir.run_log(),
+    )));
+    self.add_agent(Box::new(AgentBackendDeveloper::new(
+      self.config.clone(),
+      self.run_dir.run_log(),
+    )));
+    self.add_agent(Box::new(AgentAuthDeveloper::new(
+      self.config.clone(),
+      self.run_dir.run_log(),
+    )));
+    self.add_agent(Box::new(AgentQaEngineer::new(
+      self.config.clone(),
+      self.run_dir.run_log(),
+    )));
+    self.add_agent(Box::new(AgentFrontendDeveloper::new(
+      self.config.clone(),
+      self.run_dir.run_log(),
+    )));
+    self.add_agent(Box::new(AgentSecurityReviewer::new(
+      self.config.clone(),
+      self.run_dir.run_log(),
+    )));
   }
 
-  pub async fn execute_project(&mut self) {
+  pub async fn execute_project(&mut self) -> Result<(), AutoGippityError> {
+    self.execute_agents(None).await
+  }
+
+  // run a single agent against the factsheet, eg. only the Solutions Architect
+  pub async fn execute_agent(&mut self, position: &str) -> Result<(), AutoGippityError> {
+    self.execute_agents(Some(position)).await
+  }
+
+  // start an agent over instead of continuing from its checkpointed state
+  pub fn restart_agent(&mut self, position: &str) {
+    self.resumed_agents.retain(|saved| saved.position != position);
+  }
+
+  async fn execute_agents(&mut self, only: Option<&str>) -> Result<(), AutoGippityError> {
     self.create_agents();
 
+    // pick up each agent where the checkpoint left it
     for agent in &mut self.agents {
-      let agent_res: Result<(), Box<dyn std::error::Error>> =
-        agent.execute(&mut self.factsheet).await;
+      let position: &String = agent.get_attributes_from_agent().get_position();
+      let saved: Option<&AgentCheckpoint> = self
+        .resumed_agents
+        .iter()
+        .find(|saved| &saved.position == position);
+      if let Some(saved) = saved {
+        agent
+          .get_attributes_mut_from_agent()
+          .update_state(saved.state.clone());
+        if let Some(fix_loop) = &saved.fix_loop {
+          agent.restore_fix_loop_state(fix_loop.clone());
+        }
+      }
+    }
+    self.save_checkpoint()?;
+
+    for i in 0..self.agents.len() {
+      let position: &str = self.agents[i].get_attributes_from_agent().get_position().as_str();
+      if only.is_some_and(|only| only != position) {
+        continue;
+      }
+
+      let agent_info: &BasicAgent = self.agents[i].get_attributes_from_agent();
+      if agent_info.get_state() != &AgentState::Finished {
+        let start_msg: String = format!("Starting: {}", agent_info.get_objective());
+        PrintCommand::AICall.print_agent_message(agent_info.get_position().as_str(), start_msg.as_str());
+      }
+
+      while self.agents[i].get_attributes_from_agent().state != AgentState::Finished {
+        let step_res: Result<(), AutoGippityError> = self.agents[i].step(&mut self.factsheet).await;
+
+        // stop the run at the first failing agent
+        if let Err(e) = step_res {
+          let agent_info: &BasicAgent = self.agents[i].get_attributes_from_agent();
+          let err_msg: String = format!(
+            "{} failed: {}. Continue with: auto_gippity resume {}",
+            agent_info.position,
+            e,
+            self.run_dir.path().display()
+          );
+          PrintCommand::Issue.print_agent_message(self.attributes.get_position().as_str(), err_msg.as_str());
+          return Err(e);
+        }
+
+        self.save_checkpoint()?;
+      }
+    }
+
+    Ok(())
+  }
+
+  fn saved_endpoints(&self) -> Result<&Vec<RouteObject>, AutoGippityError> {
+    self.factsheet.api_endpoint_schema.as_ref().ok_or_else(|| {
+      AutoGippityError::Config(format!(
+        "no api endpoints saved in {}, run the backend agent first",
+        self.run_dir.path().display()
+      ))
+    })
+  }
+
+  // check the endpoints saved by the backend agent against the generated server
+  pub async fn test_endpoints(&self) -> Result<(), AutoGippityError> {
+    let endpoints: &Vec<RouteObject> = self.saved_endpoints()?;
 
-      //   let agent_info = agent.get_attributes_from_agent();
+    let backend: AgentBackendDeveloper =
+      AgentBackendDeveloper::new(self.config.clone(), self.run_dir.run_log());
+    let sources: Vec<GeneratedSource> = read_generated_sources(&self.config.workspace)?;
+    backend.confirm_run(&sources, self.factsheet.external_urls.as_deref().unwrap_or_default())?;
+    let results: Vec<EndpointResult> = backend.test_endpoints(endpoints).await?;
 
-      //   dbg!(agent_info);
+    let failed: usize = results.iter().filter(|result| !result.passed).count();
+    if failed > 0 {
+      return Err(AutoGippityError::Build(format!(
+        "{} of {} endpoint tests failed",
+        failed,
+        results.len()
+      )));
     }
+    Ok(())
+  }
+
+  // openapi 3.1 document for the endpoints saved by the backend agent
+  pub fn openapi(&self) -> Result<serde_json::Value, AutoGippityError> {
+    let endpoints: &Vec<RouteObject> = self.saved_endpoints()?;
+    Ok(openapi_document(
+      &self.factsheet.project_description,
+      endpoints,
+      self.config.backend.port,
+    ))
+  }
+
+  // plain text summary of the run for the report command
+  pub fn report(&self) -> String {
+    let mut lines: Vec<String> = vec![
+      format!("Run: {}", self.run_dir.path().display()),
+      format!("Workspace: {}", self.config.workspace.project_dir.display()),
+      format!("Project: {}", self.factsheet.project_description),
+    ];
+
+    match &self.factsheet.project_scope {
+      Some(scope) => lines.push(format!(
+        "Scope: crud {}, user login {}, external urls {}",
+        scope.is_crud_required, scope.is_user_login_and_logout, scope.is_external_urls_required
+      )),
+      None => lines.push("Scope: not decided yet".to_string()),
+    }
+
+    if let Some(urls) = &self.factsheet.external_urls {
+      lines.push(format!("External urls: {}", urls.len()));
+      lines.extend(urls.iter().map(|url| format!("  {}", url)));
+    }
+
+    if let Some(data_model) = &self.factsheet.database_schema {
+      lines.push(format!("Database tables: {}", data_model.tables.len()));
+      lines.extend(data_model.tables.iter().map(|table| {
+        format!("  {} ({} columns)", table.name, table.data_columns().count())
+      }));
+    }
+
+    match &self.factsheet.backend_code {
+      Some(code) => lines.push(format!("Backend code: {} lines", code.lines().count())),
+      None => lines.push("Backend code: not written yet".to_string()),
+    }
+
+    match &self.factsheet.frontend_code {
+      Some(page) => lines.push(format!("Frontend page: {} lines", page.lines().count())),
+      None => lines.push("Frontend page: not written yet".to_string()),
+    }
+
+    match &self.factsheet.security_findings {
+      Some(findings) => lines.push(findings_summary(findings)),
+      None => lines.push("Security review: not run yet".to_string()),
+    }
+
+    if let Some(endpoints) = &self.factsheet.api_endpoint_schema {
+      lines.push(format!("API endpoints: {}", endpoints.len()));
+      lines.extend(endpoints.iter().map(|endpoint| {
+        format!("  {} {}", endpoint.method, endpoint.route)
+      }));
+    }
+
+    lines.push("Agents:".to_string());
+    if self.resumed_agents.is_empty() {
+      lines.push("  none started".to_string());
+    }
+    lines.extend(
+      self
+        .resumed_agents
+        .iter()
+        .map(|saved| format!("  {}: {:?}", saved.position, saved.state)),
+    );
+
+    lines.join("\n")
   }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::apis::mock_server::{MockLlmServer, MockReply};
+  use crate::helpers::test_support::{factsheet, unattended, web_template, TempDir};
+  use crate::models::general::config::LlmSettings;
+  use std::path::{Path, PathBuf};
+
+  // config whose template is a small cargo project under base, with runs kept there too
+  fn template_config(base: &Path, llm: LlmSettings) -> Config {
+    let template: PathBuf = base.join("template");
+    web_template(&template);
+
+    let mut config: Config = Config {
+      llm,
+      ..Config::default()
+    };
+    config.workspace.project_dir = template;
+    config.workspace.runs_dir = base.join("runs");
+    config
+  }
 
   #[tokio::test]
   async fn test_managing_agent() {
-    let user_request: &str = "need a full stack app that fetches and tracks my fitness progress. needs to include timezone info from the web.";
+    let user_request: &str = "need a full stack app where users sign up and log in to keep a log of their workouts.";
+
+    let temp: TempDir = TempDir::new("manager");
+    let base: PathBuf = temp.path().to_path_buf();
+    let config: Config = unattended(template_config(&base, LlmSettings::test_cassette()));
+    let run_dir: RunDir = RunDir::create(&config.workspace.runs_dir).unwrap();
+    let mut managing_agent: ManagingAgent =
+      ManagingAgent::new(user_request.to_string(), run_dir, config)
+        .await
+        .expect("Error creating managing agent");
+
+    managing_agent
+      .execute_project()
+      .await
+      .expect("Error executing project");
+
+    // every agent ran on the synthetic answers and its checks passed against the stand-in template
+    let factsheet: &FactSheet = &managing_agent.factsheet;
+    assert!(factsheet.database_schema.is_some());
+    let routes: Vec<&str> = factsheet
+      .api_endpoint_schema
+      .iter()
+      .flatten()
+      .map(|route_object| route_object.route.as_str())
+      .collect();
+    assert!(routes.contains(&"/workout/{id}") && routes.contains(&"/auth/login"));
+    assert!(factsheet.frontend_code.is_some());
+    assert_eq!(factsheet.security_findings, Some(vec![]));
+
+    let workspace: &Path = &managing_agent.config.workspace.project_dir;
+    assert!(workspace.join("src/qa_tests.rs").is_file());
+    assert!(workspace.join("static/index.html").is_file());
+  }
+
+  #[tokio::test]
+  async fn test_resume_continues_from_checkpoint() {
+    let temp: TempDir = TempDir::new("resume");
+    let base: PathBuf = temp.path().to_path_buf();
+    let run_dir: RunDir = RunDir::create(&base).unwrap();
 
-    let mut managing_agent: ManagingAgent = ManagingAgent::new(user_request.to_string())
+    // stopped after the goal was set and before the architect scoped the project
+    run_dir
+      .save(&Checkpoint {
+        factsheet: factsheet("build a website that tracks todos"),
+        agents: vec![
+          AgentCheckpoint {
+            position: "Solutions Architect".to_string(),
+            state: AgentState::Discovery,
+            fix_loop: None,
+          },
+          AgentCheckpoint {
+            position: "Database Developer".to_string(),
+            state: AgentState::Finished,
+            fix_loop: None,
+          },
+          AgentCheckpoint {
+            position: "Backend Developer".to_string(),
+            state: AgentState::Finished,
+            fix_loop: None,
+          },
+        ],
+      })
+      .unwrap();
+
+    let server: MockLlmServer = MockLlmServer::start().await;
+    server.on(
+      "print_project_scope",
+      vec![MockReply::Content(
+        r#"{"is_crud_required": true, "is_user_login_and_logout": false, "is_external_urls_required": false}"#
+          .to_string(),
+      )],
+    );
+
+    let config: Config = template_config(&base, server.llm_settings());
+    run_dir.create_workspace(&config.workspace.project_dir).unwrap();
+    let mut managing_agent: ManagingAgent =
+      ManagingAgent::resume(RunDir::open(run_dir.path()).unwrap(), config).unwrap();
+    managing_agent.execute_project().await.unwrap();
+    assert!(run_dir.workspace_path().join("src/main.rs").is_file());
+
+    assert_eq!(server.calls("convert_user_input_to_goal"), 0);
+    assert_eq!(server.calls("print_project_scope"), 1);
+
+    let saved: Checkpoint = run_dir.load().unwrap();
+    assert!(saved.factsheet.project_scope.unwrap().is_crud_required);
+    assert!(saved
+      .agents
+      .iter()
+      .all(|agent| agent.state == AgentState::Finished));
+  }
+
+  #[tokio::test]
+  async fn test_execute_agent_runs_only_that_agent() {
+    let temp: TempDir = TempDir::new("scope");
+    let base: PathBuf = temp.path().to_path_buf();
+    let run_dir: RunDir = RunDir::create(&base).unwrap();
+
+    let server: MockLlmServer = MockLlmServer::start().await;
+    server.on(
+      "convert_user_input_to_goal",
+      vec![MockReply::Content("build a website that tracks todos".to_string())],
+    );
+    server.on(
+      "print_project_scope",
+      vec![MockReply::Content(
+        r#"{"is_crud_required": true, "is_user_login_and_logout": false, "is_external_urls_required": false}"#
+          .to_string(),
+      )],
+    );
+
+    let mut managing_agent: ManagingAgent = ManagingAgent::new("todo app".to_string(), run_dir, template_config(&base, server.llm_settings()))
+      .await
+      .unwrap();
+    managing_agent.execute_agent(AgentSolutionArchitect::POSITION)
       .await
-      .expect("Error creating managing agent");
+      .unwrap();
 
-    managing_agent.execute_project().await;
+    assert_eq!(server.calls("print_backend_webserver_code"), 0);
 
-    dbg!(managing_agent.factsheet);
+    let run_path: &Path = managing_agent.run_dir.path();
+    let reopened: ManagingAgent =
+      ManagingAgent::resume(RunDir::open(run_path).unwrap(), template_config(&base, server.llm_settings())).unwrap();
+    let report: String = reopened.report();
+    assert!(report.contains(&format!("Workspace: {}", run_path.join("workspace").display())));
+    assert!(report.contains("Project: build a website that tracks todos"));
+    assert!(report.contains("Scope: crud true, user login false, external urls false"));
+    assert!(report.contains("Solutions Architect: Finished"));
+    assert!(report.contains("Backend Developer: Discovery"));
   }
 }
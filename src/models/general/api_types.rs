@@ -0,0 +1,268 @@
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase", try_from = "String")]
+pub enum HttpMethod {
+  Get,
+  Post,
+  Put,
+  Patch,
+  Delete,
+}
+
+impl HttpMethod {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Get => "get",
+      Self::Post => "post",
+      Self::Put => "put",
+      Self::Patch => "patch",
+      Self::Delete => "delete",
+    }
+  }
+}
+
+impl FromStr for HttpMethod {
+  type Err = String;
+
+  // the llm writes methods as "get", "GET" or "Get"
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_lowercase().as_str() {
+      "get" => Ok(Self::Get),
+      "post" => Ok(Self::Post),
+      "put" => Ok(Self::Put),
+      "patch" => Ok(Self::Patch),
+      "delete" => Ok(Self::Delete),
+      _ => Err(format!("'{}' is not an http method", s)),
+    }
+  }
+}
+
+impl TryFrom<String> for HttpMethod {
+  type Error = String;
+
+  fn try_from(s: String) -> Result<Self, Self::Error> {
+    s.parse()
+  }
+}
+
+// upper case, the way methods are printed in requests and logs
+impl fmt::Display for HttpMethod {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.as_str().to_uppercase())
+  }
+}
+
+impl JsonSchema for HttpMethod {
+  fn inline_schema() -> bool {
+    true
+  }
+
+  fn schema_name() -> Cow<'static, str> {
+    "HttpMethod".into()
+  }
+
+  // any case is accepted when decoding, so only ask for a string
+  fn json_schema(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({ "type": "string" })
+  }
+}
+
+// type of a request or response body as described by the endpoint schema
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(from = "Value", into = "Value")]
+pub enum FieldType {
+  // "None", no body is sent or returned
+  Absent,
+  // "not_provided" or a type name we do not recognise
+  Unknown,
+  Integer,
+  Number,
+  String,
+  Bool,
+  Array(Box<FieldType>),
+  Object(BTreeMap<String, FieldType>),
+  // "Option<T>", the field may be null or left out
+  Optional(Box<FieldType>),
+}
+
+impl FieldType {
+  // read a type name such as "number", "u64", "Vec<String>" or "Option<bool>"
+  fn from_tag(tag: &str) -> Self {
+    let tag: String = tag.trim().to_lowercase();
+    if let Some(inner) = tag.strip_prefix("vec<").and_then(|rest| rest.strip_suffix('>')) {
+      return Self::Array(Box::new(Self::from_tag(inner)));
+    }
+    if let Some(inner) = tag.strip_prefix("option<").and_then(|rest| rest.strip_suffix('>')) {
+      return Self::Optional(Box::new(Self::from_tag(inner)));
+    }
+    match tag.as_str() {
+      "none" | "null" | "" => Self::Absent,
+      "integer" | "int" | "u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32"
+      | "i64" | "isize" => Self::Integer,
+      "number" | "float" | "f32" | "f64" | "decimal" => Self::Number,
+      "string" | "str" | "&str" | "text" | "char" | "date" | "datetime" | "uuid" | "email" => {
+        Self::String
+      }
+      "bool" | "boolean" => Self::Bool,
+      "vec" | "array" | "list" => Self::Array(Box::new(Self::Unknown)),
+      _ => Self::Unknown,
+    }
+  }
+
+  // fields that may be left out of a body without it being a mismatch
+  pub fn is_optional(&self) -> bool {
+    matches!(self, Self::Absent | Self::Unknown | Self::Optional(_))
+  }
+}
+
+// lenient, whatever the llm printed becomes some field type
+impl From<Value> for FieldType {
+  fn from(value: Value) -> Self {
+    match value {
+      Value::Null => Self::Absent,
+      Value::Bool(_) => Self::Bool,
+      Value::Number(number) if number.is_f64() => Self::Number,
+      Value::Number(_) => Self::Integer,
+      Value::String(tag) => Self::from_tag(&tag),
+      Value::Array(items) => Self::Array(Box::new(
+        items.into_iter().next().map(Self::from).unwrap_or(Self::Unknown),
+      )),
+      Value::Object(fields) => Self::Object(
+        fields
+          .into_iter()
+          .map(|(name, field)| (name, Self::from(field)))
+          .collect(),
+      ),
+    }
+  }
+}
+
+// back to the format the llm is asked for, so saved schemas read the same
+impl From<FieldType> for Value {
+  fn from(field_type: FieldType) -> Self {
+    match field_type {
+      FieldType::Absent => Value::from("None"),
+      FieldType::Unknown => Value::from("not_provided"),
+      FieldType::Integer => Value::from("integer"),
+      FieldType::Number => Value::from("number"),
+      FieldType::String => Value::from("string"),
+      FieldType::Bool => Value::from("bool"),
+      FieldType::Array(item) => Value::Array(match *item {
+        FieldType::Unknown => vec![],
+        item => vec![Value::from(item)],
+      }),
+      FieldType::Object(fields) => Value::Object(
+        fields
+          .into_iter()
+          .map(|(name, field)| (name, Value::from(field)))
+          .collect::<Map<String, Value>>(),
+      ),
+      FieldType::Optional(inner) => match Value::from(*inner) {
+        Value::String(tag) => Value::from(format!("Option<{}>", tag)),
+        Value::Array(items) => match items.as_slice() {
+          [Value::String(tag)] => Value::from(format!("Option<Vec<{}>>", tag)),
+          _ => Value::from("Option<Vec>"),
+        },
+        // the tag format has no optional objects, they are written as plain objects
+        other => other,
+      },
+    }
+  }
+}
+
+impl JsonSchema for FieldType {
+  fn inline_schema() -> bool {
+    true
+  }
+
+  fn schema_name() -> Cow<'static, str> {
+    "FieldType".into()
+  }
+
+  fn json_schema(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({ "type": ["string", "object", "array", "null"] })
+  }
+}
+
+// bool that the llm may also print as "true" or "false"
+pub fn lenient_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+  match Value::deserialize(deserializer)? {
+    Value::Bool(flag) => Ok(flag),
+    Value::String(flag) => match flag.trim().to_lowercase().as_str() {
+      "true" => Ok(true),
+      "false" => Ok(false),
+      _ => Err(serde::de::Error::custom(format!("'{}' is not a bool", flag))),
+    },
+    other => Err(serde::de::Error::custom(format!("{} is not a bool", other))),
+  }
+}
+
+pub fn lenient_bool_schema(_: &mut SchemaGenerator) -> Schema {
+  json_schema!({ "type": ["boolean", "string"] })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_http_method_is_case_insensitive() {
+    let method: HttpMethod = serde_json::from_value(json!("GET")).unwrap();
+    assert_eq!(method, HttpMethod::Get);
+    assert_eq!(method.to_string(), "GET");
+    assert_eq!(serde_json::to_value(HttpMethod::Patch).unwrap(), json!("patch"));
+
+    let err: serde_json::Error = serde_json::from_value::<HttpMethod>(json!("fetch")).unwrap_err();
+    assert!(err.to_string().contains("'fetch' is not an http method"));
+  }
+
+  #[test]
+  fn test_field_types_from_llm_format() {
+    let field_type: FieldType = serde_json::from_value(json!({
+      "id": "u64",
+      "price": "f64",
+      "name": "String",
+      "done": "bool",
+      "tags": ["string"],
+      "ids": "Vec<u32>",
+      "note": "Option<String>",
+      "scores": "Option<Vec<f64>>",
+      "extra": "None",
+      "meta": "not_provided",
+    }))
+    .unwrap();
+
+    let FieldType::Object(fields) = &field_type else {
+      panic!("expected an object, got {:?}", field_type);
+    };
+    assert_eq!(fields["id"], FieldType::Integer);
+    assert_eq!(fields["price"], FieldType::Number);
+    assert_eq!(fields["name"], FieldType::String);
+    assert_eq!(fields["done"], FieldType::Bool);
+    assert_eq!(fields["tags"], FieldType::Array(Box::new(FieldType::String)));
+    assert_eq!(fields["ids"], FieldType::Array(Box::new(FieldType::Integer)));
+    assert_eq!(fields["note"], FieldType::Optional(Box::new(FieldType::String)));
+    assert_eq!(
+      fields["scores"],
+      FieldType::Optional(Box::new(FieldType::Array(Box::new(FieldType::Number))))
+    );
+    assert!(fields["note"].is_optional());
+    assert!(!fields["name"].is_optional());
+    assert_eq!(fields["extra"], FieldType::Absent);
+    assert_eq!(fields["meta"], FieldType::Unknown);
+
+    // written back in the same tag format
+    let round_trip: FieldType =
+      serde_json::from_value(serde_json::to_value(&field_type).unwrap()).unwrap();
+    assert_eq!(round_trip, field_type);
+    assert_eq!(serde_json::to_value(FieldType::Absent).unwrap(), json!("None"));
+  }
+}
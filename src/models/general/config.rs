@@ -0,0 +1,520 @@
+use crate::apis::llm_provider::{LlmConfig, ProviderKind};
+use crate::apis::retry::RetryPolicy;
+use crate::apis::transcript::{TranscriptConfig, TranscriptMode, DEFAULT_CASSETTE_PATH};
+use crate::helpers::approval::ApprovalPolicy;
+use crate::helpers::sandbox::SandboxBackend;
+use crate::models::general::error::AutoGippityError;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+// read from the working directory when no config file is given
+pub const DEFAULT_CONFIG_FILE: &str = "auto_gippity.toml";
+
+// where the backend agent writes, builds and records its output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkspaceConfig {
+  // cargo project template, copied into each run and never written to
+  pub project_dir: PathBuf,
+  // relative to project_dir
+  pub code_template: PathBuf,
+  // relative to project_dir
+  pub exec_main: PathBuf,
+  // relative to project_dir
+  pub api_schema: PathBuf,
+  // relative to project_dir
+  pub openapi: PathBuf,
+  // relative to project_dir
+  pub frontend_page: PathBuf,
+  // relative to project_dir
+  pub data_access: PathBuf,
+  // relative to project_dir
+  pub migrations_dir: PathBuf,
+  // relative to project_dir, a module next to exec_main
+  pub qa_tests: PathBuf,
+  pub runs_dir: PathBuf,
+}
+
+impl Default for WorkspaceConfig {
+  fn default() -> Self {
+    Self {
+      project_dir: PathBuf::from("../web_template"),
+      code_template: PathBuf::from("src/code_template.rs"),
+      exec_main: PathBuf::from("src/main.rs"),
+      api_schema: PathBuf::from("schemas/api_schema.json"),
+      openapi: PathBuf::from("schemas/openapi.json"),
+      frontend_page: PathBuf::from("static/index.html"),
+      data_access: PathBuf::from("src/db.rs"),
+      migrations_dir: PathBuf::from("migrations"),
+      qa_tests: PathBuf::from("src/qa_tests.rs"),
+      runs_dir: PathBuf::from("runs"),
+    }
+  }
+}
+
+impl WorkspaceConfig {
+  pub fn code_template_path(&self) -> PathBuf {
+    self.project_dir.join(&self.code_template)
+  }
+
+  pub fn exec_main_path(&self) -> PathBuf {
+    self.project_dir.join(&self.exec_main)
+  }
+
+  pub fn api_schema_path(&self) -> PathBuf {
+    self.project_dir.join(&self.api_schema)
+  }
+
+  pub fn openapi_path(&self) -> PathBuf {
+    self.project_dir.join(&self.openapi)
+  }
+
+  pub fn frontend_page_path(&self) -> PathBuf {
+    self.project_dir.join(&self.frontend_page)
+  }
+
+  pub fn data_access_path(&self) -> PathBuf {
+    self.project_dir.join(&self.data_access)
+  }
+
+  pub fn migrations_path(&self) -> PathBuf {
+    self.project_dir.join(&self.migrations_dir)
+  }
+
+  pub fn qa_tests_path(&self) -> PathBuf {
+    self.project_dir.join(&self.qa_tests)
+  }
+
+  pub fn cargo_toml_path(&self) -> PathBuf {
+    self.project_dir.join("Cargo.toml")
+  }
+}
+
+// which llm to call and how patiently
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmSettings {
+  pub provider: String,
+  // provider default when not set
+  pub model: Option<String>,
+  // provider default when not set
+  pub base_url: Option<String>,
+  pub temperature: f32,
+  pub max_attempts: u32,
+  pub backoff_base_ms: u64,
+  pub backoff_max_ms: u64,
+  // longest Retry-After waited for, longer ones give up
+  pub retry_after_max_ms: u64,
+  pub backoff_jitter: f64,
+  pub decode_attempts: u32,
+  // record or replay llm exchanges, off calls the provider
+  pub transcript_mode: TranscriptMode,
+  pub cassette_path: PathBuf,
+}
+
+impl Default for LlmSettings {
+  fn default() -> Self {
+    let retry_policy: RetryPolicy = RetryPolicy::default();
+    Self {
+      provider: "openai".to_string(),
+      model: None,
+      base_url: None,
+      temperature: 0.1,
+      max_attempts: retry_policy.max_attempts,
+      backoff_base_ms: retry_policy.base_backoff.as_millis() as u64,
+      backoff_max_ms: retry_policy.max_backoff.as_millis() as u64,
+      retry_after_max_ms: retry_policy.max_retry_after.as_millis() as u64,
+      backoff_jitter: retry_policy.jitter,
+      decode_attempts: 3,
+      transcript_mode: TranscriptMode::Off,
+      cassette_path: PathBuf::from(DEFAULT_CASSETTE_PATH),
+    }
+  }
+}
+
+impl LlmSettings {
+  pub fn llm_config(&self) -> Result<LlmConfig, AutoGippityError> {
+    let provider: ProviderKind =
+      ProviderKind::from_str(&self.provider).map_err(AutoGippityError::Config)?;
+
+    let mut config: LlmConfig = LlmConfig::new(provider);
+    if let Some(model) = &self.model {
+      config.model = model.clone();
+    }
+    if let Some(base_url) = &self.base_url {
+      config.base_url = base_url.clone();
+    }
+    config.temperature = self.temperature;
+
+    // api keys only ever come from the environment
+    Ok(config.with_env_credentials())
+  }
+
+  pub fn transcript_config(&self) -> TranscriptConfig {
+    TranscriptConfig {
+      mode: self.transcript_mode,
+      cassette_path: self.cassette_path.clone(),
+    }
+  }
+
+  // replay the committed synthetic cassette so tests never reach a provider
+  #[cfg(test)]
+  pub fn test_cassette() -> Self {
+    Self {
+      transcript_mode: TranscriptMode::Replay,
+      cassette_path: PathBuf::from("cassettes/synthetic.json"),
+      ..Self::default()
+    }
+  }
+
+  pub fn retry_policy(&self) -> RetryPolicy {
+    RetryPolicy {
+      max_attempts: self.max_attempts.max(1),
+      base_backoff: Duration::from_millis(self.backoff_base_ms),
+      max_backoff: Duration::from_millis(self.backoff_max_ms),
+      max_retry_after: Duration::from_millis(self.retry_after_max_ms),
+      jitter: self.backoff_jitter.clamp(0.0, 1.0),
+    }
+  }
+}
+
+// how the backend agent builds and tests the generated server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackendConfig {
+  // 0 picks a free port for every test run
+  pub port: u16,
+  // give up when the server is not listening after this long, includes compiling it
+  #[serde(alias = "startup_wait_secs")]
+  pub startup_timeout_secs: u64,
+  pub request_timeout_secs: u64,
+  // after asking the server to stop, kill it when still running this long
+  pub shutdown_grace_secs: u64,
+  // failed builds allowed before giving up
+  pub bug_limit: u8,
+}
+
+impl Default for BackendConfig {
+  fn default() -> Self {
+    Self {
+      port: 0,
+      startup_timeout_secs: 120,
+      request_timeout_secs: 5,
+      shutdown_grace_secs: 5,
+      bug_limit: 2,
+    }
+  }
+}
+
+// who decides whether generated code may be built and run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApprovalConfig {
+  pub policy: ApprovalPolicy,
+  // security review checks the allowlist policy accepts, eg. "filesystem"
+  pub allowed_patterns: Vec<String>,
+  // deny code with high severity security findings, whatever the policy
+  pub block_high_severity: bool,
+}
+
+impl Default for ApprovalConfig {
+  fn default() -> Self {
+    Self {
+      policy: ApprovalPolicy::Interactive,
+      allowed_patterns: vec![],
+      block_high_severity: false,
+    }
+  }
+}
+
+// isolation and limits for building and running generated code
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+  pub backend: SandboxBackend,
+  // copy of the workspace that is built and run, sandbox/ in the run directory when not set
+  pub dir: Option<PathBuf>,
+  // per process, 0 for no limit
+  pub cpu_secs: u64,
+  // address space per process, 0 for no limit
+  pub memory_mb: u64,
+  pub build_timeout_secs: u64,
+}
+
+impl Default for SandboxConfig {
+  fn default() -> Self {
+    Self {
+      backend: SandboxBackend::Auto,
+      dir: None,
+      cpu_secs: 600,
+      memory_mb: 8192,
+      build_timeout_secs: 900,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  // false for scripts and ci, never wait on stdin
+  pub interactive: bool,
+  pub workspace: WorkspaceConfig,
+  pub llm: LlmSettings,
+  pub backend: BackendConfig,
+  pub approval: ApprovalConfig,
+  pub sandbox: SandboxConfig,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      interactive: true,
+      workspace: WorkspaceConfig::default(),
+      llm: LlmSettings::default(),
+      backend: BackendConfig::default(),
+      approval: ApprovalConfig::default(),
+      sandbox: SandboxConfig::default(),
+    }
+  }
+}
+
+// values given on the command line, these win over the file and env
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CliOverrides {
+  pub config_file: Option<PathBuf>,
+  pub non_interactive: bool,
+  pub project_dir: Option<PathBuf>,
+  pub runs_dir: Option<PathBuf>,
+  pub provider: Option<String>,
+  pub model: Option<String>,
+  pub port: Option<u16>,
+  pub approval: Option<ApprovalPolicy>,
+}
+
+fn env_value<T: FromStr>(name: &str) -> Result<Option<T>, AutoGippityError> {
+  match env::var(name) {
+    Ok(value) => value
+      .trim()
+      .parse()
+      .map(Some)
+      .map_err(|_| AutoGippityError::Config(format!("invalid {} '{}'", name, value))),
+    Err(_) => Ok(None),
+  }
+}
+
+impl Config {
+  pub fn from_toml_str(contents: &str) -> Result<Self, AutoGippityError> {
+    toml::from_str(contents).map_err(|e| AutoGippityError::Config(e.to_string()))
+  }
+
+  pub fn from_file(path: &Path) -> Result<Self, AutoGippityError> {
+    let contents: String = fs::read_to_string(path).map_err(|e| {
+      AutoGippityError::Config(format!("failed to read config {}: {}", path.display(), e))
+    })?;
+    Self::from_toml_str(&contents)
+      .map_err(|e| AutoGippityError::Config(format!("invalid config {}: {}", path.display(), e)))
+  }
+
+  pub fn sandbox_dir(&self) -> PathBuf {
+    self
+      .sandbox
+      .dir
+      .clone()
+      .unwrap_or_else(|| self.workspace.runs_dir.join("sandbox"))
+  }
+
+  // config file, then env variables, then command line flags
+  pub fn load(cli: &CliOverrides) -> Result<Self, AutoGippityError> {
+    dotenv::dotenv().ok();
+
+    let config_file: Option<PathBuf> = cli
+      .config_file
+      .clone()
+      .or_else(|| env::var("AUTO_GIPPITY_CONFIG").ok().map(PathBuf::from));
+
+    let mut config: Config = match config_file {
+      Some(path) => Self::from_file(&path)?,
+      None if Path::new(DEFAULT_CONFIG_FILE).is_file() => {
+        Self::from_file(Path::new(DEFAULT_CONFIG_FILE))?
+      }
+      None => Self::default(),
+    };
+
+    config.apply_env()?;
+    config.apply_cli(cli);
+    Ok(config)
+  }
+
+  fn apply_env(&mut self) -> Result<(), AutoGippityError> {
+    if let Some(interactive) = env_value::<bool>("AUTO_GIPPITY_INTERACTIVE")? {
+      self.interactive = interactive;
+    }
+    if let Some(project_dir) = env_value::<PathBuf>("AUTO_GIPPITY_PROJECT_DIR")? {
+      self.workspace.project_dir = project_dir;
+    }
+    if let Some(runs_dir) = env_value::<PathBuf>("AUTO_GIPPITY_RUNS_DIR")? {
+      self.workspace.runs_dir = runs_dir;
+    }
+
+    if let Some(provider) = env_value::<String>("LLM_PROVIDER")? {
+      self.llm.provider = provider;
+    }
+    if let Some(model) = env_value::<String>("LLM_MODEL")? {
+      self.llm.model = Some(model);
+    }
+    if let Some(base_url) = env_value::<String>("LLM_BASE_URL")? {
+      self.llm.base_url = Some(base_url);
+    }
+    if let Some(temperature) = env_value::<f32>("LLM_TEMPERATURE")? {
+      self.llm.temperature = temperature;
+    }
+    if let Some(max_attempts) = env_value::<u32>("LLM_MAX_ATTEMPTS")? {
+      self.llm.max_attempts = max_attempts;
+    }
+    if let Some(base_ms) = env_value::<u64>("LLM_BACKOFF_BASE_MS")? {
+      self.llm.backoff_base_ms = base_ms;
+    }
+    if let Some(max_ms) = env_value::<u64>("LLM_BACKOFF_MAX_MS")? {
+      self.llm.backoff_max_ms = max_ms;
+    }
+    if let Some(max_ms) = env_value::<u64>("LLM_RETRY_AFTER_MAX_MS")? {
+      self.llm.retry_after_max_ms = max_ms;
+    }
+    if let Some(jitter) = env_value::<f64>("LLM_BACKOFF_JITTER")? {
+      self.llm.backoff_jitter = jitter;
+    }
+    if let Some(decode_attempts) = env_value::<u32>("LLM_DECODE_ATTEMPTS")? {
+      self.llm.decode_attempts = decode_attempts;
+    }
+    if let Some(transcript_mode) = env_value::<TranscriptMode>("LLM_TRANSCRIPT_MODE")? {
+      self.llm.transcript_mode = transcript_mode;
+    }
+    if let Some(cassette_path) = env_value::<PathBuf>("LLM_CASSETTE")? {
+      self.llm.cassette_path = cassette_path;
+    }
+
+    if let Some(port) = env_value::<u16>("AUTO_GIPPITY_PORT")? {
+      self.backend.port = port;
+    }
+    if let Some(policy) = env_value::<ApprovalPolicy>("AUTO_GIPPITY_APPROVAL")? {
+      self.approval.policy = policy;
+    }
+    if let Some(backend) = env_value::<SandboxBackend>("AUTO_GIPPITY_SANDBOX")? {
+      self.sandbox.backend = backend;
+    }
+    Ok(())
+  }
+
+  fn apply_cli(&mut self, cli: &CliOverrides) {
+    if cli.non_interactive {
+      self.interactive = false;
+    }
+    if let Some(project_dir) = &cli.project_dir {
+      self.workspace.project_dir = project_dir.clone();
+    }
+    if let Some(runs_dir) = &cli.runs_dir {
+      self.workspace.runs_dir = runs_dir.clone();
+    }
+    if let Some(provider) = &cli.provider {
+      self.llm.provider = provider.clone();
+    }
+    if let Some(model) = &cli.model {
+      self.llm.model = Some(model.clone());
+    }
+    if let Some(port) = cli.port {
+      self.backend.port = port;
+    }
+    if let Some(policy) = cli.approval {
+      self.approval.policy = policy;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_partial_file_keeps_defaults() {
+    let config: Config = Config::from_toml_str(
+      r#"
+        [workspace]
+        project_dir = "/tmp/site"
+
+        [backend]
+        port = 9090
+
+        [approval]
+        policy = "allowlist"
+        allowed_patterns = ["filesystem"]
+
+        [sandbox]
+        backend = "bubblewrap"
+      "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.workspace.project_dir, PathBuf::from("/tmp/site"));
+    assert_eq!(
+      config.workspace.exec_main_path(),
+      PathBuf::from("/tmp/site/src/main.rs")
+    );
+    assert_eq!(config.backend.port, 9090);
+    assert_eq!(config.backend.bug_limit, 2);
+    assert!(config.interactive);
+    assert_eq!(config.approval.policy, ApprovalPolicy::Allowlist);
+    assert_eq!(config.approval.allowed_patterns, vec!["filesystem"]);
+    assert_eq!(config.llm, LlmSettings::default());
+    assert_eq!(config.sandbox.backend, SandboxBackend::Bubblewrap);
+    assert_eq!(config.sandbox_dir(), PathBuf::from("runs/sandbox"));
+  }
+
+  #[test]
+  fn test_unknown_keys_are_rejected() {
+    let res: Result<Config, AutoGippityError> = Config::from_toml_str("[backend]\nprot = 9090\n");
+    assert!(matches!(res, Err(AutoGippityError::Config(_))));
+  }
+
+  #[test]
+  fn test_cli_overrides_win() {
+    let mut config: Config = Config::from_toml_str("[llm]\nmodel = \"gpt-4o\"\n").unwrap();
+    config.apply_cli(&CliOverrides {
+      non_interactive: true,
+      model: Some("llama3".to_string()),
+      port: Some(3000),
+      ..Default::default()
+    });
+
+    assert_eq!(config.llm.model, Some("llama3".to_string()));
+    assert_eq!(config.backend.port, 3000);
+    assert!(!config.interactive);
+  }
+
+  #[test]
+  fn test_llm_settings_build_provider_config() {
+    let settings: LlmSettings = LlmSettings {
+      provider: "ollama".to_string(),
+      max_attempts: 0,
+      ..Default::default()
+    };
+
+    let llm_config: LlmConfig = settings.llm_config().unwrap();
+    assert_eq!(llm_config.provider, ProviderKind::Ollama);
+    assert_eq!(llm_config.model, "llama3");
+    assert_eq!(settings.retry_policy().max_attempts, 1);
+
+    let unknown: LlmSettings = LlmSettings {
+      provider: "skynet".to_string(),
+      ..Default::default()
+    };
+    assert!(matches!(
+      unknown.llm_config(),
+      Err(AutoGippityError::Config(_))
+    ));
+  }
+}
@@ -0,0 +1,196 @@
+use crate::helpers::output_schema::OutputRules;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// words that cannot name a generated struct, field or function, reserved ones included
+const RUST_KEYWORDS: [&str; 51] = [
+  "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do",
+  "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+  "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self",
+  "static", "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+  "virtual", "where", "while", "yield",
+];
+
+// table names that clash in the generated db.rs, as the conn parameter or as an imported type
+const GENERATED_NAMES: [&str; 7] = [
+  "conn",
+  "row",
+  "connection",
+  "result",
+  "optional_extension",
+  "serialize",
+  "deserialize",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+  Integer,
+  Real,
+  Text,
+  Boolean,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ColumnModel {
+  pub name: String,
+  pub column_type: ColumnType,
+  #[serde(default)]
+  pub nullable: bool,
+  // name of the table whose id this column holds
+  #[serde(default)]
+  pub references: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct TableModel {
+  pub name: String,
+  // an integer id primary key is always added, an "id" column listed here is ignored
+  pub columns: Vec<ColumnModel>,
+}
+
+impl TableModel {
+  pub fn data_columns(&self) -> impl Iterator<Item = &ColumnModel> {
+    self.columns.iter().filter(|column| column.name != "id")
+  }
+}
+
+// tables of the generated server's sqlite database, in creation order
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct DataModel {
+  pub tables: Vec<TableModel>,
+}
+
+fn name_violation(path: &str, name: &str) -> Option<String> {
+  let snake_case: bool = name.starts_with(|c: char| c.is_ascii_lowercase())
+    && name
+      .chars()
+      .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+  if !snake_case {
+    return Some(format!("{}: '{}' must be a lowercase snake_case name", path, name));
+  }
+  if RUST_KEYWORDS.contains(&name) {
+    return Some(format!("{}: '{}' is a rust keyword, pick another name", path, name));
+  }
+  None
+}
+
+fn table_name_violation(path: &str, name: &str) -> Option<String> {
+  if GENERATED_NAMES.contains(&name) {
+    return Some(format!(
+      "{}: '{}' clashes with a name in the generated data access code, pick another name",
+      path, name
+    ));
+  }
+  name_violation(path, name)
+}
+
+impl OutputRules for DataModel {
+  fn rule_violations(&self) -> Vec<String> {
+    let mut violations: Vec<String> = vec![];
+    if self.tables.is_empty() {
+      violations.push("$.tables: at least one table must be listed".to_string());
+    }
+
+    for (i, table) in self.tables.iter().enumerate() {
+      let table_path: String = format!("$.tables[{}]", i);
+      violations.extend(table_name_violation(&format!("{}.name", table_path), &table.name));
+      if self.tables[..i].iter().any(|earlier| earlier.name == table.name) {
+        violations.push(format!("{}.name: table '{}' is listed twice", table_path, table.name));
+      }
+      if table.data_columns().next().is_none() {
+        violations.push(format!("{}.columns: at least one column besides id is needed", table_path));
+      }
+
+      for (j, column) in table.columns.iter().enumerate() {
+        let column_path: String = format!("{}.columns[{}]", table_path, j);
+        violations.extend(name_violation(&format!("{}.name", column_path), &column.name));
+        if table.columns[..j].iter().any(|earlier| earlier.name == column.name) {
+          violations.push(format!("{}.name: column '{}' is listed twice", column_path, column.name));
+        }
+
+        let Some(referenced) = &column.references else {
+          continue;
+        };
+        // tables are created in order, so a reference must point back
+        if !self.tables[..i].iter().any(|earlier| &earlier.name == referenced) {
+          violations.push(format!(
+            "{}.references: '{}' must be a table listed before '{}'",
+            column_path, referenced, table.name
+          ));
+        }
+        if column.column_type != ColumnType::Integer {
+          violations.push(format!(
+            "{}.column_type: a column referencing '{}' must be an integer",
+            column_path, referenced
+          ));
+        }
+      }
+    }
+    violations
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_data_model_rules() {
+    let model: DataModel = serde_json::from_value(json!({
+      "tables": [
+        {"name": "user", "columns": [{"name": "id", "column_type": "integer"}, {"name": "email", "column_type": "text"}]},
+        {"name": "todo", "columns": [
+          {"name": "title", "column_type": "text"},
+          {"name": "done", "column_type": "boolean"},
+          {"name": "user_id", "column_type": "integer", "references": "user", "nullable": true}
+        ]}
+      ]
+    }))
+    .unwrap();
+    assert!(model.rule_violations().is_empty());
+
+    let model: DataModel = serde_json::from_value(json!({
+      "tables": [
+        {"name": "Todo", "columns": [{"name": "type", "column_type": "text"}, {"name": "owner", "column_type": "text", "references": "user"}]},
+        {"name": "user", "columns": [{"name": "id", "column_type": "integer"}]}
+      ]
+    }))
+    .unwrap();
+    assert_eq!(
+      model.rule_violations(),
+      vec![
+        "$.tables[0].name: 'Todo' must be a lowercase snake_case name",
+        "$.tables[0].columns[0].name: 'type' is a rust keyword, pick another name",
+        "$.tables[0].columns[1].references: 'user' must be a table listed before 'Todo'",
+        "$.tables[0].columns[1].column_type: a column referencing 'user' must be an integer",
+        "$.tables[1].columns: at least one column besides id is needed",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_reserved_and_generated_names() {
+    let model: DataModel = serde_json::from_value(json!({
+      "tables": [
+        {"name": "conn", "columns": [{"name": "try", "column_type": "text"}, {"name": "gen", "column_type": "integer"}]},
+        {"name": "row", "columns": [{"name": "conn", "column_type": "text"}]},
+        {"name": "box", "columns": [{"name": "row", "column_type": "text"}]}
+      ]
+    }))
+    .unwrap();
+    // conn and row are fine as columns, they are only ever read as fields
+    assert_eq!(
+      model.rule_violations(),
+      vec![
+        "$.tables[0].name: 'conn' clashes with a name in the generated data access code, pick another name",
+        "$.tables[0].columns[0].name: 'try' is a rust keyword, pick another name",
+        "$.tables[0].columns[1].name: 'gen' is a rust keyword, pick another name",
+        "$.tables[1].name: 'row' clashes with a name in the generated data access code, pick another name",
+        "$.tables[2].name: 'box' is a rust keyword, pick another name",
+      ]
+    );
+  }
+}
@@ -0,0 +1,166 @@
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub enum AutoGippityError {
+  // missing or invalid settings, eg. api keys
+  Config(String),
+  // could not reach a server at all
+  Transport(String),
+  // llm provider answered with an error status
+  Provider {
+    status: u16,
+    message: String,
+    retry_after: Option<Duration>,
+  },
+  // llm output or a response body did not have the expected shape
+  Decode(String),
+  // generated code failed to build
+  Build(String),
+  // local failures running the pipeline, eg. io or spawning processes
+  Runtime(String),
+  // the user declined to continue
+  UserAbort(String),
+}
+
+impl AutoGippityError {
+  pub fn kind(&self) -> &'static str {
+    match self {
+      Self::Config(_) => "config",
+      Self::Transport(_) => "transport",
+      Self::Provider { .. } => "provider",
+      Self::Decode(_) => "decode",
+      Self::Build(_) => "build",
+      Self::Runtime(_) => "runtime",
+      Self::UserAbort(_) => "user abort",
+    }
+  }
+
+  // transient failures worth another attempt, auth and config problems are fatal
+  pub fn is_retryable(&self) -> bool {
+    match self {
+      Self::Transport(_) => true,
+      Self::Provider { status, .. } => *status == 408 || *status == 429 || *status >= 500,
+      _ => false,
+    }
+  }
+
+  pub fn retry_after(&self) -> Option<Duration> {
+    match self {
+      Self::Provider { retry_after, .. } => *retry_after,
+      _ => None,
+    }
+  }
+
+  // process exit code reported by main
+  pub fn exit_code(&self) -> i32 {
+    match self {
+      Self::Config(_) => 2,
+      Self::Transport(_) => 3,
+      Self::Provider { .. } => 4,
+      Self::Decode(_) => 5,
+      Self::Build(_) => 6,
+      Self::Runtime(_) => 7,
+      Self::UserAbort(_) => 8,
+    }
+  }
+}
+
+impl fmt::Display for AutoGippityError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Config(msg) => write!(f, "configuration error: {}", msg),
+      Self::Transport(msg) => write!(f, "transport error: {}", msg),
+      Self::Provider {
+        status, message, ..
+      } => {
+        write!(f, "llm provider returned {}: {}", status, message)
+      }
+      Self::Decode(msg) => write!(f, "decode error: {}", msg),
+      Self::Build(msg) => write!(f, "build error: {}", msg),
+      Self::Runtime(msg) => write!(f, "runtime error: {}", msg),
+      Self::UserAbort(msg) => write!(f, "aborted by user: {}", msg),
+    }
+  }
+}
+
+impl std::error::Error for AutoGippityError {}
+
+impl From<reqwest::Error> for AutoGippityError {
+  fn from(e: reqwest::Error) -> Self {
+    if e.is_decode() {
+      return Self::Decode(e.to_string());
+    }
+    match e.status() {
+      Some(status) => Self::Provider {
+        status: status.as_u16(),
+        message: e.to_string(),
+        retry_after: None,
+      },
+      None => Self::Transport(e.to_string()),
+    }
+  }
+}
+
+impl From<serde_json::Error> for AutoGippityError {
+  fn from(e: serde_json::Error) -> Self {
+    Self::Decode(e.to_string())
+  }
+}
+
+impl From<std::io::Error> for AutoGippityError {
+  fn from(e: std::io::Error) -> Self {
+    Self::Runtime(e.to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashSet;
+
+  #[test]
+  fn test_exit_codes_are_distinct() {
+    let errors: Vec<AutoGippityError> = vec![
+      AutoGippityError::Config(String::new()),
+      AutoGippityError::Transport(String::new()),
+      AutoGippityError::Provider {
+        status: 500,
+        message: String::new(),
+        retry_after: None,
+      },
+      AutoGippityError::Decode(String::new()),
+      AutoGippityError::Build(String::new()),
+      AutoGippityError::Runtime(String::new()),
+      AutoGippityError::UserAbort(String::new()),
+    ];
+
+    let codes: HashSet<i32> = errors.iter().map(|e| e.exit_code()).collect();
+    assert_eq!(codes.len(), errors.len());
+    assert!(codes.iter().all(|code| *code != 0 && *code != 1));
+  }
+
+  #[test]
+  fn test_auth_failures_are_fatal() {
+    let provider_err = |status: u16| AutoGippityError::Provider {
+      status,
+      message: String::new(),
+      retry_after: None,
+    };
+
+    assert!(provider_err(429).is_retryable());
+    assert!(provider_err(503).is_retryable());
+    assert!(!provider_err(401).is_retryable());
+    assert!(!provider_err(403).is_retryable());
+    assert!(AutoGippityError::Transport(String::new()).is_retryable());
+    assert!(!AutoGippityError::Config(String::new()).is_retryable());
+    assert!(!AutoGippityError::Decode(String::new()).is_retryable());
+  }
+
+  #[test]
+  fn test_io_errors_become_runtime() {
+    let io_err: std::io::Error = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+    let err: AutoGippityError = io_err.into();
+    assert_eq!(err.kind(), "runtime");
+  }
+}
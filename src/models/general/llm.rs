@@ -1,12 +1,12 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Message {
   pub role: String,
   pub content: String,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatCompletion {
   pub model: String,
   pub messages: Vec<Message>,
@@ -27,3 +27,44 @@ pub struct APIChoice {
 pub struct APIResponse {
   pub choices: Vec<APIChoice>,
 }
+
+// anthropic messages api
+#[derive(Debug, Serialize, Clone)]
+pub struct AnthropicRequest {
+  pub model: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub system: Option<String>,
+  pub messages: Vec<Message>,
+  pub max_tokens: u32,
+  pub temperature: f32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnthropicContent {
+  #[serde(default)]
+  pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnthropicResponse {
+  pub content: Vec<AnthropicContent>,
+}
+
+// ollama chat api
+#[derive(Debug, Serialize, Clone)]
+pub struct OllamaOptions {
+  pub temperature: f32,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct OllamaChatRequest {
+  pub model: String,
+  pub messages: Vec<Message>,
+  pub stream: bool,
+  pub options: OllamaOptions,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OllamaChatResponse {
+  pub message: APIMessage,
+}
@@ -1 +1,5 @@
+pub mod api_types;
+pub mod config;
+pub mod data_model;
+pub mod error;
 pub mod llm;